@@ -0,0 +1,38 @@
+// Move generation speed. Throughput is reported in nodes per second:
+//
+//     cargo bench -p chess
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use chess::perft::perft;
+use chess::rules::Position;
+
+const POSITIONS: [(&str, &str, u32); 3] = [
+    (
+        "start",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        4,
+    ),
+    (
+        "kiwipete",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        3,
+    ),
+    ("endgame", "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 5),
+];
+
+fn perft_benches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("perft");
+    group.sample_size(10);
+    for (name, fen, depth) in POSITIONS {
+        let mut position = Position::from_fen(fen).unwrap();
+        let nodes = perft(&mut position, depth);
+        group.throughput(Throughput::Elements(nodes));
+        group.bench_with_input(BenchmarkId::new(name, depth), &depth, |b, &depth| {
+            b.iter(|| perft(&mut position, depth))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, perft_benches);
+criterion_main!(benches);
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use chess::search::{search, SearchLimits, SearchResult};
+
+use crate::game::{arg_value, ChessGame, Player, Players};
+
+/// Depth and time budget for the built-in computer player, from `--depth N` and `--movetime MS`.
+#[derive(Resource)]
+pub struct AiSettings {
+    pub limits: SearchLimits,
+}
+
+impl AiSettings {
+    pub fn from_args() -> Self {
+        let mut limits = SearchLimits::default();
+        if let Some(depth) = arg_value("--depth").and_then(|value| value.parse().ok()) {
+            limits.max_depth = depth;
+        }
+        if let Some(millis) = arg_value("--movetime").and_then(|value| value.parse().ok()) {
+            limits.time_limit = Some(Duration::from_millis(millis));
+        }
+        AiSettings { limits }
+    }
+}
+
+/// The search running in the background, tagged with the position it was started from.
+#[derive(Resource, Default)]
+pub struct AiSearch {
+    task: Option<(u64, Task<SearchResult>)>,
+}
+
+/// Start a search on the compute pool when it is the computer's turn, so the render loop keeps going.
+pub fn start_ai_search(
+    game: Res<ChessGame>,
+    players: Res<Players>,
+    settings: Res<AiSettings>,
+    mut search_state: ResMut<AiSearch>,
+) {
+    if search_state.task.is_some()
+        || players.get(game.position.side_to_move()) != Player::Computer
+        || !game.is_live()
+    {
+        return;
+    }
+
+    let position = game.position.clone();
+    let limits = settings.limits;
+    let hash = position.zobrist_hash();
+    let task = AsyncComputeTaskPool::get().spawn(async move { search(&position, limits) });
+    search_state.task = Some((hash, task));
+}
+
+/// Play the computer's move once the search is done, unless the position moved on meanwhile,
+/// the side was handed back to a person or the game ended on the clock.
+pub fn finish_ai_search(
+    mut game: ResMut<ChessGame>,
+    players: Res<Players>,
+    mut search_state: ResMut<AiSearch>,
+) {
+    let Some((hash, task)) = search_state.task.as_mut() else {
+        return;
+    };
+    let Some(result) = block_on(future::poll_once(task)) else {
+        return;
+    };
+    let searched = *hash;
+    search_state.task = None;
+
+    if game.position.zobrist_hash() != searched
+        || players.get(game.position.side_to_move()) != Player::Computer
+        || !game.is_live()
+    {
+        return;
+    }
+    if let Some(mv) = result.best_move {
+        info!(
+            "Computer plays after depth {} ({} nodes, score {})",
+            result.depth, result.nodes, result.score
+        );
+        game.play(mv);
+    }
+}
@@ -0,0 +1,33 @@
+// A stand-in UCI engine for tests: answers every `go` with the next move given on its command line.
+//
+//     scripted_uci e7e5 g8f6
+//
+// Once the script runs out it answers `bestmove 0000`.
+use std::io::{self, BufRead, Write};
+
+fn main() {
+    let mut script = std::env::args().skip(1);
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else { break };
+        let command = line.split_whitespace().next().unwrap_or("");
+        let reply = match command {
+            "uci" => "id name Scripted\nid author chess tests\nuciok".to_string(),
+            "isready" => "readyok".to_string(),
+            "go" => format!(
+                "bestmove {}",
+                script.next().unwrap_or_else(|| "0000".to_string())
+            ),
+            "quit" => break,
+            _ => continue,
+        };
+        if writeln!(stdout, "{reply}")
+            .and_then(|_| stdout.flush())
+            .is_err()
+        {
+            break;
+        }
+    }
+}
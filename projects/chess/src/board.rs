@@ -4,6 +4,11 @@ use bevy::{
     prelude::*,
     window::WindowTheme,
 };
+use chess::rules;
+
+use crate::camera::ChessCamera;
+use crate::game::{ChessGame, Player, Players};
+use crate::promotion::PendingPromotion;
 
 #[derive(Component, Debug)]
 pub struct Square {
@@ -15,18 +20,32 @@ impl Square {
     fn is_white(&self) -> bool {
         (self.x + self.y + 1) % 2 == 0
     }
+
+    /// Index of this square in `rules::Position`. `x` runs along the ranks, `y` along the files.
+    pub fn index(&self) -> u8 {
+        rules::square(self.y, self.x)
+    }
+}
+
+/// World position of the centre of a `rules::Position` square.
+pub fn square_translation(sq: u8) -> Vec3 {
+    Vec3::new(rules::rank_of(sq) as f32, 0., rules::file_of(sq) as f32)
 }
 
 #[derive(Resource)]
-struct SelectedSquare {
-    entity: Option<Entity>,
+pub struct SelectedSquare {
+    pub entity: Option<Entity>,
 }
 
-// impl Default for SelectedSquare {
-//     fn default() -> Self {
-//         None
-//     }
-// }
+/// Square materials, swapped onto the square meshes to show the selection and legal targets.
+#[derive(Resource)]
+pub struct SquareMaterials {
+    pub white: Handle<StandardMaterial>,
+    pub black: Handle<StandardMaterial>,
+    pub selected: Handle<StandardMaterial>,
+    pub legal_target: Handle<StandardMaterial>,
+    pub check: Handle<StandardMaterial>,
+}
 
 pub fn create_board(
     mut commands: Commands,
@@ -38,6 +57,13 @@ pub fn create_board(
     let black_material = materials.add(Color::srgb(0., 0.1, 0.1));
 
     commands.insert_resource(SelectedSquare { entity: None });
+    commands.insert_resource(SquareMaterials {
+        white: white_material.clone(),
+        black: black_material.clone(),
+        selected: materials.add(Color::srgb(0.9, 0.7, 0.1)),
+        legal_target: materials.add(Color::srgb(0.3, 0.7, 0.3)),
+        check: materials.add(Color::srgb(0.8, 0.1, 0.1)),
+    });
 
     // Spawn 64 squares
     for i in 0..8 {
@@ -59,32 +85,162 @@ pub fn create_board(
     }
 }
 
+/// A file or rank name drawn over the board edge at `position`.
+#[derive(Component)]
+pub struct BoardLabel {
+    pub position: Vec3,
+}
+
+/// a–h along both edges of the files and 1–8 along both edges of the ranks.
+pub fn spawn_board_labels(mut commands: Commands) {
+    // Half a square beyond the edge squares, whose centres sit at 0 and 7.
+    let (near, far) = (-0.9, 7.9);
+    for i in 0..8u8 {
+        let file = ((b'a' + i) as char).to_string();
+        let rank = (i + 1).to_string();
+        for (text, position) in [
+            (file.clone(), Vec3::new(near, 0., i as f32)),
+            (file.clone(), Vec3::new(far, 0., i as f32)),
+            (rank.clone(), Vec3::new(i as f32, 0., near)),
+            (rank.clone(), Vec3::new(i as f32, 0., far)),
+        ] {
+            commands.spawn((
+                Text::new(text),
+                TextFont {
+                    font_size: 18.0,
+                    ..default()
+                },
+                Node {
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                BoardLabel { position },
+            ));
+        }
+    }
+}
+
+/// Keep the labels over their spot on the board as the camera moves.
+pub fn place_board_labels(
+    cameras: Query<(&Camera, &GlobalTransform), With<ChessCamera>>,
+    mut labels: Query<(&BoardLabel, &mut Node, &ComputedNode)>,
+) {
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+    for (label, mut node, computed) in labels.iter_mut() {
+        let Ok(viewport) = camera.world_to_viewport(camera_transform, label.position) else {
+            continue;
+        };
+        // Centre the text on the point.
+        let corner = viewport - computed.size() * computed.inverse_scale_factor() / 2.0;
+        let (left, top) = (Val::Px(corner.x), Val::Px(corner.y));
+        if node.left != left || node.top != top {
+            node.left = left;
+            node.top = top;
+        }
+    }
+}
+
+/// Two-click move input: the first click on a square selects the piece on it,
+/// the second click plays the move if it is legal.
+pub fn select_square(
+    buttons: Res<ButtonInput<MouseButton>>,
+    pointers: Query<&PointerInteraction>,
+    squares: Query<&Square>,
+    mut selected: ResMut<SelectedSquare>,
+    mut game: ResMut<ChessGame>,
+    players: Res<Players>,
+    mut promotion: ResMut<PendingPromotion>,
+) {
+    if !buttons.just_pressed(MouseButton::Left) || promotion.squares.is_some() {
+        return;
+    }
+    if players.get(game.position.side_to_move()) != Player::Human || !game.accepts_moves() {
+        return;
+    }
+
+    let Some((clicked_entity, clicked)) = pointers
+        .iter()
+        .filter_map(|interaction| interaction.get_nearest_hit())
+        .find_map(|(entity, _)| squares.get(*entity).ok().map(|square| (*entity, square)))
+    else {
+        return;
+    };
+    let target = clicked.index();
+
+    if let Some(from) = selected.entity.and_then(|entity| squares.get(entity).ok()) {
+        let from = from.index();
+        let chosen = game
+            .position
+            .legal_moves_from(from)
+            .into_iter()
+            .find(|&mv| mv.to == target || game.position.castling_rook_of(mv) == Some(target));
+        if let Some(mv) = chosen {
+            // Promotions wait for the chooser to pick the piece.
+            if mv.promotion.is_some() {
+                promotion.squares = Some((from, mv.to));
+            } else {
+                game.play(mv);
+            }
+            selected.entity = None;
+            return;
+        }
+    }
+
+    let side_to_move = game.position.side_to_move();
+    selected.entity = match game.position.piece_at(target) {
+        Some(piece) if piece.color == side_to_move && selected.entity != Some(clicked_entity) => {
+            Some(clicked_entity)
+        }
+        _ => None,
+    };
+}
+
+/// Swap square materials to show the selected square, where its piece can legally go,
+/// and the king of the side to move when it is in check.
 pub fn color_squares(
-    mut interaction_query: Query<(&Interaction), Changed<Interaction>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    selected: Res<SelectedSquare>,
+    game: Res<ChessGame>,
+    square_materials: Res<SquareMaterials>,
+    mut squares: Query<(Entity, &Square, &mut MeshMaterial3d<StandardMaterial>)>,
 ) {
-    for (interaction) in interaction_query.iter_mut() {
-        println!("Interaction: {:?} ", interaction);
-        // match *interaction {
-        //     Interaction::Pressed => {
-        //         // Change the material color to red when clicked
-        //         if let Some(mat) = materials.get_mut(&*material) {
-        //             mat.base_color = Color::rgb(1.0, 0.0, 0.0);
-        //         }
-        //     }
-        //     Interaction::Hovered => {
-        //         // Optional: Change the material color to yellow when hovered
-        //         if let Some(mat) = materials.get_mut(&*material) {
-        //             mat.base_color = Color::rgb(1.0, 1.0, 0.0);
-        //         }
-        //     }
-        //     Interaction::None => {
-        //         // Optional: Reset the material color to green when not hovered
-        //         if let Some(mat) = materials.get_mut(&*material) {
-        //             mat.base_color = Color::rgb(0.3, 0.5, 0.3);
-        //         }
-        //     }
-        // }
+    if !selected.is_changed() && !game.is_changed() {
+        return;
+    }
+
+    let targets: Vec<u8> = selected
+        .entity
+        .and_then(|entity| squares.get(entity).ok())
+        .map(|(_, square, _)| {
+            game.position
+                .legal_moves_from(square.index())
+                .iter()
+                .map(|mv| mv.to)
+                .collect()
+        })
+        .unwrap_or_default();
+    let checked_king = game
+        .position
+        .in_check()
+        .then(|| game.position.king_square(game.position.side_to_move()))
+        .flatten();
+
+    for (entity, square, mut material) in squares.iter_mut() {
+        let wanted = if selected.entity == Some(entity) {
+            &square_materials.selected
+        } else if targets.contains(&square.index()) {
+            &square_materials.legal_target
+        } else if checked_king == Some(square.index()) {
+            &square_materials.check
+        } else if square.is_white() {
+            &square_materials.white
+        } else {
+            &square_materials.black
+        };
+        if material.0 != *wanted {
+            material.0 = wanted.clone();
+        }
     }
 }
 
@@ -101,11 +257,4 @@ pub fn draw_mesh_intersections(pointers: Query<&PointerInteraction>, mut gizmos:
             Color::srgb(0.0, 1.0, 0.0),
         );
     }
-
-    for (entity, hitData) in pointers
-        .iter()
-        .filter_map(|interaction| interaction.get_nearest_hit())
-    {}
-
-    // let (target_entity, hit) = pointers.iter().next().unwrap();
 }
@@ -0,0 +1,202 @@
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use bevy::input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll};
+use bevy::prelude::*;
+use chess::rules::PieceColor;
+
+use crate::game::{ChessGame, Player, Players};
+
+/// The middle of the board in world space.
+const BOARD_CENTER: Vec3 = Vec3::new(3.5, 0.0, 3.5);
+
+/// Seconds a switch between views takes.
+const SWITCH_SECONDS: f32 = 0.6;
+
+#[derive(Component)]
+pub struct ChessCamera;
+
+/// A camera position on a sphere around the board centre.
+///
+/// `yaw` 0 looks from White's side (low ranks, negative x) with the a-file on the left;
+/// `yaw` PI looks from Black's side.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Orbit {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+}
+
+impl Orbit {
+    fn side(color: PieceColor) -> Self {
+        Orbit {
+            yaw: Orbit::yaw_of(color),
+            pitch: 0.9,
+            distance: 12.0,
+        }
+    }
+
+    fn top_down(color: PieceColor) -> Self {
+        Orbit {
+            yaw: Orbit::yaw_of(color),
+            // Just short of straight down, so "up" on screen still points away from `color`.
+            pitch: FRAC_PI_2 - 0.001,
+            distance: 12.0,
+        }
+    }
+
+    fn yaw_of(color: PieceColor) -> f32 {
+        match color {
+            PieceColor::White => 0.0,
+            PieceColor::Black => PI,
+        }
+    }
+
+    pub fn transform(&self) -> Transform {
+        let offset = Vec3::new(
+            -self.pitch.cos() * self.yaw.cos(),
+            self.pitch.sin(),
+            -self.pitch.cos() * self.yaw.sin(),
+        );
+        Transform::from_translation(BOARD_CENTER + offset * self.distance)
+            .looking_at(BOARD_CENTER, Vec3::Y)
+    }
+
+    /// Blend towards `to`, turning the short way round.
+    fn lerp(&self, to: &Orbit, t: f32) -> Orbit {
+        let turn = (to.yaw - self.yaw + PI).rem_euclid(TAU) - PI;
+        Orbit {
+            yaw: self.yaw + turn * t,
+            pitch: self.pitch + (to.pitch - self.pitch) * t,
+            distance: self.distance + (to.distance - self.distance) * t,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CameraPreset {
+    WhiteSide,
+    BlackSide,
+    TopDown,
+    /// Right-drag to orbit, scroll to zoom.
+    FreeOrbit,
+}
+
+/// Where the camera is, where it is heading and which preset it follows.
+#[derive(Resource)]
+pub struct CameraRig {
+    pub preset: CameraPreset,
+    /// The side the board faces in the side and top-down presets.
+    pub facing: PieceColor,
+    /// Turn the board to the side to move when both sides are human.
+    pub auto_flip: bool,
+    current: Orbit,
+    from: Orbit,
+    to: Orbit,
+    timer: Timer,
+}
+
+impl Default for CameraRig {
+    fn default() -> Self {
+        let orbit = Orbit::side(PieceColor::White);
+        let mut timer = Timer::from_seconds(SWITCH_SECONDS, TimerMode::Once);
+        timer.tick(timer.duration());
+        CameraRig {
+            preset: CameraPreset::WhiteSide,
+            facing: PieceColor::White,
+            auto_flip: true,
+            current: orbit,
+            from: orbit,
+            to: orbit,
+            timer,
+        }
+    }
+}
+
+impl CameraRig {
+    pub fn transform(&self) -> Transform {
+        self.current.transform()
+    }
+
+    /// Start an animated move to wherever the preset and facing put the camera.
+    fn retarget(&mut self) {
+        let to = match self.preset {
+            CameraPreset::WhiteSide | CameraPreset::BlackSide => Orbit::side(self.facing),
+            CameraPreset::TopDown => Orbit::top_down(self.facing),
+            CameraPreset::FreeOrbit => return,
+        };
+        if to != self.to {
+            self.from = self.current;
+            self.to = to;
+            self.timer.reset();
+        }
+    }
+}
+
+/// F1 white side, F2 black side, F3 top-down, F4 free orbit; F5 toggles auto-flip.
+pub fn switch_camera(
+    keys: Res<ButtonInput<KeyCode>>,
+    game: Res<ChessGame>,
+    players: Res<Players>,
+    mut rig: ResMut<CameraRig>,
+) {
+    let preset = [
+        (KeyCode::F1, CameraPreset::WhiteSide),
+        (KeyCode::F2, CameraPreset::BlackSide),
+        (KeyCode::F3, CameraPreset::TopDown),
+        (KeyCode::F4, CameraPreset::FreeOrbit),
+    ]
+    .into_iter()
+    .find(|(key, _)| keys.just_pressed(*key))
+    .map(|(_, preset)| preset);
+    if let Some(preset) = preset {
+        rig.preset = preset;
+        match preset {
+            CameraPreset::WhiteSide => rig.facing = PieceColor::White,
+            CameraPreset::BlackSide => rig.facing = PieceColor::Black,
+            CameraPreset::TopDown | CameraPreset::FreeOrbit => {}
+        }
+        rig.retarget();
+    }
+    if keys.just_pressed(KeyCode::F5) {
+        rig.auto_flip = !rig.auto_flip;
+        info!("Auto-flip {}", if rig.auto_flip { "on" } else { "off" });
+    }
+
+    let hot_seat = players.white == Player::Human && players.black == Player::Human;
+    if rig.auto_flip && hot_seat && game.is_changed() {
+        let side = game.position.side_to_move();
+        if rig.facing != side {
+            rig.facing = side;
+            rig.retarget();
+        }
+    }
+}
+
+/// Ease the camera towards its target view, or follow the mouse in free orbit.
+pub fn move_camera(
+    time: Res<Time>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    motion: Res<AccumulatedMouseMotion>,
+    scroll: Res<AccumulatedMouseScroll>,
+    mut rig: ResMut<CameraRig>,
+    mut cameras: Query<&mut Transform, With<ChessCamera>>,
+) {
+    let rig = &mut *rig;
+    if !rig.timer.finished() {
+        rig.timer.tick(time.delta());
+        let t = rig.timer.fraction();
+        rig.current = rig.from.lerp(&rig.to, t * t * (3.0 - 2.0 * t));
+    } else if rig.preset == CameraPreset::FreeOrbit {
+        if buttons.pressed(MouseButton::Right) {
+            rig.current.yaw += motion.delta.x * 0.005;
+            rig.current.pitch = (rig.current.pitch + motion.delta.y * 0.005).clamp(0.1, 1.5);
+        }
+        rig.current.distance = (rig.current.distance - scroll.delta.y).clamp(5.0, 25.0);
+    } else {
+        return;
+    }
+
+    for mut transform in cameras.iter_mut() {
+        *transform = rig.transform();
+    }
+}
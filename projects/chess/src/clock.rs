@@ -0,0 +1,147 @@
+// Chess clocks. Time is fed in explicitly, so the clock runs off whatever time source the caller uses.
+use std::fmt;
+use std::time::Duration;
+
+use crate::rules::PieceColor;
+
+/// Time added back after each move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bonus {
+    None,
+    /// Fischer: the full increment is added after every move.
+    Increment(Duration),
+    /// Bronstein: the time used on the move is given back, up to the delay.
+    Delay(Duration),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub bonus: Bonus,
+}
+
+impl TimeControl {
+    pub fn classical() -> Self {
+        TimeControl::minutes(90, Bonus::Increment(Duration::from_secs(30)))
+    }
+
+    pub fn rapid() -> Self {
+        TimeControl::minutes(15, Bonus::Increment(Duration::from_secs(10)))
+    }
+
+    pub fn blitz() -> Self {
+        TimeControl::minutes(5, Bonus::None)
+    }
+
+    fn minutes(minutes: u64, bonus: Bonus) -> Self {
+        TimeControl {
+            base: Duration::from_secs(minutes * 60),
+            bonus,
+        }
+    }
+
+    /// `classical`, `rapid`, `blitz`, or `<minutes>` optionally followed by `+<seconds>` for a
+    /// Fischer increment or `+d<seconds>` for a Bronstein delay, e.g. `3+2` or `5+d3`.
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim() {
+            "classical" => return Some(TimeControl::classical()),
+            "rapid" => return Some(TimeControl::rapid()),
+            "blitz" => return Some(TimeControl::blitz()),
+            _ => {}
+        }
+        let (minutes, bonus) = match text.trim().split_once('+') {
+            Some((minutes, bonus)) => (minutes, Some(bonus)),
+            None => (text.trim(), None),
+        };
+        let minutes: u64 = minutes.parse().ok()?;
+        let bonus = match bonus {
+            None => Bonus::None,
+            Some(bonus) => match bonus.strip_prefix('d') {
+                Some(delay) => Bonus::Delay(Duration::from_secs(delay.parse().ok()?)),
+                None => Bonus::Increment(Duration::from_secs(bonus.parse().ok()?)),
+            },
+        };
+        Some(TimeControl::minutes(minutes, bonus))
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.base.as_secs() / 60)?;
+        match self.bonus {
+            Bonus::None => Ok(()),
+            Bonus::Increment(increment) => write!(f, "+{}", increment.as_secs()),
+            Bonus::Delay(delay) => write!(f, "+d{}", delay.as_secs()),
+        }
+    }
+}
+
+/// Both players' remaining time.
+///
+/// The owner calls `tick` with the time that passed for the side to move and `press` when that
+/// side completes a move. A side whose time runs out is flagged and its clock stops at zero.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChessClock {
+    pub control: TimeControl,
+    remaining: [Duration; 2],
+    /// Time the side to move has used on the current move, for the Bronstein delay.
+    spent: Duration,
+    flagged: Option<PieceColor>,
+}
+
+impl ChessClock {
+    pub fn new(control: TimeControl) -> Self {
+        ChessClock {
+            control,
+            remaining: [control.base; 2],
+            spent: Duration::ZERO,
+            flagged: None,
+        }
+    }
+
+    pub fn remaining(&self, color: PieceColor) -> Duration {
+        self.remaining[color.index()]
+    }
+
+    /// The side whose time ran out, if any.
+    pub fn flagged(&self) -> Option<PieceColor> {
+        self.flagged
+    }
+
+    /// Run `color`'s clock for `elapsed`.
+    pub fn tick(&mut self, color: PieceColor, elapsed: Duration) {
+        if self.flagged.is_some() {
+            return;
+        }
+        let remaining = &mut self.remaining[color.index()];
+        *remaining = remaining.saturating_sub(elapsed);
+        self.spent += elapsed;
+        if remaining.is_zero() {
+            self.flagged = Some(color);
+        }
+    }
+
+    /// `color` finished a move: add its bonus and start timing the next move afresh.
+    pub fn press(&mut self, color: PieceColor) {
+        if self.flagged.is_some() {
+            return;
+        }
+        let bonus = match self.control.bonus {
+            Bonus::None => Duration::ZERO,
+            Bonus::Increment(increment) => increment,
+            Bonus::Delay(delay) => delay.min(self.spent),
+        };
+        self.remaining[color.index()] += bonus;
+        self.spent = Duration::ZERO;
+    }
+}
+
+/// `m:ss`, with tenths once under ten seconds.
+pub fn format_clock(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds < 10 {
+        format!("0:{seconds:02}.{}", time.subsec_millis() / 100)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
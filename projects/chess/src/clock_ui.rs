@@ -0,0 +1,329 @@
+use bevy::prelude::*;
+use chess::clock::{format_clock, ChessClock, TimeControl};
+use chess::rules::{Move, PieceColor, Position};
+
+use crate::game::{arg_value, ChessGame};
+
+/// The game clocks, if the game was started with `--clock <control>`
+/// (`classical`, `rapid`, `blitz`, `3+2` for a Fischer increment, `5+d3` for a Bronstein delay).
+///
+/// The clock is kept as it stood at the start of every ply of the record, before the side to
+/// move started thinking, so taking a move back also gives back the time spent on it. A
+/// separate clock runs for the ply the board is showing.
+#[derive(Resource)]
+pub struct GameClock {
+    pub control: Option<TimeControl>,
+    snapshots: Vec<ChessClock>,
+    /// The running clock for the end of the record, kept while stepping back so that redoing
+    /// the moves finds it as it was.
+    live: Option<ChessClock>,
+    /// The running clock for a ply behind the end of the record, charged to the side to move
+    /// there if they play a different move.
+    branch: Option<(usize, ChessClock)>,
+    /// The game the snapshots belong to, to spot new moves, takebacks and new games.
+    start: Position,
+    moves: Vec<Move>,
+}
+
+impl GameClock {
+    pub fn from_args() -> Self {
+        let control = arg_value("--clock").and_then(|text| {
+            let control = TimeControl::parse(&text);
+            if control.is_none() {
+                error!("unknown time control {text:?}, playing without clocks");
+            }
+            control
+        });
+        GameClock {
+            control,
+            snapshots: control.map(ChessClock::new).into_iter().collect(),
+            live: control.map(ChessClock::new),
+            branch: None,
+            start: Position::starting(),
+            moves: Vec::new(),
+        }
+    }
+
+    /// The running clock for the ply the board was showing when the game last changed.
+    pub fn running(&self) -> Option<&ChessClock> {
+        match &self.branch {
+            Some((_, clock)) => Some(clock),
+            None => self.live.as_ref(),
+        }
+    }
+
+    fn running_mut(&mut self) -> Option<&mut ChessClock> {
+        match &mut self.branch {
+            Some((_, clock)) => Some(clock),
+            None => self.live.as_mut(),
+        }
+    }
+
+    /// Bring the clocks in line with the game record: moves that were taken back and replaced
+    /// are dropped, each new move presses the clock of the side that played it, and stepping
+    /// through the record picks up the clock of the ply the board is showing.
+    fn follow(&mut self, game: &ChessGame) {
+        let Some(control) = self.control else {
+            return;
+        };
+        let moves: Vec<Move> = game.history.iter().map(|played| played.mv).collect();
+        if game.start != self.start {
+            self.start = game.start.clone();
+            self.moves.clear();
+            self.snapshots = vec![ChessClock::new(control)];
+            self.live = Some(ChessClock::new(control));
+            self.branch = None;
+        }
+        let common = moves
+            .iter()
+            .zip(&self.moves)
+            .take_while(|(new, old)| new == old)
+            .count();
+        if common < moves.len() {
+            // The first new move was thought about on the clock running where it was played.
+            let mut next = match self.branch.take() {
+                Some((ply, clock)) if ply == common => clock,
+                _ if common == self.moves.len() => self
+                    .live
+                    .take()
+                    .unwrap_or_else(|| self.snapshots[common].clone()),
+                _ => self.snapshots[common].clone(),
+            };
+            self.snapshots.truncate(common + 1);
+            for ply in common..moves.len() {
+                next.press(self.mover(ply));
+                self.snapshots.push(next.clone());
+            }
+            self.live = Some(next);
+        } else if common < self.moves.len() {
+            // Moves were dropped from the record without a new one: start over from there.
+            self.snapshots.truncate(common + 1);
+            self.live = Some(self.snapshots[common].clone());
+        }
+        self.moves = moves;
+
+        self.branch = match self.branch.take() {
+            Some((ply, clock)) if ply == game.cursor => Some((ply, clock)),
+            _ if game.cursor < self.moves.len() => {
+                Some((game.cursor, self.snapshots[game.cursor].clone()))
+            }
+            _ => None,
+        };
+    }
+
+    fn mover(&self, ply: usize) -> PieceColor {
+        if ply.is_multiple_of(2) {
+            self.start.side_to_move()
+        } else {
+            self.start.side_to_move().opposite()
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct ClockText;
+
+pub fn spawn_clocks(mut commands: Commands, clock: Res<GameClock>) {
+    if clock.control.is_none() {
+        return;
+    }
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 28.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            ..default()
+        },
+        ClockText,
+    ));
+}
+
+/// Run the side to move's clock while the game is going, press it after each new move and
+/// flag the game lost when a clock runs out at the end of the record. Time spent after
+/// stepping back only counts if a different move is played there; redoing the moves finds
+/// the clocks as they were. A finished game pauses the clocks, and a new or loaded game
+/// resets them.
+pub fn run_clocks(time: Res<Time>, mut game: ResMut<ChessGame>, mut clock: ResMut<GameClock>) {
+    if game.is_changed() {
+        clock.follow(&game);
+    }
+    if !game.accepts_moves() {
+        return;
+    }
+    let Some(running) = clock.running_mut() else {
+        return;
+    };
+    let side = game.position.side_to_move();
+    running.tick(side, time.delta());
+    if let Some(color) = running.flagged().filter(|_| game.is_live()) {
+        game.flag(color);
+    }
+}
+
+/// Show the clocks for the move the board is showing.
+pub fn update_clocks(clock: Res<GameClock>, mut text: Query<&mut Text, With<ClockText>>) {
+    let (Some(chess_clock), Ok(mut text)) = (clock.running(), text.get_single_mut()) else {
+        return;
+    };
+    let line = |color: PieceColor| {
+        let remaining = chess_clock.remaining(color);
+        let flag = if chess_clock.flagged() == Some(color) {
+            " flag"
+        } else {
+            ""
+        };
+        format!("{color:?} {}{flag}", format_clock(remaining))
+    };
+    let clocks = format!("{}\n{}", line(PieceColor::White), line(PieceColor::Black));
+    if text.0 != clocks {
+        text.0 = clocks;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chess::outcome::Outcome;
+    use chess::rules::parse_square;
+
+    use super::*;
+
+    fn clocked(control: &str) -> World {
+        let control = TimeControl::parse(control).unwrap();
+        let mut world = World::new();
+        world.insert_resource(Time::<()>::default());
+        world.insert_resource(ChessGame::default());
+        world.insert_resource(GameClock {
+            control: Some(control),
+            snapshots: vec![ChessClock::new(control)],
+            live: Some(ChessClock::new(control)),
+            branch: None,
+            start: Position::starting(),
+            moves: Vec::new(),
+        });
+        world
+    }
+
+    /// Let `seconds` pass on the clock of the side to move.
+    fn wait(world: &mut World, schedule: &mut Schedule, seconds: u64) {
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(seconds));
+        schedule.run(world);
+    }
+
+    fn play(world: &mut World, name: &str) {
+        let mut game = world.resource_mut::<ChessGame>();
+        let from = parse_square(&name[..2]).unwrap();
+        let to = parse_square(&name[2..]).unwrap();
+        let mv = game
+            .position
+            .legal_moves()
+            .into_iter()
+            .find(|mv| mv.from == from && mv.to == to)
+            .unwrap();
+        game.play(mv);
+    }
+
+    #[test]
+    fn a_move_after_the_flag_falls_does_not_save_the_game() {
+        let mut world = clocked("1+0");
+        let mut schedule = Schedule::default();
+        schedule.add_systems(run_clocks);
+        play(&mut world, "e2e4");
+        wait(&mut world, &mut schedule, 10);
+        wait(&mut world, &mut schedule, 61);
+        let lost = Some(Outcome::Timeout {
+            winner: PieceColor::White,
+        });
+        assert_eq!(world.resource::<ChessGame>().outcome(), lost);
+
+        // A move that was already on its way lands anyway.
+        play(&mut world, "e7e5");
+        wait(&mut world, &mut schedule, 1);
+        let game = world.resource::<ChessGame>();
+        assert_eq!(game.outcome(), lost);
+        assert!(!game.is_live() && !game.accepts_moves());
+    }
+
+    #[test]
+    fn a_new_line_from_before_the_flag_clears_it() {
+        let mut world = clocked("1+0");
+        let mut schedule = Schedule::default();
+        schedule.add_systems(run_clocks);
+        play(&mut world, "e2e4");
+        wait(&mut world, &mut schedule, 61);
+        assert!(world.resource::<ChessGame>().outcome().is_some());
+
+        world.resource_mut::<ChessGame>().step_back();
+        assert!(world.resource::<ChessGame>().accepts_moves());
+        play(&mut world, "d2d4");
+        assert_eq!(world.resource::<ChessGame>().outcome(), None);
+    }
+
+    fn remaining(world: &World) -> (Duration, Duration) {
+        let clock = world.resource::<GameClock>().running().unwrap();
+        (
+            clock.remaining(PieceColor::White),
+            clock.remaining(PieceColor::Black),
+        )
+    }
+
+    #[test]
+    fn undo_and_redo_restore_the_clocks_exactly() {
+        let mut world = clocked("5+2");
+        let mut schedule = Schedule::default();
+        schedule.add_systems(run_clocks);
+        wait(&mut world, &mut schedule, 10);
+        play(&mut world, "e2e4");
+        wait(&mut world, &mut schedule, 20);
+        play(&mut world, "e7e5");
+        wait(&mut world, &mut schedule, 30);
+        let before = world.resource::<GameClock>().running().cloned();
+        assert_eq!(
+            remaining(&world),
+            (Duration::from_secs(262), Duration::from_secs(282))
+        );
+
+        // Back to before White's move, with the ten seconds spent on it given back.
+        world.resource_mut::<ChessGame>().step_back();
+        world.resource_mut::<ChessGame>().step_back();
+        wait(&mut world, &mut schedule, 0);
+        assert_eq!(
+            remaining(&world),
+            (Duration::from_secs(300), Duration::from_secs(300))
+        );
+        wait(&mut world, &mut schedule, 5);
+
+        world.resource_mut::<ChessGame>().step_forward();
+        world.resource_mut::<ChessGame>().step_forward();
+        wait(&mut world, &mut schedule, 0);
+        assert_eq!(world.resource::<GameClock>().running().cloned(), before);
+    }
+
+    #[test]
+    fn thinking_after_a_takeback_is_charged_to_the_new_move() {
+        let mut world = clocked("5+0");
+        let mut schedule = Schedule::default();
+        schedule.add_systems(run_clocks);
+        wait(&mut world, &mut schedule, 10);
+        play(&mut world, "e2e4");
+        wait(&mut world, &mut schedule, 20);
+
+        world.resource_mut::<ChessGame>().step_back();
+        wait(&mut world, &mut schedule, 0);
+        wait(&mut world, &mut schedule, 40);
+        play(&mut world, "d2d4");
+        wait(&mut world, &mut schedule, 0);
+        assert_eq!(
+            remaining(&world),
+            (Duration::from_secs(260), Duration::from_secs(300))
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Mutex;
+use std::thread;
+
+use bevy::prelude::*;
+use chess::rules::Move;
+use chess::uci::{move_from_uci, UciEngine};
+
+use crate::ai::AiSettings;
+use crate::game::{arg_value, ChessGame, Player, Players};
+
+type EngineReply = (Option<UciEngine>, Result<Option<String>, String>);
+
+/// An external UCI engine given with `--engine <path>`, playing for whichever color is set to `engine`.
+///
+/// The process is started on first use. While it thinks it lives on a thread of its own, which
+/// sends it back with the reply; a long think would otherwise hold up a task pool thread the
+/// built-in search needs.
+#[derive(Resource, Default)]
+pub struct EngineBridge {
+    pub path: Option<String>,
+    engine: Option<UciEngine>,
+    task: Option<(u64, Mutex<Receiver<EngineReply>>)>,
+    /// The position the engine last had no usable move for; not asked again until it changes.
+    refused: Option<u64>,
+}
+
+impl EngineBridge {
+    pub fn from_args() -> Self {
+        EngineBridge {
+            path: arg_value("--engine"),
+            ..default()
+        }
+    }
+}
+
+pub fn start_engine_search(
+    game: Res<ChessGame>,
+    players: Res<Players>,
+    settings: Res<AiSettings>,
+    mut bridge: ResMut<EngineBridge>,
+) {
+    if bridge.task.is_some()
+        || players.get(game.position.side_to_move()) != Player::Engine
+        || !game.is_live()
+    {
+        return;
+    }
+    let Some(path) = bridge.path.clone() else {
+        return;
+    };
+    let hash = game.position.zobrist_hash();
+    if bridge.refused == Some(hash) {
+        return;
+    }
+
+    let start = game.start.clone();
+    let moves: Vec<Move> = game.history[..game.cursor]
+        .iter()
+        .map(|played| played.mv)
+        .collect();
+    let limits = settings.limits;
+    let engine = bridge.engine.take();
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let reply = match engine {
+            Some(engine) => Ok(engine),
+            None => {
+                UciEngine::start(&path, &[]).map_err(|err| format!("could not start {path}: {err}"))
+            }
+        }
+        .and_then(|mut engine| {
+            engine
+                .set_chess960(start.has_chess960_castling())
+                .and_then(|()| engine.best_move(&start, &moves, limits))
+                .map(|best| (engine, best))
+                .map_err(|err| format!("engine stopped responding: {err}"))
+        });
+        let _ = sender.send(match reply {
+            Ok((engine, best)) => (Some(engine), Ok(best)),
+            Err(err) => (None, Err(err)),
+        });
+    });
+    bridge.task = Some((hash, Mutex::new(receiver)));
+}
+
+/// Play the engine's move after checking it against our own legal moves.
+pub fn finish_engine_search(
+    mut game: ResMut<ChessGame>,
+    mut players: ResMut<Players>,
+    mut bridge: ResMut<EngineBridge>,
+) {
+    let Some((hash, receiver)) = bridge.task.as_mut() else {
+        return;
+    };
+    let searched = *hash;
+    let (engine, reply) = match receiver
+        .get_mut()
+        .expect("engine mutex poisoned")
+        .try_recv()
+    {
+        Ok(reply) => reply,
+        Err(TryRecvError::Empty) => return,
+        Err(TryRecvError::Disconnected) => (None, Err("the engine thread stopped".to_string())),
+    };
+    bridge.task = None;
+    bridge.engine = engine;
+
+    let side = game.position.side_to_move();
+    let best = match reply {
+        Ok(best) => best,
+        Err(err) => {
+            error!("{err}; {side:?} goes back to a human player");
+            *players.get_mut(side) = Player::Human;
+            return;
+        }
+    };
+    if game.position.zobrist_hash() != searched
+        || players.get(side) != Player::Engine
+        || !game.is_live()
+    {
+        return;
+    }
+    let Some(best) = best else {
+        error!("Engine found no move for {side:?} in a live position");
+        bridge.refused = Some(searched);
+        return;
+    };
+    match move_from_uci(&game.position, &best) {
+        Some(mv) => game.play(mv),
+        None => {
+            error!("Engine sent illegal move {best}; {side:?} goes back to a human player");
+            *players.get_mut(side) = Player::Human;
+            bridge.refused = Some(searched);
+        }
+    }
+}
@@ -0,0 +1,337 @@
+// Forsyth-Edwards Notation import and export for `Position`.
+use std::fmt;
+
+use crate::rules::{
+    file_of, parse_square, rank_of, square, square_name, CastleSide, CastlingRights, Piece,
+    PieceColor, PieceKind, Position,
+};
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FenError(pub String);
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid FEN: {}", self.0)
+    }
+}
+
+impl std::error::Error for FenError {}
+
+fn error(message: impl Into<String>) -> FenError {
+    FenError(message.into())
+}
+
+fn piece_from_char(c: char) -> Option<Piece> {
+    let color = if c.is_ascii_uppercase() {
+        PieceColor::White
+    } else {
+        PieceColor::Black
+    };
+    let kind = match c.to_ascii_lowercase() {
+        'p' => PieceKind::Pawn,
+        'n' => PieceKind::Knight,
+        'b' => PieceKind::Bishop,
+        'r' => PieceKind::Rook,
+        'q' => PieceKind::Queen,
+        'k' => PieceKind::King,
+        _ => return None,
+    };
+    Some(Piece::new(color, kind))
+}
+
+pub fn piece_to_char(piece: Piece) -> char {
+    let c = match piece.kind {
+        PieceKind::Pawn => 'p',
+        PieceKind::Knight => 'n',
+        PieceKind::Bishop => 'b',
+        PieceKind::Rook => 'r',
+        PieceKind::Queen => 'q',
+        PieceKind::King => 'k',
+    };
+    match piece.color {
+        PieceColor::White => c.to_ascii_uppercase(),
+        PieceColor::Black => c,
+    }
+}
+
+impl Position {
+    pub fn from_fen(fen: &str) -> Result<Position, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 4 {
+            return Err(error("expected at least 4 fields"));
+        }
+        let mut position = Position::empty();
+
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(error("expected 8 ranks"));
+        }
+        for (i, rank_text) in ranks.iter().enumerate() {
+            let rank = 7 - i as u8;
+            let mut file = 0u8;
+            for c in rank_text.chars() {
+                if let Some(skip) = c.to_digit(10) {
+                    if !(1..=8).contains(&skip) {
+                        return Err(error(format!("bad empty square count '{c}'")));
+                    }
+                    file += skip as u8;
+                    if file > 8 {
+                        return Err(error(format!("rank {} is too long", rank + 1)));
+                    }
+                } else {
+                    let piece =
+                        piece_from_char(c).ok_or_else(|| error(format!("unknown piece '{c}'")))?;
+                    if file >= 8 {
+                        return Err(error(format!("rank {} is too long", rank + 1)));
+                    }
+                    position.set_piece(square(file, rank), Some(piece));
+                    file += 1;
+                }
+            }
+            if file != 8 {
+                return Err(error(format!("rank {} does not have 8 files", rank + 1)));
+            }
+        }
+
+        for color in [PieceColor::White, PieceColor::Black] {
+            let kings = position
+                .pieces()
+                .filter(|(_, piece)| *piece == Piece::new(color, PieceKind::King))
+                .count();
+            if kings != 1 {
+                return Err(error(format!("{color:?} must have exactly one king")));
+            }
+        }
+
+        position.set_side_to_move(match fields[1] {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            other => return Err(error(format!("unknown side to move '{other}'"))),
+        });
+
+        position.set_castling(parse_castling(&position, fields[2])?);
+
+        let en_passant = match fields[3] {
+            "-" => None,
+            name => Some(
+                parse_square(name)
+                    .ok_or_else(|| error(format!("bad en passant square '{name}'")))?,
+            ),
+        };
+        position.set_en_passant(en_passant.filter(|&sq| can_capture_en_passant(&position, sq)));
+
+        if let Some(clock) = fields.get(4) {
+            position.set_halfmove_clock(
+                clock
+                    .parse()
+                    .map_err(|_| error(format!("bad halfmove clock '{clock}'")))?,
+            );
+        }
+        if let Some(number) = fields.get(5) {
+            position.set_fullmove_number(
+                number
+                    .parse()
+                    .map_err(|_| error(format!("bad fullmove number '{number}'")))?,
+            );
+        }
+
+        Ok(position)
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut placement = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.piece_at(square(file, rank)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        placement.push(piece_to_char(piece));
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                placement.push('/');
+            }
+        }
+
+        let side = match self.side_to_move() {
+            PieceColor::White => "w",
+            PieceColor::Black => "b",
+        };
+        let en_passant = self
+            .en_passant()
+            .map(square_name)
+            .unwrap_or_else(|| "-".to_string());
+
+        format!(
+            "{} {} {} {} {} {}",
+            placement,
+            side,
+            castling_to_string(self),
+            en_passant,
+            self.halfmove_clock(),
+            self.fullmove_number()
+        )
+    }
+}
+
+/// `K`/`Q` name the rook furthest from the king on that side, as in X-FEN; a file letter
+/// (`A`-`H` for White, `a`-`h` for Black) names the rook on that file, as in Shredder-FEN.
+fn parse_castling(position: &Position, text: &str) -> Result<CastlingRights, FenError> {
+    let mut rights = CastlingRights::default();
+    if text == "-" {
+        return Ok(rights);
+    }
+    for c in text.chars() {
+        let color = if c.is_ascii_uppercase() {
+            PieceColor::White
+        } else {
+            PieceColor::Black
+        };
+        let (side, rook_file) = match c.to_ascii_lowercase() {
+            'k' => (
+                CastleSide::King,
+                outermost_rook_file(position, color, CastleSide::King),
+            ),
+            'q' => (
+                CastleSide::Queen,
+                outermost_rook_file(position, color, CastleSide::Queen),
+            ),
+            file @ 'a'..='h' => {
+                let file = file as u8 - b'a';
+                let rook = Some(Piece::new(color, PieceKind::Rook));
+                let king_file = position
+                    .king_square(color)
+                    .filter(|&king| rank_of(king) == color.back_rank())
+                    .map(file_of)
+                    .ok_or_else(|| error(format!("castling right '{c}' without a king")))?;
+                let side = if file > king_file {
+                    CastleSide::King
+                } else {
+                    CastleSide::Queen
+                };
+                let on_file = position.piece_at(square(file, color.back_rank())) == rook;
+                (side, on_file.then_some(file))
+            }
+            _ => return Err(error(format!("unknown castling right '{c}'"))),
+        };
+        let rook_file =
+            rook_file.ok_or_else(|| error(format!("castling right '{c}' without a rook")))?;
+        rights.set(color, side, Some(rook_file));
+    }
+    Ok(rights)
+}
+
+fn outermost_rook_file(position: &Position, color: PieceColor, side: CastleSide) -> Option<u8> {
+    let back_rank = color.back_rank();
+    let king_file = position
+        .king_square(color)
+        .filter(|&king| rank_of(king) == back_rank)
+        .map(file_of)?;
+    let rook = Some(Piece::new(color, PieceKind::Rook));
+    let mut files: Vec<u8> = match side {
+        CastleSide::King => ((king_file + 1)..8).rev().collect(),
+        CastleSide::Queen => (0..king_file).collect(),
+    };
+    files.retain(|&file| position.piece_at(square(file, back_rank)) == rook);
+    files.first().copied()
+}
+
+/// X-FEN: `K`/`Q` when the castling rook is the outermost one on its side, which covers
+/// standard chess and most Chess960 positions, and the rook's file letter otherwise.
+fn castling_to_string(position: &Position) -> String {
+    let rights = position.castling();
+    let mut text = String::new();
+    for color in [PieceColor::White, PieceColor::Black] {
+        for (side, letter) in [(CastleSide::King, 'K'), (CastleSide::Queen, 'Q')] {
+            let Some(file) = rights.rook_file(color, side) else {
+                continue;
+            };
+            let letter = if outermost_rook_file(position, color, side) == Some(file) {
+                letter
+            } else {
+                (b'A' + file) as char
+            };
+            text.push(match color {
+                PieceColor::White => letter,
+                PieceColor::Black => letter.to_ascii_lowercase(),
+            });
+        }
+    }
+    if text.is_empty() {
+        text.push('-');
+    }
+    text
+}
+
+/// Whether a pawn of the side to move stands next to the pawn that just double-pushed past `sq`.
+fn can_capture_en_passant(position: &Position, sq: u8) -> bool {
+    let us = position.side_to_move();
+    let (target_rank, pawn_rank) = match us {
+        PieceColor::White => (5, 4),
+        PieceColor::Black => (2, 3),
+    };
+    if rank_of(sq) != target_rank {
+        return false;
+    }
+    let file = file_of(sq);
+    [file.checked_sub(1), Some(file + 1).filter(|&f| f < 8)]
+        .into_iter()
+        .flatten()
+        .any(|f| position.piece_at(square(f, pawn_rank)) == Some(Piece::new(us, PieceKind::Pawn)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fen_round_trips() {
+        for fen in [
+            STARTING_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 12 40",
+        ] {
+            assert_eq!(Position::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn bad_fens_are_rejected() {
+        for fen in [
+            "",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq -",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN w KQkq -",
+            "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -",
+            "rnbqkbnr/pppppppp/08/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNRR w KQkq -",
+            "rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -",
+            "rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ -",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq -",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq z9",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1",
+        ] {
+            assert!(Position::from_fen(fen).is_err(), "{fen:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn long_runs_of_digits_are_rejected_without_overflow() {
+        let rank = "9".repeat(30);
+        let fen = format!("{rank}/8/8/8/8/8/8/4K2k w - - 0 1");
+        assert!(Position::from_fen(&fen).is_err());
+        let fen = format!("{}/8/8/8/8/8/8/4K2k w - - 0 1", "1".repeat(300));
+        assert!(Position::from_fen(&fen).is_err());
+    }
+}
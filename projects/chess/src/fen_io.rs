@@ -0,0 +1,80 @@
+use std::io::BufRead;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use chess::rules::Position;
+
+use crate::board::SelectedSquare;
+use crate::game::ChessGame;
+use crate::record::LoadPgnEvent;
+
+/// Replace the current game with the position described by a FEN string.
+#[derive(Event)]
+pub struct LoadFenEvent(pub String);
+
+/// Lines typed into the terminal the app was started from.
+#[derive(Resource)]
+pub struct FenConsole(Mutex<Receiver<String>>);
+
+pub fn spawn_fen_console(mut commands: Commands) {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    commands.insert_resource(FenConsole(Mutex::new(receiver)));
+}
+
+/// Each terminal line is `fen`, which prints the live position, `pgn <path>` to open a game,
+/// or a FEN to load.
+pub fn read_fen_console(
+    console: Res<FenConsole>,
+    game: Res<ChessGame>,
+    mut load_events: EventWriter<LoadFenEvent>,
+    mut pgn_events: EventWriter<LoadPgnEvent>,
+) {
+    let Ok(receiver) = console.0.lock() else {
+        return;
+    };
+    for line in receiver.try_iter() {
+        let line = line.trim();
+        match line {
+            "" => {}
+            "fen" => println!("{}", game.position.to_fen()),
+            _ if line.starts_with("pgn ") => {
+                pgn_events.send(LoadPgnEvent(line[4..].trim().into()));
+            }
+            fen => {
+                load_events.send(LoadFenEvent(fen.to_string()));
+            }
+        }
+    }
+}
+
+/// Press F to print the live position as FEN.
+pub fn export_fen(keys: Res<ButtonInput<KeyCode>>, game: Res<ChessGame>) {
+    if keys.just_pressed(KeyCode::KeyF) {
+        println!("{}", game.position.to_fen());
+    }
+}
+
+pub fn load_fen(
+    mut events: EventReader<LoadFenEvent>,
+    mut game: ResMut<ChessGame>,
+    mut selected: ResMut<SelectedSquare>,
+) {
+    for LoadFenEvent(fen) in events.read() {
+        match Position::from_fen(fen) {
+            Ok(position) => {
+                *game = ChessGame::new(position);
+                selected.entity = None;
+            }
+            Err(err) => warn!("{err}"),
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use chess::outcome::{outcome, Outcome};
+use chess::pgn::{parse_pgn, PgnGame};
+use chess::rules::{Move, PieceColor, Position, Undo};
+use chess::san::move_to_san;
+use chess::variant::{chess960_id_from_seed, Variant};
+
+use crate::engine::EngineBridge;
+
+/// A move in the game record, with what is needed to step back over it.
+#[derive(Clone, Debug)]
+pub struct PlayedMove {
+    pub mv: Move,
+    pub san: String,
+    undo: Undo,
+}
+
+/// The authoritative game state. Rendered pieces follow `position`.
+///
+/// `history` is the whole recorded line; only the first `cursor` moves are applied to
+/// `position`, so a loaded game can be stepped through in both directions.
+#[derive(Resource)]
+pub struct ChessGame {
+    pub position: Position,
+    pub start: Position,
+    pub history: Vec<PlayedMove>,
+    pub cursor: usize,
+    /// PGN tag pairs carried over from a loaded game, e.g. player names.
+    pub tags: Vec<(String, String)>,
+    /// The side whose clock ran out at the end of the record.
+    flagged: Option<PieceColor>,
+    /// How the game ended as of the cursor, refreshed after every change of position.
+    outcome: Option<Outcome>,
+}
+
+impl Default for ChessGame {
+    fn default() -> Self {
+        ChessGame::new(Position::starting())
+    }
+}
+
+impl ChessGame {
+    pub fn new(start: Position) -> Self {
+        let mut game = ChessGame {
+            position: start.clone(),
+            start,
+            history: Vec::new(),
+            cursor: 0,
+            tags: Vec::new(),
+            flagged: None,
+            outcome: None,
+        };
+        game.refresh_outcome();
+        game
+    }
+
+    /// Start from `--fen "<FEN>"`, `--pgn <path>`, `--setup <path>` (see `Variant::parse_config`)
+    /// or `--chess960 <seed>` on the command line, if given.
+    pub fn from_args() -> Self {
+        if let Some(path) = arg_value("--pgn") {
+            match read_pgn(path.as_ref()) {
+                Ok(pgn) => return ChessGame::from_pgn(&pgn),
+                Err(err) => error!("{err}, starting from the initial position"),
+            }
+        } else if let Some(fen) = arg_value("--fen") {
+            match Position::from_fen(&fen) {
+                Ok(position) => return ChessGame::new(position),
+                Err(err) => error!("{err}, starting from the initial position"),
+            }
+        } else if let Some(path) = arg_value("--setup") {
+            match read_setup(path.as_ref()) {
+                Ok(variant) => return ChessGame::new(variant.start_position()),
+                Err(err) => error!("{err}, starting from the initial position"),
+            }
+        } else if let Some(seed) = arg_value("--chess960") {
+            let seed = seed.parse().unwrap_or_else(|_| {
+                error!("--chess960 takes a number, not {seed:?}; using seed 0");
+                0
+            });
+            let id = chess960_id_from_seed(seed);
+            info!("Chess960 setup {id} from seed {seed}");
+            return ChessGame::new(Variant::Chess960 { id }.start_position());
+        }
+        ChessGame::default()
+    }
+
+    /// Load a parsed PGN positioned at its first move, ready to be stepped through.
+    pub fn from_pgn(pgn: &PgnGame) -> Self {
+        let mut game = ChessGame::new(pgn.start.clone());
+        for &mv in &pgn.moves {
+            game.play(mv);
+        }
+        game.tags = pgn.tags.clone();
+        while game.step_back() {}
+        game
+    }
+
+    /// Play a move that has already been checked against `Position::legal_moves`.
+    /// Playing from the middle of the record replaces everything after the cursor, along with
+    /// a flag that fell at its end; at the end of the record a fallen flag stands.
+    pub fn play(&mut self, mv: Move) {
+        if self.cursor < self.history.len() {
+            self.flagged = None;
+        }
+        self.history.truncate(self.cursor);
+        let san = move_to_san(&self.position, mv);
+        let undo = self.position.make_move(mv);
+        self.history.push(PlayedMove { mv, san, undo });
+        self.cursor += 1;
+        self.refresh_outcome();
+    }
+
+    pub fn step_back(&mut self) -> bool {
+        if self.cursor == 0 {
+            return false;
+        }
+        self.cursor -= 1;
+        let played = &self.history[self.cursor];
+        self.position.unmake_move(played.mv, played.undo);
+        self.refresh_outcome();
+        true
+    }
+
+    pub fn step_forward(&mut self) -> bool {
+        let Some(played) = self.history.get_mut(self.cursor) else {
+            return false;
+        };
+        played.undo = self.position.make_move(played.mv);
+        self.cursor += 1;
+        self.refresh_outcome();
+        true
+    }
+
+    /// Whether the board is at the end of the record and the game is still going.
+    pub fn is_live(&self) -> bool {
+        self.cursor == self.history.len() && self.outcome().is_none()
+    }
+
+    /// Whether a move may be played here: anywhere back in the record, where it starts a new
+    /// line, or at its end while the game is still going.
+    pub fn accepts_moves(&self) -> bool {
+        self.cursor < self.history.len() || self.outcome().is_none()
+    }
+
+    /// Zobrist hashes of the positions since the last capture or pawn move, newest first,
+    /// up to the cursor. Nothing older can come round again.
+    pub fn position_hashes(&self) -> Vec<u64> {
+        let mut position = self.position.clone();
+        let mut hashes = vec![position.zobrist_hash()];
+        let reversible = (position.halfmove_clock() as usize).min(self.cursor);
+        for played in self.history[..self.cursor].iter().rev().take(reversible) {
+            position.unmake_move(played.mv, played.undo);
+            hashes.push(position.zobrist_hash());
+        }
+        hashes
+    }
+
+    /// How the game ended, if it has, as of the cursor.
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    /// End the game because `color` ran out of time.
+    pub fn flag(&mut self, color: PieceColor) {
+        self.flagged = Some(color);
+        self.refresh_outcome();
+    }
+
+    fn refresh_outcome(&mut self) {
+        let timeout = self
+            .flagged
+            .filter(|_| self.cursor == self.history.len())
+            .map(|color| Outcome::Timeout {
+                winner: color.opposite(),
+            });
+        self.outcome = timeout.or_else(|| outcome(&self.position, &self.position_hashes()));
+    }
+
+    /// PGN result for the game as played up to the cursor.
+    pub fn result(&self) -> &'static str {
+        self.outcome().map_or("*", |outcome| outcome.result())
+    }
+
+    /// The game up to the cursor as a PGN record.
+    pub fn to_pgn(&self) -> PgnGame {
+        let mut pgn = PgnGame {
+            tags: self.tags.clone(),
+            start: self.start.clone(),
+            moves: self.history[..self.cursor]
+                .iter()
+                .map(|played| played.mv)
+                .collect(),
+        };
+        for (name, default) in [
+            ("Event", "Casual game"),
+            ("Site", "Bevy chess"),
+            ("Round", "-"),
+            ("White", "White"),
+            ("Black", "Black"),
+        ] {
+            if pgn.tag(name).is_none() {
+                pgn.set_tag(name, default);
+            }
+        }
+        if self.start.has_chess960_castling() && pgn.tag("Variant").is_none() {
+            pgn.set_tag("Variant", "Chess960");
+        }
+        if pgn.tag("Date").is_none() {
+            pgn.set_tag("Date", today());
+        }
+        pgn.set_tag("Result", self.result());
+        pgn
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Player {
+    Human,
+    Computer,
+    /// The external UCI engine from `--engine`.
+    Engine,
+    /// The other end of a network game.
+    Remote,
+}
+
+impl Player {
+    fn parse(text: &str) -> Option<Player> {
+        match text {
+            "human" => Some(Player::Human),
+            "computer" | "ai" => Some(Player::Computer),
+            "engine" => Some(Player::Engine),
+            _ => None,
+        }
+    }
+}
+
+/// Who moves for each color, set with `--white <player>` / `--black <player>`
+/// and toggled at runtime with W and B.
+#[derive(Resource)]
+pub struct Players {
+    pub white: Player,
+    pub black: Player,
+}
+
+impl Players {
+    pub fn from_args() -> Self {
+        let player = |flag| {
+            arg_value(flag)
+                .and_then(|value| Player::parse(&value))
+                .unwrap_or(Player::Human)
+        };
+        Players {
+            white: player("--white"),
+            black: player("--black"),
+        }
+    }
+
+    pub fn get(&self, color: PieceColor) -> Player {
+        match color {
+            PieceColor::White => self.white,
+            PieceColor::Black => self.black,
+        }
+    }
+
+    pub fn get_mut(&mut self, color: PieceColor) -> &mut Player {
+        match color {
+            PieceColor::White => &mut self.white,
+            PieceColor::Black => &mut self.black,
+        }
+    }
+}
+
+/// W and B cycle a color through human, computer and, if `--engine` was given, the engine.
+pub fn toggle_players(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut players: ResMut<Players>,
+    bridge: Res<EngineBridge>,
+) {
+    for (key, color) in [
+        (KeyCode::KeyW, PieceColor::White),
+        (KeyCode::KeyB, PieceColor::Black),
+    ] {
+        if keys.just_pressed(key) {
+            let player = players.get_mut(color);
+            *player = match *player {
+                Player::Human => Player::Computer,
+                Player::Computer if bridge.path.is_some() => Player::Engine,
+                Player::Computer | Player::Engine => Player::Human,
+                Player::Remote => continue,
+            };
+            info!("{color:?} is now played by {:?}", *player);
+        }
+    }
+}
+
+/// The argument following `flag` on the command line.
+pub fn arg_value(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != flag);
+    args.next()?;
+    args.next()
+}
+
+pub fn read_pgn(path: &Path) -> Result<PgnGame, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+    parse_pgn(&text).map_err(|err| format!("{}: {err}", path.display()))
+}
+
+pub fn read_setup(path: &Path) -> Result<Variant, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+    Variant::parse_config(&text).map_err(|err| format!("{}: {err}", path.display()))
+}
+
+/// Today's UTC date as a PGN `YYYY.MM.DD` string.
+fn today() -> String {
+    let Ok(elapsed) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) else {
+        return "????.??.??".to_string();
+    };
+    // Days since 1970-01-01 to a civil date, after Howard Hinnant's `civil_from_days`.
+    let days = (elapsed.as_secs() / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{year:04}.{month:02}.{day:02}")
+}
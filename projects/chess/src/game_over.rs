@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+
+use crate::board::SelectedSquare;
+use crate::game::ChessGame;
+
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GameState {
+    #[default]
+    Playing,
+    GameOver,
+}
+
+#[derive(Component)]
+pub struct GameOverScreen;
+
+#[derive(Component)]
+pub struct RestartButton;
+
+/// Switch to the game-over screen when the position ends the game, and back if stepping
+/// through the record leaves the final position.
+pub fn detect_game_end(
+    game: Res<ChessGame>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !game.is_changed() {
+        return;
+    }
+    let over = game.outcome().is_some();
+    match state.get() {
+        GameState::Playing if over => next_state.set(GameState::GameOver),
+        GameState::GameOver if !over => next_state.set(GameState::Playing),
+        _ => {}
+    }
+}
+
+pub fn spawn_game_over_screen(mut commands: Commands, game: Res<ChessGame>) {
+    let message = game
+        .outcome()
+        .map(|outcome| outcome.to_string())
+        .unwrap_or_default();
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(20.0),
+                width: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            GameOverScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(message),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+            ));
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        padding: UiRect::axes(Val::Px(24.0), Val::Px(8.0)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                    RestartButton,
+                ))
+                .with_children(|button| {
+                    button.spawn((
+                        Text::new("Restart"),
+                        TextFont {
+                            font_size: 28.0,
+                            ..default()
+                        },
+                    ));
+                });
+        });
+}
+
+pub fn despawn_game_over_screen(
+    mut commands: Commands,
+    screens: Query<Entity, With<GameOverScreen>>,
+) {
+    for entity in screens.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Start a new game from the same starting position.
+pub fn restart_button(
+    mut buttons: Query<(&Interaction, &mut BackgroundColor), With<RestartButton>>,
+    mut game: ResMut<ChessGame>,
+    mut selected: ResMut<SelectedSquare>,
+) {
+    for (interaction, mut background) in buttons.iter_mut() {
+        let color = match *interaction {
+            Interaction::Pressed => {
+                *game = ChessGame::new(game.start.clone());
+                selected.entity = None;
+                return;
+            }
+            Interaction::Hovered => Color::srgb(0.35, 0.35, 0.35),
+            Interaction::None => Color::srgb(0.2, 0.2, 0.2),
+        };
+        if background.0 != color {
+            background.0 = color;
+        }
+    }
+}
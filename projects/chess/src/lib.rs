@@ -0,0 +1,13 @@
+// Engine-side chess code that does not depend on Bevy.
+pub mod clock;
+pub mod fen;
+pub mod net;
+pub mod outcome;
+pub mod perft;
+pub mod pgn;
+pub mod rules;
+pub mod san;
+pub mod search;
+pub mod uci;
+pub mod variant;
+pub mod zobrist;
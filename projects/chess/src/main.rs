@@ -5,11 +5,33 @@ use bevy::{
     window::WindowTheme,
 };
 
+mod ai;
 mod board;
+mod camera;
+mod clock_ui;
+mod engine;
+mod fen_io;
+mod game;
+mod game_over;
+mod network;
 mod pieces;
+mod promotion;
+mod record;
+mod takeback;
 
+use ai::*;
 use board::*;
+use camera::*;
+use clock_ui::*;
+use engine::*;
+use fen_io::*;
+use game::{toggle_players, ChessGame, Players};
+use game_over::*;
+use network::*;
 use pieces::*; // this use namespace
+use promotion::*;
+use record::*;
+use takeback::*;
 
 #[derive(Resource)]
 struct Msaa {
@@ -39,8 +61,88 @@ fn main() {
             }),
             ..default()
         }),))
-        .add_systems(Startup, (setup, create_board, create_pieces))
-        .add_systems(Update, (draw_mesh_intersections, color_squares))
+        .insert_resource(ChessGame::from_args())
+        .insert_resource(Players::from_args())
+        .insert_resource(AiSettings::from_args())
+        .init_resource::<AiSearch>()
+        .init_resource::<PendingPromotion>()
+        .init_resource::<CameraRig>()
+        .insert_resource(GameClock::from_args())
+        .insert_resource(EngineBridge::from_args())
+        .insert_resource(Network::from_args())
+        .init_state::<GameState>()
+        .add_event::<LoadFenEvent>()
+        .add_event::<LoadPgnEvent>()
+        .add_systems(
+            Startup,
+            (
+                setup,
+                create_board,
+                spawn_board_labels,
+                create_pieces,
+                spawn_fen_console,
+                spawn_move_list,
+                spawn_clocks,
+                spawn_takeback_buttons,
+                connect_network.after(create_pieces),
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                draw_mesh_intersections,
+                (
+                    read_fen_console,
+                    load_fen.run_if(offline),
+                    load_pgn.run_if(offline),
+                )
+                    .chain(),
+                (export_fen, save_pgn, toggle_players),
+                (
+                    select_square.run_if(in_state(GameState::Playing)),
+                    choose_promotion,
+                    step_through_game.run_if(offline),
+                    takeback.run_if(offline),
+                    finish_connection,
+                    finish_ai_search,
+                    finish_engine_search,
+                )
+                    .after(load_pgn),
+                sync_network
+                    .after(select_square)
+                    .after(choose_promotion)
+                    .after(finish_ai_search)
+                    .after(finish_engine_search)
+                    .after(finish_connection),
+                start_ai_search.after(finish_ai_search),
+                start_engine_search.after(finish_engine_search),
+                (color_squares, sync_pieces, update_move_list)
+                    .after(sync_network)
+                    .after(select_square)
+                    .after(choose_promotion)
+                    .after(step_through_game)
+                    .after(takeback)
+                    .after(finish_ai_search)
+                    .after(finish_engine_search),
+                (run_clocks, update_clocks)
+                    .chain()
+                    .after(select_square)
+                    .after(choose_promotion)
+                    .after(finish_ai_search)
+                    .after(finish_engine_search),
+                (animate_pieces, fade_pieces).after(sync_pieces),
+                (switch_camera, move_camera, place_board_labels)
+                    .chain()
+                    .after(sync_pieces),
+                show_promotion_chooser.after(choose_promotion),
+                detect_game_end.after(color_squares).after(run_clocks),
+                restart_button
+                    .run_if(in_state(GameState::GameOver))
+                    .run_if(offline),
+            ),
+        )
+        .add_systems(OnEnter(GameState::GameOver), spawn_game_over_screen)
+        .add_systems(OnExit(GameState::GameOver), despawn_game_over_screen)
         .add_plugins(MeshPickingPlugin)
         .insert_resource(Msaa { samples: 4 })
         .insert_resource(MeshPickingSettings {
@@ -52,16 +154,14 @@ fn main() {
 }
 
 /// Set up a simple 3D scene
-fn setup(mut commands: Commands) {
+fn setup(mut commands: Commands, rig: Res<CameraRig>) {
     //Camera
     commands.spawn((
         // mutable cause pub fn spawn<T: Bundle>(&mut self, bundle: T) -> EntityCommands
         Camera3d::default(),
-        Transform::from_matrix(Mat4::from_rotation_translation(
-            Quat::from_xyzw(-0.3, -0.5, -0.3, 0.5).normalize(),
-            Vec3::new(-7.0, 20.0, 4.0),
-        )),
+        rig.transform(),
         RayCastPickable,
+        ChessCamera,
     ));
 
     // Light
@@ -74,155 +174,5 @@ fn setup(mut commands: Commands) {
     ));
 }
 
-fn create_pieces(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
-    // Load all the meshes
-    let king_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh0/Primitive0");
-    let king_cross_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh1/Primitive0");
-    let pawn_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh2/Primitive0");
-    let knight_1_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh3/Primitive0");
-    let knight_2_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh4/Primitive0");
-    let rook_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh5/Primitive0");
-    let bishop_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh6/Primitive0");
-    let queen_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh7/Primitive0");
-
-    // Add some materials
-    let white_material = materials.add(Color::srgb(1., 0.8, 0.8));
-    let black_material = materials.add(Color::srgb(0., 0.2, 0.2));
-
-    spawn_rook(
-        &mut commands,
-        white_material.clone(),
-        rook_handle.clone(),
-        Vec3::new(0., 0., 0.),
-    );
-    spawn_knight(
-        &mut commands,
-        white_material.clone(),
-        knight_1_handle.clone(),
-        knight_2_handle.clone(),
-        Vec3::new(0., 0., 1.),
-    );
-    spawn_bishop(
-        &mut commands,
-        white_material.clone(),
-        bishop_handle.clone(),
-        Vec3::new(0., 0., 2.),
-    );
-    spawn_queen(
-        &mut commands,
-        white_material.clone(),
-        queen_handle.clone(),
-        Vec3::new(0., 0., 3.),
-    );
-    spawn_king(
-        &mut commands,
-        white_material.clone(),
-        king_handle.clone(),
-        king_cross_handle.clone(),
-        Vec3::new(0., 0., 4.),
-    );
-    spawn_bishop(
-        &mut commands,
-        white_material.clone(),
-        bishop_handle.clone(),
-        Vec3::new(0., 0., 5.),
-    );
-    spawn_knight(
-        &mut commands,
-        white_material.clone(),
-        knight_1_handle.clone(),
-        knight_2_handle.clone(),
-        Vec3::new(0., 0., 6.),
-    );
-    spawn_rook(
-        &mut commands,
-        white_material.clone(),
-        rook_handle.clone(),
-        Vec3::new(0., 0., 7.),
-    );
-
-    for i in 0..8 {
-        spawn_pawn(
-            &mut commands,
-            white_material.clone(),
-            pawn_handle.clone(),
-            Vec3::new(1., 0., i as f32),
-        );
-    }
-
-    spawn_rook(
-        &mut commands,
-        black_material.clone(),
-        rook_handle.clone(),
-        Vec3::new(7., 0., 0.),
-    );
-    spawn_knight(
-        &mut commands,
-        black_material.clone(),
-        knight_1_handle.clone(),
-        knight_2_handle.clone(),
-        Vec3::new(7., 0., 1.),
-    );
-    spawn_bishop(
-        &mut commands,
-        black_material.clone(),
-        bishop_handle.clone(),
-        Vec3::new(7., 0., 2.),
-    );
-    spawn_queen(
-        &mut commands,
-        black_material.clone(),
-        queen_handle.clone(),
-        Vec3::new(7., 0., 3.),
-    );
-    spawn_king(
-        &mut commands,
-        black_material.clone(),
-        king_handle.clone(),
-        king_cross_handle.clone(),
-        Vec3::new(7., 0., 4.),
-    );
-    spawn_bishop(
-        &mut commands,
-        black_material.clone(),
-        bishop_handle.clone(),
-        Vec3::new(7., 0., 5.),
-    );
-    spawn_knight(
-        &mut commands,
-        black_material.clone(),
-        knight_1_handle.clone(),
-        knight_2_handle.clone(),
-        Vec3::new(7., 0., 6.),
-    );
-    spawn_rook(
-        &mut commands,
-        black_material.clone(),
-        rook_handle.clone(),
-        Vec3::new(7., 0., 7.),
-    );
-
-    for i in 0..8 {
-        spawn_pawn(
-            &mut commands,
-            black_material.clone(),
-            pawn_handle.clone(),
-            Vec3::new(6., 0., i as f32),
-        );
-    }
-}
-
 // https://rustic-chess.org/
 // https://caballerocoll.com/blog/bevy-chess-tutorial/
@@ -0,0 +1,335 @@
+// Two-player games over TCP: a small line-based protocol and a connection that checks both ends agree.
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::fen::FenError;
+use crate::rules::{Move, PieceColor, Position};
+use crate::uci::{move_from_uci, move_to_uci};
+
+/// Bumped whenever a message changes shape; peers with different versions refuse to play.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// One line on the wire.
+///
+/// ```text
+/// hello <version> <host color> <start FEN>   host -> guest, first line
+/// welcome <version>                          guest -> host, accepts the game
+/// reject <reason>                            either way, refuses or ends the game
+/// move <uci> <hash>                          a move and the Zobrist hash after it, in hex
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Hello {
+        version: u32,
+        host_color: PieceColor,
+        start: String,
+    },
+    Welcome {
+        version: u32,
+    },
+    Reject {
+        reason: String,
+    },
+    Move {
+        mv: String,
+        hash: u64,
+    },
+}
+
+impl Message {
+    pub fn to_line(&self) -> String {
+        match self {
+            Message::Hello {
+                version,
+                host_color,
+                start,
+            } => format!("hello {version} {} {start}", color_name(*host_color)),
+            Message::Welcome { version } => format!("welcome {version}"),
+            Message::Reject { reason } => format!("reject {reason}"),
+            Message::Move { mv, hash } => format!("move {mv} {hash:016x}"),
+        }
+    }
+
+    pub fn parse(line: &str) -> Option<Message> {
+        let line = line.trim();
+        let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match word {
+            "hello" => {
+                let mut fields = rest.splitn(3, ' ');
+                let version = fields.next()?.parse().ok()?;
+                let host_color = match fields.next()? {
+                    "white" => PieceColor::White,
+                    "black" => PieceColor::Black,
+                    _ => return None,
+                };
+                let start = fields.next()?.to_string();
+                Some(Message::Hello {
+                    version,
+                    host_color,
+                    start,
+                })
+            }
+            "welcome" => Some(Message::Welcome {
+                version: rest.parse().ok()?,
+            }),
+            "reject" => Some(Message::Reject {
+                reason: rest.to_string(),
+            }),
+            "move" => {
+                let (mv, hash) = rest.split_once(' ')?;
+                Some(Message::Move {
+                    mv: mv.to_string(),
+                    hash: u64::from_str_radix(hash, 16).ok()?,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+fn color_name(color: PieceColor) -> &'static str {
+    match color {
+        PieceColor::White => "white",
+        PieceColor::Black => "black",
+    }
+}
+
+#[derive(Debug)]
+pub enum NetError {
+    Io(io::Error),
+    /// The peer closed the connection.
+    Disconnected,
+    /// A line that is not a message, or a message out of turn.
+    Protocol(String),
+    VersionMismatch {
+        ours: u32,
+        theirs: u32,
+    },
+    BadStart(FenError),
+    /// The peer refused the game or ended it.
+    Rejected(String),
+    /// A move that is not legal here, from the peer or about to be sent.
+    IllegalMove(String),
+    /// Both ends played the same move but reached different positions.
+    Desync {
+        ours: u64,
+        theirs: u64,
+    },
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::Io(err) => write!(f, "network error: {err}"),
+            NetError::Disconnected => write!(f, "the other player disconnected"),
+            NetError::Protocol(line) => write!(f, "unexpected message {line:?}"),
+            NetError::VersionMismatch { ours, theirs } => {
+                write!(f, "protocol version {theirs} does not match ours ({ours})")
+            }
+            NetError::BadStart(err) => write!(f, "bad starting position: {err}"),
+            NetError::Rejected(reason) => write!(f, "the other player refused: {reason}"),
+            NetError::IllegalMove(mv) => write!(f, "illegal move {mv}"),
+            NetError::Desync { ours, theirs } => {
+                write!(
+                    f,
+                    "positions differ: ours {ours:016x}, theirs {theirs:016x}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for NetError {}
+
+impl From<io::Error> for NetError {
+    fn from(err: io::Error) -> Self {
+        NetError::Io(err)
+    }
+}
+
+/// A game against a peer on the other end of a TCP connection.
+///
+/// Both ends keep their own copy of the position. Every move is checked against it before it
+/// is sent and again when it arrives, and the hash sent along with it must match the hash the
+/// receiver reaches, so the two copies cannot drift apart unnoticed.
+pub struct NetGame {
+    stream: TcpStream,
+    /// Lines read by a background thread. Only ever used through `&mut self`; the mutex just
+    /// makes the game `Sync` so it can sit in shared state such as an ECS resource.
+    incoming: Mutex<Receiver<io::Result<String>>>,
+    /// The color played on this end.
+    pub color: PieceColor,
+    pub start: Position,
+    position: Position,
+}
+
+impl NetGame {
+    /// Wait for one guest on `listener` and offer it a game from `start`, with this end
+    /// playing `color`.
+    pub fn host(
+        listener: &TcpListener,
+        color: PieceColor,
+        start: Position,
+    ) -> Result<Self, NetError> {
+        let (stream, _) = listener.accept()?;
+        let mut game = NetGame::new(stream, color, start)?;
+        game.send(&Message::Hello {
+            version: PROTOCOL_VERSION,
+            host_color: color,
+            start: game.start.to_fen(),
+        })?;
+        match game.receive(None)? {
+            Message::Welcome { version } if version == PROTOCOL_VERSION => Ok(game),
+            Message::Welcome { version } => Err(NetError::VersionMismatch {
+                ours: PROTOCOL_VERSION,
+                theirs: version,
+            }),
+            Message::Reject { reason } => Err(NetError::Rejected(reason)),
+            other => Err(NetError::Protocol(other.to_line())),
+        }
+    }
+
+    /// Connect to a host and accept its game.
+    pub fn join(addr: impl ToSocketAddrs) -> Result<Self, NetError> {
+        let stream = TcpStream::connect(addr)?;
+        let mut game = NetGame::new(stream, PieceColor::White, Position::starting())?;
+        let (host_color, start) = match game.receive(None)? {
+            Message::Hello {
+                version,
+                host_color,
+                start,
+            } => {
+                if version != PROTOCOL_VERSION {
+                    let _ = game.send(&Message::Reject {
+                        reason: format!("protocol version {PROTOCOL_VERSION} only"),
+                    });
+                    return Err(NetError::VersionMismatch {
+                        ours: PROTOCOL_VERSION,
+                        theirs: version,
+                    });
+                }
+                (host_color, start)
+            }
+            other => return Err(NetError::Protocol(other.to_line())),
+        };
+        let start = Position::from_fen(&start).map_err(NetError::BadStart)?;
+        game.color = host_color.opposite();
+        game.position = start.clone();
+        game.start = start;
+        game.send(&Message::Welcome {
+            version: PROTOCOL_VERSION,
+        })?;
+        Ok(game)
+    }
+
+    fn new(stream: TcpStream, color: PieceColor, start: Position) -> Result<Self, NetError> {
+        stream.set_nodelay(true)?;
+        let reader = BufReader::new(stream.try_clone()?);
+        let (sender, incoming) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                if sender.send(line).is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(NetGame {
+            stream,
+            incoming: Mutex::new(incoming),
+            color,
+            position: start.clone(),
+            start,
+        })
+    }
+
+    /// The position both ends agree on.
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    fn send(&mut self, message: &Message) -> Result<(), NetError> {
+        writeln!(self.stream, "{}", message.to_line())?;
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    /// Next message; `None` waits as long as it takes.
+    fn receive(&mut self, timeout: Option<Duration>) -> Result<Message, NetError> {
+        let incoming = self.incoming.get_mut().expect("reader mutex poisoned");
+        let line = match timeout {
+            None => incoming.recv().map_err(|_| NetError::Disconnected)?,
+            Some(timeout) => incoming.recv_timeout(timeout).map_err(|err| match err {
+                RecvTimeoutError::Timeout => {
+                    NetError::Io(io::Error::new(io::ErrorKind::TimedOut, "no message"))
+                }
+                RecvTimeoutError::Disconnected => NetError::Disconnected,
+            })?,
+        }?;
+        Message::parse(&line).ok_or(NetError::Protocol(line))
+    }
+
+    /// Play one of our own moves and send it.
+    pub fn send_move(&mut self, mv: Move) -> Result<(), NetError> {
+        if self.position.side_to_move() != self.color || !self.position.is_legal(mv) {
+            return Err(NetError::IllegalMove(move_to_uci(mv)));
+        }
+        self.position.make_move(mv);
+        let hash = self.position.zobrist_hash();
+        self.send(&Message::Move {
+            mv: move_to_uci(mv),
+            hash,
+        })
+    }
+
+    /// The peer's next move if one has arrived, without waiting.
+    pub fn poll_move(&mut self) -> Result<Option<Move>, NetError> {
+        let incoming = self.incoming.get_mut().expect("reader mutex poisoned");
+        let line = match incoming.try_recv() {
+            Ok(line) => line?,
+            Err(TryRecvError::Empty) => return Ok(None),
+            Err(TryRecvError::Disconnected) => return Err(NetError::Disconnected),
+        };
+        let message = Message::parse(&line).ok_or(NetError::Protocol(line))?;
+        self.apply(message).map(Some)
+    }
+
+    /// Wait up to `timeout` for the peer's next move.
+    pub fn wait_move(&mut self, timeout: Duration) -> Result<Move, NetError> {
+        let message = self.receive(Some(timeout))?;
+        self.apply(message)
+    }
+
+    fn apply(&mut self, message: Message) -> Result<Move, NetError> {
+        let (text, theirs) = match message {
+            Message::Move { mv, hash } => (mv, hash),
+            Message::Reject { reason } => return Err(NetError::Rejected(reason)),
+            other => return Err(NetError::Protocol(other.to_line())),
+        };
+        if self.position.side_to_move() == self.color {
+            return Err(NetError::Protocol(format!("move {text} out of turn")));
+        }
+        let Some(mv) = move_from_uci(&self.position, &text) else {
+            let _ = self.reject(&format!("illegal move {text}"));
+            return Err(NetError::IllegalMove(text));
+        };
+        self.position.make_move(mv);
+        let ours = self.position.zobrist_hash();
+        if ours != theirs {
+            let _ = self.reject(&format!("desync after {text}"));
+            return Err(NetError::Desync { ours, theirs });
+        }
+        Ok(mv)
+    }
+
+    /// Tell the peer the game is over on this end, e.g. when quitting.
+    pub fn reject(&mut self, reason: &str) -> Result<(), NetError> {
+        self.send(&Message::Reject {
+            reason: reason.to_string(),
+        })
+    }
+}
@@ -0,0 +1,170 @@
+use std::net::TcpListener;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Mutex;
+use std::thread;
+
+use bevy::prelude::*;
+use chess::net::{NetError, NetGame};
+use chess::rules::PieceColor;
+
+use crate::board::SelectedSquare;
+use crate::game::{arg_value, ChessGame, Player, Players};
+
+enum Role {
+    Host { addr: String, color: PieceColor },
+    Join { addr: String },
+}
+
+/// A game against another instance over TCP, set up with `--host <addr>` or `--join <addr>`.
+/// The host plays White unless `--net-color black` is given and picks the starting position.
+#[derive(Resource)]
+pub struct Network {
+    role: Option<Role>,
+    /// The handshake, on a thread of its own since it blocks until the peer answers.
+    connecting: Option<Mutex<Receiver<Result<NetGame, NetError>>>>,
+    game: Option<NetGame>,
+    /// Moves of the game record the peer already knows about.
+    synced: usize,
+}
+
+impl Network {
+    pub fn from_args() -> Self {
+        let role = if let Some(addr) = arg_value("--host") {
+            let color = match arg_value("--net-color").as_deref() {
+                Some("black") => PieceColor::Black,
+                _ => PieceColor::White,
+            };
+            Some(Role::Host { addr, color })
+        } else {
+            arg_value("--join").map(|addr| Role::Join { addr })
+        };
+        Network {
+            role,
+            connecting: None,
+            game: None,
+            synced: 0,
+        }
+    }
+
+    fn disconnect(&mut self, players: &mut Players, err: NetError) {
+        error!("{err}");
+        if let Some(mut net) = self.game.take() {
+            let _ = net.reject(&err.to_string());
+            // Let whoever is at this end finish the game alone.
+            *players.get_mut(net.color.opposite()) = Player::Human;
+        }
+    }
+}
+
+/// Run condition for everything that rewrites the game record, which a networked game cannot do.
+pub fn offline(network: Res<Network>) -> bool {
+    network.role.is_none()
+}
+
+/// Start hosting or joining in the background, so the window stays responsive while waiting.
+/// Waiting on the network would hold up a task pool thread for as long as the peer takes, so
+/// it gets a thread of its own.
+pub fn connect_network(mut network: ResMut<Network>, game: Res<ChessGame>) {
+    let (sender, receiver) = mpsc::channel();
+    match &network.role {
+        None => return,
+        Some(Role::Host { addr, color }) => {
+            let (addr, color, start) = (addr.clone(), *color, game.start.clone());
+            info!("Waiting for a player to join on {addr}");
+            thread::spawn(move || {
+                let result = TcpListener::bind(&addr)
+                    .map_err(NetError::from)
+                    .and_then(|listener| NetGame::host(&listener, color, start));
+                let _ = sender.send(result);
+            });
+        }
+        Some(Role::Join { addr }) => {
+            let addr = addr.clone();
+            info!("Joining {addr}");
+            thread::spawn(move || {
+                let _ = sender.send(NetGame::join(addr));
+            });
+        }
+    }
+    network.connecting = Some(Mutex::new(receiver));
+}
+
+/// Once connected, start the host's game with the remote side marked as such.
+pub fn finish_connection(
+    mut network: ResMut<Network>,
+    mut game: ResMut<ChessGame>,
+    mut players: ResMut<Players>,
+    mut selected: ResMut<SelectedSquare>,
+) {
+    let Some(connecting) = network.connecting.as_mut() else {
+        return;
+    };
+    let result = match connecting
+        .get_mut()
+        .expect("connection mutex poisoned")
+        .try_recv()
+    {
+        Ok(result) => result,
+        Err(TryRecvError::Empty) => return,
+        Err(TryRecvError::Disconnected) => {
+            error!("Could not connect: the connection thread stopped");
+            network.connecting = None;
+            return;
+        }
+    };
+    network.connecting = None;
+    match result {
+        Ok(net) => {
+            info!("Connected, playing {:?}", net.color);
+            *game = ChessGame::new(net.start.clone());
+            *players.get_mut(net.color.opposite()) = Player::Remote;
+            if players.get(net.color) == Player::Remote {
+                *players.get_mut(net.color) = Player::Human;
+            }
+            selected.entity = None;
+            network.synced = 0;
+            network.game = Some(net);
+        }
+        Err(err) => error!("Could not connect: {err}"),
+    }
+}
+
+/// Send the moves played here and play the ones that arrived, then make sure both ends still
+/// hold the same position.
+pub fn sync_network(
+    mut network: ResMut<Network>,
+    mut game: ResMut<ChessGame>,
+    mut players: ResMut<Players>,
+) {
+    let network = &mut *network;
+    let Some(net) = network.game.as_mut() else {
+        return;
+    };
+
+    let mut result = Ok(());
+    while result.is_ok() && network.synced < game.history.len() {
+        result = net.send_move(game.history[network.synced].mv);
+        network.synced += 1;
+    }
+    // Once a flag falls the game is over here, whatever the peer still sends.
+    while result.is_ok() && game.is_live() {
+        match net.poll_move() {
+            Ok(Some(mv)) => {
+                game.play(mv);
+                network.synced += 1;
+            }
+            Ok(None) => break,
+            Err(err) => result = Err(err),
+        }
+    }
+    if result.is_ok() {
+        let (ours, theirs) = (game.position.zobrist_hash(), net.position().zobrist_hash());
+        if ours != theirs {
+            result = Err(NetError::Desync { ours, theirs });
+        }
+    }
+
+    if let Err(err) = result {
+        network.disconnect(&mut players, err);
+    }
+}
@@ -0,0 +1,213 @@
+// How a game ends: checkmate, stalemate and the automatic draws.
+use std::fmt;
+
+use crate::rules::{file_of, rank_of, PieceColor, PieceKind, Position};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Checkmate {
+        winner: PieceColor,
+    },
+    Stalemate,
+    ThreefoldRepetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+    /// The other side ran out of time.
+    Timeout {
+        winner: PieceColor,
+    },
+}
+
+impl Outcome {
+    pub fn winner(&self) -> Option<PieceColor> {
+        match self {
+            Outcome::Checkmate { winner } | Outcome::Timeout { winner } => Some(*winner),
+            _ => None,
+        }
+    }
+
+    /// The PGN result token.
+    pub fn result(&self) -> &'static str {
+        match self.winner() {
+            Some(PieceColor::White) => "1-0",
+            Some(PieceColor::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Checkmate { winner } => write!(f, "Checkmate, {winner:?} wins"),
+            Outcome::Stalemate => write!(f, "Draw by stalemate"),
+            Outcome::ThreefoldRepetition => write!(f, "Draw by threefold repetition"),
+            Outcome::FiftyMoveRule => write!(f, "Draw by the fifty-move rule"),
+            Outcome::InsufficientMaterial => write!(f, "Draw by insufficient material"),
+            Outcome::Timeout { winner } => {
+                write!(f, "{:?} lost on time, {winner:?} wins", winner.opposite())
+            }
+        }
+    }
+}
+
+/// The outcome of `position`, if the game is over.
+///
+/// `history` holds the Zobrist hashes of the positions of the game so far, including this one,
+/// and is only used to spot repetitions. Positions from before the last capture or pawn move
+/// can never recur, so it need not go back further than that.
+pub fn outcome(position: &Position, history: &[u64]) -> Option<Outcome> {
+    if position.legal_moves().is_empty() {
+        return Some(if position.in_check() {
+            Outcome::Checkmate {
+                winner: position.side_to_move().opposite(),
+            }
+        } else {
+            Outcome::Stalemate
+        });
+    }
+    if position.halfmove_clock() >= 100 {
+        return Some(Outcome::FiftyMoveRule);
+    }
+    let current = position.zobrist_hash();
+    if history.iter().filter(|&&hash| hash == current).count() >= 3 {
+        return Some(Outcome::ThreefoldRepetition);
+    }
+    if has_insufficient_material(position) {
+        return Some(Outcome::InsufficientMaterial);
+    }
+    None
+}
+
+/// Neither side can ever mate: bare kings, a lone minor piece, or only bishops all on one color.
+pub fn has_insufficient_material(position: &Position) -> bool {
+    let mut knights = 0;
+    let mut bishops = 0;
+    let mut bishop_square_colors = [false; 2];
+    for (sq, piece) in position.pieces() {
+        match piece.kind {
+            PieceKind::King => {}
+            PieceKind::Knight => knights += 1,
+            PieceKind::Bishop => {
+                bishops += 1;
+                bishop_square_colors[((file_of(sq) + rank_of(sq)) % 2) as usize] = true;
+            }
+            PieceKind::Pawn | PieceKind::Rook | PieceKind::Queen => return false,
+        }
+    }
+    let mixed_bishops = bishop_square_colors[0] && bishop_square_colors[1];
+    knights + bishops <= 1 || (knights == 0 && !mixed_bishops)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::parse_square;
+
+    fn fen(text: &str) -> Position {
+        Position::from_fen(text).unwrap()
+    }
+
+    /// Play moves given as `e2e4`, returning the hash of every position along the way.
+    fn play(position: &mut Position, moves: &[&str]) -> Vec<u64> {
+        let mut hashes = vec![position.zobrist_hash()];
+        for name in moves {
+            let from = parse_square(&name[..2]).unwrap();
+            let to = parse_square(&name[2..]).unwrap();
+            let mv = position
+                .legal_moves()
+                .into_iter()
+                .find(|mv| mv.from == from && mv.to == to)
+                .unwrap();
+            position.make_move(mv);
+            hashes.push(position.zobrist_hash());
+        }
+        hashes
+    }
+
+    #[test]
+    fn checkmate_and_stalemate() {
+        let mated = fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1");
+        assert_eq!(
+            outcome(&mated, &[]),
+            Some(Outcome::Checkmate {
+                winner: PieceColor::White
+            })
+        );
+        let stalemated = fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        assert_eq!(outcome(&stalemated, &[]), Some(Outcome::Stalemate));
+    }
+
+    #[test]
+    fn threefold_repetition() {
+        let mut position = Position::starting();
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        let mut hashes = play(&mut position, &shuffle);
+        assert_eq!(outcome(&position, &hashes), None);
+        hashes.extend(&play(&mut position, &shuffle)[1..]);
+        assert_eq!(
+            outcome(&position, &hashes),
+            Some(Outcome::ThreefoldRepetition)
+        );
+    }
+
+    #[test]
+    fn same_pieces_with_other_rights_do_not_repeat() {
+        // The kings walk out and back, losing castling rights on the first trip.
+        let mut position = fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        let trip = ["e1e2", "e8e7", "e2e1", "e7e8"];
+        let mut hashes = play(&mut position, &trip);
+        hashes.extend(&play(&mut position, &trip)[1..]);
+        assert_eq!(outcome(&position, &hashes), None);
+        hashes.extend(&play(&mut position, &trip)[1..]);
+        assert_eq!(
+            outcome(&position, &hashes),
+            Some(Outcome::ThreefoldRepetition)
+        );
+    }
+
+    #[test]
+    fn fifty_move_rule() {
+        let position = fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80");
+        assert_eq!(outcome(&position, &[]), None);
+        let mut position = position;
+        play(&mut position, &["a1a2"]);
+        assert_eq!(outcome(&position, &[]), Some(Outcome::FiftyMoveRule));
+
+        // Mate on the hundredth half-move still counts as mate.
+        let mated = fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 100 80");
+        assert!(matches!(
+            outcome(&mated, &[]),
+            Some(Outcome::Checkmate { .. })
+        ));
+    }
+
+    #[test]
+    fn insufficient_material() {
+        for drawn in [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1N2K3 w - - 0 1",
+            "4kn2/8/8/8/8/8/8/4K3 w - - 0 1",
+            // Bishops that all stand on squares of one color, whoever owns them.
+            "2b1k3/8/8/8/8/8/8/4KB2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/B1B1K3 w - - 0 1",
+        ] {
+            assert!(has_insufficient_material(&fen(drawn)), "{drawn}");
+            assert_eq!(
+                outcome(&fen(drawn), &[]),
+                Some(Outcome::InsufficientMaterial)
+            );
+        }
+        for playable in [
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/R3K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1",
+            "4kn2/8/8/8/8/8/8/1N2K3 w - - 0 1",
+            // Bishops on squares of both colors.
+            "2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+        ] {
+            assert!(!has_insufficient_material(&fen(playable)), "{playable}");
+        }
+    }
+}
@@ -0,0 +1,36 @@
+// Move generation counting: the number of leaf nodes reachable in exactly `depth` plies.
+// Comparing against published counts is the standard way to validate a move generator.
+use crate::rules::{Move, Position};
+
+pub fn perft(position: &mut Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = position.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves
+        .into_iter()
+        .map(|mv| {
+            let undo = position.make_move(mv);
+            let nodes = perft(position, depth - 1);
+            position.unmake_move(mv, undo);
+            nodes
+        })
+        .sum()
+}
+
+/// Perft split by root move, for narrowing down which move a miscount comes from.
+pub fn divide(position: &mut Position, depth: u32) -> Vec<(Move, u64)> {
+    position
+        .legal_moves()
+        .into_iter()
+        .map(|mv| {
+            let undo = position.make_move(mv);
+            let nodes = perft(position, depth.saturating_sub(1));
+            position.unmake_move(mv, undo);
+            (mv, nodes)
+        })
+        .collect()
+}
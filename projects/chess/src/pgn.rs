@@ -0,0 +1,316 @@
+// Portable Game Notation: reading and writing whole games.
+use std::fmt;
+
+use crate::fen::{FenError, STARTING_FEN};
+use crate::rules::{Move, PieceColor, Position};
+use crate::san::{move_to_san, parse_san};
+
+/// Tags every exported game carries, in the order PGN requires them.
+pub const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+    BadTag(String),
+    BadFen(FenError),
+    IllegalMove { ply: usize, san: String },
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnError::BadTag(line) => write!(f, "malformed tag pair: {line}"),
+            PgnError::BadFen(err) => write!(f, "{err}"),
+            PgnError::IllegalMove { ply, san } => write!(f, "illegal move '{san}' at ply {ply}"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub start: Position,
+    pub moves: Vec<Move>,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.tags.iter_mut().find(|(key, _)| key == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.tags.push((name.to_string(), value)),
+        }
+    }
+
+    /// Write the game out in export format: the seven tag roster first, movetext wrapped at 80 columns.
+    pub fn to_pgn(&self) -> String {
+        let mut tags: Vec<(String, String)> = SEVEN_TAG_ROSTER
+            .iter()
+            .map(|&name| {
+                let unknown = match name {
+                    "Date" => "????.??.??",
+                    "Result" => "*",
+                    _ => "?",
+                };
+                (
+                    name.to_string(),
+                    self.tag(name).unwrap_or(unknown).to_string(),
+                )
+            })
+            .collect();
+        if self.start.to_fen() != STARTING_FEN {
+            tags.push(("SetUp".to_string(), "1".to_string()));
+            tags.push(("FEN".to_string(), self.start.to_fen()));
+        }
+        for (key, value) in &self.tags {
+            if !tags.iter().any(|(existing, _)| existing == key) {
+                tags.push((key.clone(), value.clone()));
+            }
+        }
+
+        let mut out = String::new();
+        for (key, value) in &tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            out.push_str(&format!("[{key} \"{value}\"]\n"));
+        }
+        out.push('\n');
+
+        let mut tokens = Vec::new();
+        let mut position = self.start.clone();
+        for (i, &mv) in self.moves.iter().enumerate() {
+            let number = position.fullmove_number();
+            match position.side_to_move() {
+                PieceColor::White => tokens.push(format!("{number}.")),
+                PieceColor::Black if i == 0 => tokens.push(format!("{number}...")),
+                PieceColor::Black => {}
+            }
+            tokens.push(move_to_san(&position, mv));
+            position.make_move(mv);
+        }
+        tokens.push(self.tag("Result").unwrap_or("*").to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > 80 {
+                out.push_str(&line);
+                out.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        out.push_str(&line);
+        out.push('\n');
+        out
+    }
+}
+
+/// Parse the first game in `text`. Comments, variations and NAGs are skipped.
+pub fn parse_pgn(text: &str) -> Result<PgnGame, PgnError> {
+    let mut tags = Vec::new();
+    let mut movetext = String::new();
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            tags.push(parse_tag(trimmed)?);
+        } else if !trimmed.starts_with('%') {
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+    }
+
+    let start = match tags.iter().find(|(key, _)| key == "FEN") {
+        Some((_, fen)) => Position::from_fen(fen).map_err(PgnError::BadFen)?,
+        None => Position::starting(),
+    };
+
+    let mut position = start.clone();
+    let mut moves = Vec::new();
+    for token in movetext_tokens(&movetext) {
+        let Some(mv) = parse_san(&position, &token) else {
+            return Err(PgnError::IllegalMove {
+                ply: moves.len() + 1,
+                san: token,
+            });
+        };
+        position.make_move(mv);
+        moves.push(mv);
+    }
+
+    Ok(PgnGame { tags, start, moves })
+}
+
+fn parse_tag(line: &str) -> Result<(String, String), PgnError> {
+    let bad = || PgnError::BadTag(line.to_string());
+    let inner = line
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .ok_or_else(bad)?;
+    let (key, value) = inner
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or_else(bad)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .ok_or_else(bad)?;
+    Ok((
+        key.to_string(),
+        value.replace("\\\"", "\"").replace("\\\\", "\\"),
+    ))
+}
+
+/// SAN tokens of the main line, with move numbers, results and annotations removed.
+fn movetext_tokens(movetext: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut variation_depth = 0;
+    let mut chars = movetext.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                flush(&mut current, &mut tokens);
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+            ';' => {
+                flush(&mut current, &mut tokens);
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '(' => {
+                flush(&mut current, &mut tokens);
+                variation_depth += 1;
+            }
+            // A stray `)` closes nothing; the main line carries on.
+            ')' if variation_depth == 0 => flush(&mut current, &mut tokens),
+            ')' => {
+                current.clear();
+                variation_depth -= 1;
+            }
+            c if c.is_whitespace() => {
+                if variation_depth == 0 {
+                    flush(&mut current, &mut tokens);
+                } else {
+                    current.clear();
+                }
+            }
+            c if variation_depth == 0 => current.push(c),
+            _ => {}
+        }
+    }
+    flush(&mut current, &mut tokens);
+    tokens
+}
+
+fn flush(current: &mut String, tokens: &mut Vec<String>) {
+    let token = std::mem::take(current);
+    if matches!(token.as_str(), "1-0" | "0-1" | "1/2-1/2" | "*") {
+        return;
+    }
+    // Strip a leading move number such as `12.` or `12...`, and an `e.p.` after a capture.
+    let token = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+    let token = token.strip_suffix("e.p.").unwrap_or(token);
+    if !token.is_empty() && !token.starts_with('$') {
+        tokens.push(token.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME: &str = r#"[Event "Casual"]
+[White "Alice"]
+[Black "Bob"]
+[Result "1-0"]
+
+1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0
+"#;
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let game = parse_pgn(GAME).unwrap();
+        assert_eq!(game.moves.len(), 7);
+        assert_eq!(game.tag("White"), Some("Alice"));
+
+        let text = game.to_pgn();
+        assert!(text.starts_with("[Event \"Casual\"]\n[Site \"?\"]"));
+        assert!(text.contains("4. Qxf7# 1-0"));
+        let loaded = parse_pgn(&text).unwrap();
+        assert_eq!(loaded.moves, game.moves);
+        assert_eq!(loaded.to_pgn(), text);
+    }
+
+    #[test]
+    fn games_from_a_set_up_position_round_trip() {
+        let mut game = PgnGame {
+            tags: Vec::new(),
+            start: Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 30").unwrap(),
+            moves: Vec::new(),
+        };
+        let mut position = game.start.clone();
+        for san in ["Kd7", "e4", "Kc6"] {
+            let mv = parse_san(&position, san).unwrap();
+            position.make_move(mv);
+            game.moves.push(mv);
+        }
+        let text = game.to_pgn();
+        assert!(text.contains("[SetUp \"1\"]"));
+        assert!(text.contains("30... Kd7 31. e4 Kc6 *"));
+        let loaded = parse_pgn(&text).unwrap();
+        assert_eq!(loaded.start, game.start);
+        assert_eq!(loaded.moves, game.moves);
+    }
+
+    #[test]
+    fn comments_variations_and_annotations_are_skipped() {
+        let text = "1. e4 {best by test} e5 (1... c5 2. Nf3 (2. c3)) 2. Nf3 $1 ; done\n2... Nc6 *";
+        let game = parse_pgn(text).unwrap();
+        assert_eq!(game.moves.len(), 4);
+    }
+
+    #[test]
+    fn stray_closing_parentheses_do_not_hide_moves() {
+        let game = parse_pgn("1. e4 ) e5 2. Nf3) Nc6 *").unwrap();
+        assert_eq!(game.moves.len(), 4);
+    }
+
+    #[test]
+    fn en_passant_marks_are_skipped() {
+        let game = parse_pgn("1. e4 a6 2. e5 d5 3. exd6 e.p. Ra7 4. d7+ Kxd7 *").unwrap();
+        assert_eq!(game.moves.len(), 8);
+        let game = parse_pgn("1. e4 a6 2. e5 d5 3. exd6e.p. *").unwrap();
+        assert_eq!(game.moves.len(), 5);
+    }
+
+    #[test]
+    fn illegal_moves_say_where_they_are() {
+        assert_eq!(
+            parse_pgn("1. e4 e5 2. Ke3 *"),
+            Err(PgnError::IllegalMove {
+                ply: 3,
+                san: "Ke3".to_string()
+            })
+        );
+    }
+}
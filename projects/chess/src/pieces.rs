@@ -1,4 +1,299 @@
 use bevy::prelude::*;
+use chess::rules::{Piece, PieceColor, PieceKind};
+
+use crate::board::square_translation;
+use crate::game::ChessGame;
+
+/// Links a rendered piece back to the square it stands on in the `ChessGame` position.
+#[derive(Component, Debug)]
+pub struct BoardPiece {
+    pub piece: Piece,
+    pub square: u8,
+}
+
+/// Meshes from `pieces.glb` and the two piece materials, loaded once at startup.
+#[derive(Resource)]
+pub struct PieceAssets {
+    pub king: Handle<Mesh>,
+    pub king_cross: Handle<Mesh>,
+    pub pawn: Handle<Mesh>,
+    pub knight_1: Handle<Mesh>,
+    pub knight_2: Handle<Mesh>,
+    pub rook: Handle<Mesh>,
+    pub bishop: Handle<Mesh>,
+    pub queen: Handle<Mesh>,
+    pub white_material: Handle<StandardMaterial>,
+    pub black_material: Handle<StandardMaterial>,
+}
+
+impl PieceAssets {
+    pub fn material(&self, color: PieceColor) -> Handle<StandardMaterial> {
+        match color {
+            PieceColor::White => self.white_material.clone(),
+            PieceColor::Black => self.black_material.clone(),
+        }
+    }
+}
+
+/// Load the piece meshes and spawn every piece of the starting `ChessGame` position.
+pub fn create_pieces(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    game: Res<ChessGame>,
+) {
+    let assets = PieceAssets {
+        king: asset_server.load("models/chess_kit/pieces.glb#Mesh0/Primitive0"),
+        king_cross: asset_server.load("models/chess_kit/pieces.glb#Mesh1/Primitive0"),
+        pawn: asset_server.load("models/chess_kit/pieces.glb#Mesh2/Primitive0"),
+        knight_1: asset_server.load("models/chess_kit/pieces.glb#Mesh3/Primitive0"),
+        knight_2: asset_server.load("models/chess_kit/pieces.glb#Mesh4/Primitive0"),
+        rook: asset_server.load("models/chess_kit/pieces.glb#Mesh5/Primitive0"),
+        bishop: asset_server.load("models/chess_kit/pieces.glb#Mesh6/Primitive0"),
+        queen: asset_server.load("models/chess_kit/pieces.glb#Mesh7/Primitive0"),
+        white_material: materials.add(Color::srgb(1., 0.8, 0.8)),
+        black_material: materials.add(Color::srgb(0., 0.2, 0.2)),
+    };
+
+    for (square, piece) in game.position.pieces() {
+        spawn_piece(&mut commands, &assets, piece, square);
+    }
+    commands.insert_resource(assets);
+}
+
+/// Spawn the mesh for `piece` on `square` and tag it with a `BoardPiece`.
+pub fn spawn_piece(
+    commands: &mut Commands,
+    assets: &PieceAssets,
+    piece: Piece,
+    square: u8,
+) -> Entity {
+    let material = assets.material(piece.color);
+    let position = square_translation(square);
+    let entity = match piece.kind {
+        PieceKind::King => spawn_king(
+            commands,
+            material,
+            assets.king.clone(),
+            assets.king_cross.clone(),
+            position,
+        ),
+        PieceKind::Queen => spawn_queen(commands, material, assets.queen.clone(), position),
+        PieceKind::Rook => spawn_rook(commands, material, assets.rook.clone(), position),
+        PieceKind::Bishop => spawn_bishop(commands, material, assets.bishop.clone(), position),
+        PieceKind::Knight => spawn_knight(
+            commands,
+            material,
+            assets.knight_1.clone(),
+            assets.knight_2.clone(),
+            position,
+        ),
+        PieceKind::Pawn => spawn_pawn(commands, material, assets.pawn.clone(), position),
+    };
+    commands.entity(entity).insert(BoardPiece { piece, square });
+    entity
+}
+
+/// Seconds a piece takes to slide from one square to another.
+const MOVE_SECONDS: f32 = 0.35;
+
+/// How high knights and castling rooks lift to hop over the pieces in their way.
+const HOP_HEIGHT: f32 = 0.8;
+
+/// Slides a piece's parent `Transform` between two squares, along an arc if `lift` is above zero.
+#[derive(Component)]
+pub struct PieceTween {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub lift: f32,
+    pub timer: Timer,
+}
+
+impl PieceTween {
+    pub fn new(start: Vec3, end: Vec3, lift: f32) -> Self {
+        PieceTween {
+            start,
+            end,
+            lift,
+            timer: Timer::from_seconds(MOVE_SECONDS, TimerMode::Once),
+        }
+    }
+}
+
+/// Grows a piece in from nothing or shrinks it away, despawning it once it is gone.
+/// Shrinking pieces are no longer `BoardPiece`s, so later syncs leave them alone.
+#[derive(Component)]
+pub struct PieceFade {
+    pub appearing: bool,
+    pub timer: Timer,
+}
+
+impl PieceFade {
+    pub fn appear() -> Self {
+        PieceFade {
+            appearing: true,
+            timer: Timer::from_seconds(MOVE_SECONDS, TimerMode::Once),
+        }
+    }
+
+    pub fn vanish() -> Self {
+        PieceFade {
+            appearing: false,
+            timer: Timer::from_seconds(MOVE_SECONDS, TimerMode::Once),
+        }
+    }
+}
+
+/// Bring the rendered pieces in line with the game position whenever it changes.
+///
+/// Pieces that left their square are matched with the nearest empty-handed square that now
+/// holds the same piece and slide there. A pawn that vanished next to a new piece of its own
+/// color was promoted: it slides onto the promotion square and fades out as the new piece
+/// fades in. Other leftovers are captured pieces and shrink away while the capturer arrives,
+/// which covers en passant too, and anything still missing (pieces restored by stepping back)
+/// fades in.
+pub fn sync_pieces(
+    mut commands: Commands,
+    game: Res<ChessGame>,
+    assets: Res<PieceAssets>,
+    mut pieces: Query<(Entity, &mut BoardPiece, &Transform)>,
+) {
+    // `create_pieces` already spawned the initial position.
+    if !game.is_changed() || game.is_added() {
+        return;
+    }
+
+    let mut settled = [false; 64];
+    let mut stale = Vec::new();
+    for (entity, board_piece, _) in pieces.iter() {
+        let square = board_piece.square;
+        if game.position.piece_at(square) == Some(board_piece.piece) && !settled[square as usize] {
+            settled[square as usize] = true;
+        } else {
+            stale.push(entity);
+        }
+    }
+
+    let mut moved = Vec::new();
+    let mut missing = Vec::new();
+    for (square, piece) in game.position.pieces() {
+        if settled[square as usize] {
+            continue;
+        }
+        let target = square_translation(square);
+        let nearest = stale
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &entity)| {
+                let (_, board_piece, transform) = pieces.get(entity).ok()?;
+                (board_piece.piece == piece)
+                    .then(|| (i, transform.translation.distance_squared(target)))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i);
+
+        match nearest {
+            Some(i) => {
+                let entity = stale.swap_remove(i);
+                let Ok((_, mut board_piece, transform)) = pieces.get_mut(entity) else {
+                    continue;
+                };
+                board_piece.square = square;
+                moved.push((entity, piece, transform.translation, target));
+            }
+            None => missing.push((square, piece)),
+        }
+    }
+
+    // Only castling moves a king and a rook at once; the rook hops over the king.
+    let moved_kind = |kind| moved.iter().any(|(_, piece, _, _)| piece.kind == kind);
+    let castling = moved_kind(PieceKind::King) && moved_kind(PieceKind::Rook);
+    for (entity, piece, start, end) in moved {
+        let hops = piece.kind == PieceKind::Knight || (castling && piece.kind == PieceKind::Rook);
+        let lift = if hops { HOP_HEIGHT } else { 0.0 };
+        commands
+            .entity(entity)
+            .insert(PieceTween::new(start, end, lift));
+    }
+
+    for (square, piece) in missing {
+        let promoted_pawn = (piece.kind != PieceKind::Pawn)
+            .then(|| {
+                stale.iter().position(|&entity| {
+                    pieces.get(entity).is_ok_and(|(_, board_piece, _)| {
+                        board_piece.piece == Piece::new(piece.color, PieceKind::Pawn)
+                    })
+                })
+            })
+            .flatten();
+        if let Some(i) = promoted_pawn {
+            let entity = stale.swap_remove(i);
+            if let Ok((_, _, transform)) = pieces.get(entity) {
+                commands.entity(entity).insert(PieceTween::new(
+                    transform.translation,
+                    square_translation(square),
+                    0.0,
+                ));
+            }
+            commands
+                .entity(entity)
+                .remove::<BoardPiece>()
+                .insert(PieceFade::vanish());
+        }
+        let entity = spawn_piece(&mut commands, &assets, piece, square);
+        commands.entity(entity).insert((
+            PieceFade::appear(),
+            Transform::from_translation(square_translation(square)).with_scale(Vec3::ZERO),
+        ));
+    }
+
+    for entity in stale {
+        commands
+            .entity(entity)
+            .remove::<(BoardPiece, PieceTween)>()
+            .insert(PieceFade::vanish());
+    }
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+pub fn animate_pieces(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut tweens: Query<(Entity, &mut PieceTween, &mut Transform)>,
+) {
+    for (entity, mut tween, mut transform) in tweens.iter_mut() {
+        tween.timer.tick(time.delta());
+        let t = tween.timer.fraction();
+        let arc = Vec3::Y * tween.lift * 4.0 * t * (1.0 - t);
+        transform.translation = tween.start.lerp(tween.end, smoothstep(t)) + arc;
+        if tween.timer.finished() {
+            commands.entity(entity).remove::<PieceTween>();
+        }
+    }
+}
+
+pub fn fade_pieces(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut fades: Query<(Entity, &mut PieceFade, &mut Transform)>,
+) {
+    for (entity, mut fade, mut transform) in fades.iter_mut() {
+        fade.timer.tick(time.delta());
+        let t = smoothstep(fade.timer.fraction());
+        transform.scale = Vec3::splat(if fade.appearing { t } else { 1.0 - t });
+        if !fade.timer.finished() {
+            continue;
+        }
+        if fade.appearing {
+            commands.entity(entity).remove::<PieceFade>();
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
 
 pub fn spawn_king(
     commands: &mut Commands,
@@ -6,7 +301,7 @@ pub fn spawn_king(
     mesh: Handle<Mesh>,
     mesh_cross: Handle<Mesh>,
     position: Vec3,
-) {
+) -> Entity {
     commands
         // Spawn parent entity
         .spawn((Transform::from_translation(position), Visibility::Visible))
@@ -26,7 +321,8 @@ pub fn spawn_king(
                     transform
                 },
             ));
-        });
+        })
+        .id()
 }
 
 pub fn spawn_knight(
@@ -35,7 +331,7 @@ pub fn spawn_knight(
     mesh1: Handle<Mesh>,
     mesh2: Handle<Mesh>,
     position: Vec3,
-) {
+) -> Entity {
     commands
         // Spawn parent entity
         .spawn((Transform::from_translation(position), Visibility::Visible))
@@ -51,7 +347,8 @@ pub fn spawn_knight(
                     .with_scale(Vec3::new(0.2, 0.2, 0.2));
                 transform
             }));
-        });
+        })
+        .id()
 }
 
 pub fn spawn_queen(
@@ -59,21 +356,19 @@ pub fn spawn_queen(
     material: Handle<StandardMaterial>,
     mesh: Handle<Mesh>,
     position: Vec3,
-) {
+) -> Entity {
     commands
         // Spawn parent entity
-        .spawn((
-            Transform::from_translation(position - Vec3::new(0., 0., 2.0)),
-            Visibility::Visible,
-        ))
+        .spawn((Transform::from_translation(position), Visibility::Visible))
         // Add children to the parent
         .with_children(|parent| {
             parent.spawn((Mesh3d(mesh.clone()), MeshMaterial3d(material.clone()), {
-                let transform = Transform::from_translation(Vec3::new(-0.2, 0., 0.95))
+                let transform = Transform::from_translation(Vec3::new(-0.2, 0., -1.05))
                     .with_scale(Vec3::new(0.2, 0.2, 0.2));
                 transform
             }));
-        });
+        })
+        .id()
 }
 
 pub fn spawn_bishop(
@@ -81,7 +376,7 @@ pub fn spawn_bishop(
     material: Handle<StandardMaterial>,
     mesh: Handle<Mesh>,
     position: Vec3,
-) {
+) -> Entity {
     commands
         // Spawn parent entity
         .spawn((Transform::from_translation(position), Visibility::Visible))
@@ -92,7 +387,8 @@ pub fn spawn_bishop(
                     .with_scale(Vec3::new(0.2, 0.2, 0.2));
                 transform
             }));
-        });
+        })
+        .id()
 }
 
 pub fn spawn_rook(
@@ -100,7 +396,7 @@ pub fn spawn_rook(
     material: Handle<StandardMaterial>,
     mesh: Handle<Mesh>,
     position: Vec3,
-) {
+) -> Entity {
     commands
         // Spawn parent entity
         .spawn((Transform::from_translation(position), Visibility::Visible))
@@ -111,7 +407,8 @@ pub fn spawn_rook(
                     .with_scale(Vec3::new(0.2, 0.2, 0.2));
                 transform
             }));
-        });
+        })
+        .id()
 }
 
 pub fn spawn_pawn(
@@ -119,7 +416,7 @@ pub fn spawn_pawn(
     material: Handle<StandardMaterial>,
     mesh: Handle<Mesh>,
     position: Vec3,
-) {
+) -> Entity {
     commands
         // Spawn parent entity
         .spawn((Transform::from_translation(position), Visibility::Visible))
@@ -130,5 +427,6 @@ pub fn spawn_pawn(
                     .with_scale(Vec3::new(0.2, 0.2, 0.2));
                 transform
             }));
-        });
+        })
+        .id()
 }
@@ -0,0 +1,145 @@
+use bevy::prelude::*;
+use chess::rules::{square_name, Move, PieceKind};
+
+use crate::game::ChessGame;
+
+/// A pawn move to the last rank waiting for the player to pick the piece it becomes.
+#[derive(Resource, Default)]
+pub struct PendingPromotion {
+    pub squares: Option<(u8, u8)>,
+}
+
+impl PendingPromotion {
+    /// The legal move that promotes to `kind`, if the pending move is still playable.
+    fn legal_move(&self, game: &ChessGame, kind: PieceKind) -> Option<Move> {
+        let (from, to) = self.squares?;
+        if !game.accepts_moves() {
+            return None;
+        }
+        game.position
+            .legal_moves_from(from)
+            .into_iter()
+            .find(|mv| mv.to == to && mv.promotion == Some(kind))
+    }
+}
+
+#[derive(Component)]
+pub struct PromotionChooser;
+
+#[derive(Component)]
+pub struct PromotionButton(PieceKind);
+
+const CHOICES: [(PieceKind, &str, KeyCode); 4] = [
+    (PieceKind::Queen, "1 Queen", KeyCode::Digit1),
+    (PieceKind::Rook, "2 Rook", KeyCode::Digit2),
+    (PieceKind::Bishop, "3 Bishop", KeyCode::Digit3),
+    (PieceKind::Knight, "4 Knight", KeyCode::Digit4),
+];
+
+/// Show the chooser while a promotion is pending and remove it once it is settled.
+pub fn show_promotion_chooser(
+    mut commands: Commands,
+    pending: Res<PendingPromotion>,
+    choosers: Query<Entity, With<PromotionChooser>>,
+) {
+    if !pending.is_changed() {
+        return;
+    }
+    for entity in choosers.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some((_, to)) = pending.squares else {
+        return;
+    };
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(20.0),
+                width: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            PromotionChooser,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(format!("Promote on {}", square_name(to))),
+                TextFont {
+                    font_size: 24.0,
+                    ..default()
+                },
+            ));
+            parent
+                .spawn(Node {
+                    column_gap: Val::Px(8.0),
+                    ..default()
+                })
+                .with_children(|row| {
+                    for (kind, label, _) in CHOICES {
+                        row.spawn((
+                            Button,
+                            Node {
+                                padding: UiRect::axes(Val::Px(16.0), Val::Px(6.0)),
+                                ..default()
+                            },
+                            BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                            PromotionButton(kind),
+                        ))
+                        .with_children(|button| {
+                            button.spawn((
+                                Text::new(label),
+                                TextFont {
+                                    font_size: 24.0,
+                                    ..default()
+                                },
+                            ));
+                        });
+                    }
+                });
+        });
+}
+
+/// Play the pending promotion with the piece picked by button or by the digit key shown on it.
+/// Escape cancels, and a pending move that stopped being legal, for instance because a new
+/// position was loaded, is dropped.
+pub fn choose_promotion(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut buttons: Query<(&Interaction, &PromotionButton, &mut BackgroundColor)>,
+    mut pending: ResMut<PendingPromotion>,
+    mut game: ResMut<ChessGame>,
+) {
+    if pending.squares.is_none() {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Escape) || pending.legal_move(&game, PieceKind::Queen).is_none() {
+        pending.squares = None;
+        return;
+    }
+
+    let mut chosen = CHOICES
+        .iter()
+        .find(|(_, _, key)| keys.just_pressed(*key))
+        .map(|(kind, _, _)| *kind);
+    for (interaction, button, mut background) in buttons.iter_mut() {
+        let color = match *interaction {
+            Interaction::Pressed => {
+                chosen = Some(button.0);
+                continue;
+            }
+            Interaction::Hovered => Color::srgb(0.35, 0.35, 0.35),
+            Interaction::None => Color::srgb(0.2, 0.2, 0.2),
+        };
+        if background.0 != color {
+            background.0 = color;
+        }
+    }
+
+    if let Some(mv) = chosen.and_then(|kind| pending.legal_move(&game, kind)) {
+        game.play(mv);
+        pending.squares = None;
+    }
+}
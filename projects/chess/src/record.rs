@@ -0,0 +1,121 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use chess::rules::PieceColor;
+
+use crate::board::SelectedSquare;
+use crate::game::{read_pgn, ChessGame};
+
+/// Where S saves the current game.
+const SAVE_PATH: &str = "game.pgn";
+
+/// Open a PGN file at its first move for review.
+#[derive(Event)]
+pub struct LoadPgnEvent(pub PathBuf);
+
+#[derive(Component)]
+pub struct MoveListText;
+
+pub fn spawn_move_list(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 18.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            max_width: Val::Px(260.0),
+            ..default()
+        },
+        MoveListText,
+    ));
+}
+
+/// Show the recorded moves in SAN, with the move the board is showing in brackets.
+pub fn update_move_list(game: Res<ChessGame>, mut text: Query<&mut Text, With<MoveListText>>) {
+    if !game.is_changed() {
+        return;
+    }
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+
+    let mut moves = game.start.clone();
+    let mut lines = Vec::new();
+    for (i, played) in game.history.iter().enumerate() {
+        let san = if i + 1 == game.cursor {
+            format!("[{}]", played.san)
+        } else {
+            played.san.clone()
+        };
+        match moves.side_to_move() {
+            PieceColor::White => lines.push(format!("{}. {}", moves.fullmove_number(), san)),
+            PieceColor::Black => match lines.last_mut() {
+                Some(line) if i > 0 => line.push_str(&format!(" {san}")),
+                _ => lines.push(format!("{}... {}", moves.fullmove_number(), san)),
+            },
+        }
+        moves.make_move(played.mv);
+    }
+    text.0 = lines.join("\n");
+}
+
+/// Left/Right step through the record, Home/End jump to either end.
+pub fn step_through_game(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut game: ResMut<ChessGame>,
+    mut selected: ResMut<SelectedSquare>,
+) {
+    let stepped = if keys.just_pressed(KeyCode::ArrowLeft) {
+        game.step_back()
+    } else if keys.just_pressed(KeyCode::ArrowRight) {
+        game.step_forward()
+    } else if keys.just_pressed(KeyCode::Home) {
+        let mut any = false;
+        while game.step_back() {
+            any = true;
+        }
+        any
+    } else if keys.just_pressed(KeyCode::End) {
+        let mut any = false;
+        while game.step_forward() {
+            any = true;
+        }
+        any
+    } else {
+        false
+    };
+    if stepped {
+        selected.entity = None;
+    }
+}
+
+/// Press S to save the game so far as PGN.
+pub fn save_pgn(keys: Res<ButtonInput<KeyCode>>, game: Res<ChessGame>) {
+    if !keys.just_pressed(KeyCode::KeyS) {
+        return;
+    }
+    match std::fs::write(SAVE_PATH, game.to_pgn().to_pgn()) {
+        Ok(()) => info!("Saved game to {SAVE_PATH}"),
+        Err(err) => error!("Could not save {SAVE_PATH}: {err}"),
+    }
+}
+
+pub fn load_pgn(
+    mut events: EventReader<LoadPgnEvent>,
+    mut game: ResMut<ChessGame>,
+    mut selected: ResMut<SelectedSquare>,
+) {
+    for LoadPgnEvent(path) in events.read() {
+        match read_pgn(path) {
+            Ok(pgn) => {
+                *game = ChessGame::from_pgn(&pgn);
+                selected.entity = None;
+            }
+            Err(err) => warn!("{err}"),
+        }
+    }
+}
@@ -0,0 +1,793 @@
+// Pure chess rules: board model and legal move generation.
+// Nothing in here knows about Bevy, so positions can be built and checked without a window.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PieceColor {
+    White,
+    Black,
+}
+
+impl PieceColor {
+    pub fn opposite(self) -> Self {
+        match self {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        }
+    }
+
+    pub fn index(self) -> usize {
+        match self {
+            PieceColor::White => 0,
+            PieceColor::Black => 1,
+        }
+    }
+
+    /// Rank the pieces of this color start on.
+    pub fn back_rank(self) -> u8 {
+        match self {
+            PieceColor::White => 0,
+            PieceColor::Black => 7,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PieceKind {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+impl PieceKind {
+    pub const ALL: [PieceKind; 6] = [
+        PieceKind::Pawn,
+        PieceKind::Knight,
+        PieceKind::Bishop,
+        PieceKind::Rook,
+        PieceKind::Queen,
+        PieceKind::King,
+    ];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Piece {
+    pub color: PieceColor,
+    pub kind: PieceKind,
+}
+
+impl Piece {
+    pub fn new(color: PieceColor, kind: PieceKind) -> Self {
+        Piece { color, kind }
+    }
+}
+
+/// Squares are indexed 0..64 as `rank * 8 + file`, so a1 = 0, h1 = 7 and h8 = 63.
+pub fn square(file: u8, rank: u8) -> u8 {
+    rank * 8 + file
+}
+
+pub fn file_of(sq: u8) -> u8 {
+    sq % 8
+}
+
+pub fn rank_of(sq: u8) -> u8 {
+    sq / 8
+}
+
+pub fn square_name(sq: u8) -> String {
+    format!("{}{}", (b'a' + file_of(sq)) as char, rank_of(sq) + 1)
+}
+
+pub fn parse_square(name: &str) -> Option<u8> {
+    let bytes = name.as_bytes();
+    if bytes.len() != 2 {
+        return None;
+    }
+    let file = bytes[0].wrapping_sub(b'a');
+    let rank = bytes[1].wrapping_sub(b'1');
+    if file < 8 && rank < 8 {
+        Some(square(file, rank))
+    } else {
+        None
+    }
+}
+
+/// Square reached from `sq` by stepping `df` files and `dr` ranks, if it is still on the board.
+fn offset(sq: u8, df: i8, dr: i8) -> Option<u8> {
+    let file = file_of(sq) as i8 + df;
+    let rank = rank_of(sq) as i8 + dr;
+    if (0..8).contains(&file) && (0..8).contains(&rank) {
+        Some(square(file as u8, rank as u8))
+    } else {
+        None
+    }
+}
+
+const KNIGHT_STEPS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const KING_STEPS: [(i8, i8); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+const ROOK_DIRS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CastleSide {
+    King,
+    Queen,
+}
+
+impl CastleSide {
+    pub fn index(self) -> usize {
+        match self {
+            CastleSide::King => 0,
+            CastleSide::Queen => 1,
+        }
+    }
+
+    /// File the king lands on after castling to this side.
+    pub fn king_target_file(self) -> u8 {
+        match self {
+            CastleSide::King => 6,
+            CastleSide::Queen => 2,
+        }
+    }
+
+    /// File the rook lands on after castling to this side.
+    pub fn rook_target_file(self) -> u8 {
+        match self {
+            CastleSide::King => 5,
+            CastleSide::Queen => 3,
+        }
+    }
+}
+
+/// Remaining castling rights, stored as the file of the rook that may still castle.
+/// Keeping the file rather than a flag lets the same rules cover non-standard rook placement.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CastlingRights {
+    rooks: [[Option<u8>; 2]; 2],
+}
+
+impl CastlingRights {
+    pub fn standard() -> Self {
+        CastlingRights {
+            rooks: [[Some(7), Some(0)], [Some(7), Some(0)]],
+        }
+    }
+
+    pub fn rook_file(&self, color: PieceColor, side: CastleSide) -> Option<u8> {
+        self.rooks[color.index()][side.index()]
+    }
+
+    pub fn set(&mut self, color: PieceColor, side: CastleSide, rook_file: Option<u8>) {
+        self.rooks[color.index()][side.index()] = rook_file;
+    }
+
+    pub fn clear(&mut self, color: PieceColor) {
+        self.rooks[color.index()] = [None, None];
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rooks.iter().flatten().all(Option::is_none)
+    }
+
+    /// Drop any right whose rook starts on `sq`, because that rook moved or was captured.
+    fn clear_rook_square(&mut self, sq: u8) {
+        for color in [PieceColor::White, PieceColor::Black] {
+            if rank_of(sq) != color.back_rank() {
+                continue;
+            }
+            for side in [CastleSide::King, CastleSide::Queen] {
+                if self.rook_file(color, side) == Some(file_of(sq)) {
+                    self.set(color, side, None);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MoveKind {
+    Normal,
+    DoublePush,
+    EnPassant,
+    Castle(CastleSide),
+}
+
+/// A move on the board. For castling `from`/`to` are the king's squares.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub from: u8,
+    pub to: u8,
+    pub promotion: Option<PieceKind>,
+    pub kind: MoveKind,
+}
+
+impl Move {
+    pub fn new(from: u8, to: u8) -> Self {
+        Move {
+            from,
+            to,
+            promotion: None,
+            kind: MoveKind::Normal,
+        }
+    }
+}
+
+/// Everything `make_move` overwrites that cannot be recomputed from the move itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Undo {
+    pub captured: Option<Piece>,
+    pub castling: CastlingRights,
+    pub en_passant: Option<u8>,
+    pub halfmove_clock: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Position {
+    board: [Option<Piece>; 64],
+    side_to_move: PieceColor,
+    castling: CastlingRights,
+    en_passant: Option<u8>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position::starting()
+    }
+}
+
+impl Position {
+    /// A board with no pieces, white to move.
+    pub fn empty() -> Self {
+        Position {
+            board: [None; 64],
+            side_to_move: PieceColor::White,
+            castling: CastlingRights::default(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+
+    pub fn starting() -> Self {
+        Position::from_back_rank([
+            PieceKind::Rook,
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Queen,
+            PieceKind::King,
+            PieceKind::Bishop,
+            PieceKind::Knight,
+            PieceKind::Rook,
+        ])
+    }
+
+    /// A starting position with `back_rank` (a-file first) mirrored for both sides behind full
+    /// pawn rows. Each side may castle with the outermost rook on either side of its king.
+    pub fn from_back_rank(back_rank: [PieceKind; 8]) -> Self {
+        let mut position = Position::empty();
+        for (file, kind) in back_rank.into_iter().enumerate() {
+            let file = file as u8;
+            position.set_piece(square(file, 0), Some(Piece::new(PieceColor::White, kind)));
+            position.set_piece(
+                square(file, 1),
+                Some(Piece::new(PieceColor::White, PieceKind::Pawn)),
+            );
+            position.set_piece(
+                square(file, 6),
+                Some(Piece::new(PieceColor::Black, PieceKind::Pawn)),
+            );
+            position.set_piece(square(file, 7), Some(Piece::new(PieceColor::Black, kind)));
+        }
+        if let Some(king) = back_rank.iter().position(|&kind| kind == PieceKind::King) {
+            let rooks: Vec<u8> = (0..8u8)
+                .filter(|&file| back_rank[file as usize] == PieceKind::Rook)
+                .collect();
+            let king_side = rooks
+                .iter()
+                .copied()
+                .filter(|&file| file > king as u8)
+                .max();
+            let queen_side = rooks
+                .iter()
+                .copied()
+                .filter(|&file| file < (king as u8))
+                .min();
+            for color in [PieceColor::White, PieceColor::Black] {
+                position.castling.set(color, CastleSide::King, king_side);
+                position.castling.set(color, CastleSide::Queen, queen_side);
+            }
+        }
+        position
+    }
+
+    pub fn piece_at(&self, sq: u8) -> Option<Piece> {
+        self.board[sq as usize]
+    }
+
+    pub fn set_piece(&mut self, sq: u8, piece: Option<Piece>) {
+        self.board[sq as usize] = piece;
+    }
+
+    /// Every occupied square with the piece on it, a1 first.
+    pub fn pieces(&self) -> impl Iterator<Item = (u8, Piece)> + '_ {
+        self.board
+            .iter()
+            .enumerate()
+            .filter_map(|(sq, piece)| piece.map(|piece| (sq as u8, piece)))
+    }
+
+    pub fn side_to_move(&self) -> PieceColor {
+        self.side_to_move
+    }
+
+    pub fn set_side_to_move(&mut self, color: PieceColor) {
+        self.side_to_move = color;
+    }
+
+    pub fn castling(&self) -> CastlingRights {
+        self.castling
+    }
+
+    pub fn set_castling(&mut self, castling: CastlingRights) {
+        self.castling = castling;
+    }
+
+    pub fn en_passant(&self) -> Option<u8> {
+        self.en_passant
+    }
+
+    pub fn set_en_passant(&mut self, sq: Option<u8>) {
+        self.en_passant = sq;
+    }
+
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn set_halfmove_clock(&mut self, clock: u32) {
+        self.halfmove_clock = clock;
+    }
+
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    pub fn set_fullmove_number(&mut self, number: u32) {
+        self.fullmove_number = number;
+    }
+
+    pub fn king_square(&self, color: PieceColor) -> Option<u8> {
+        self.pieces()
+            .find(|(_, piece)| *piece == Piece::new(color, PieceKind::King))
+            .map(|(sq, _)| sq)
+    }
+
+    pub fn in_check(&self) -> bool {
+        self.is_in_check(self.side_to_move)
+    }
+
+    pub fn is_in_check(&self, color: PieceColor) -> bool {
+        self.king_square(color)
+            .is_some_and(|king| self.is_attacked(king, color.opposite()))
+    }
+
+    /// Whether any piece of color `by` attacks `sq`.
+    pub fn is_attacked(&self, sq: u8, by: PieceColor) -> bool {
+        let is = |target: Option<u8>, kind: PieceKind| {
+            target.is_some_and(|t| self.piece_at(t) == Some(Piece::new(by, kind)))
+        };
+
+        // A pawn of color `by` attacks `sq` from one rank behind it, from its point of view.
+        let pawn_rank = match by {
+            PieceColor::White => -1,
+            PieceColor::Black => 1,
+        };
+        if is(offset(sq, -1, pawn_rank), PieceKind::Pawn)
+            || is(offset(sq, 1, pawn_rank), PieceKind::Pawn)
+        {
+            return true;
+        }
+        if KNIGHT_STEPS
+            .iter()
+            .any(|&(df, dr)| is(offset(sq, df, dr), PieceKind::Knight))
+        {
+            return true;
+        }
+        if KING_STEPS
+            .iter()
+            .any(|&(df, dr)| is(offset(sq, df, dr), PieceKind::King))
+        {
+            return true;
+        }
+
+        let slider_hits = |dirs: &[(i8, i8)], kind: PieceKind| {
+            dirs.iter().any(|&(df, dr)| {
+                let mut current = sq;
+                while let Some(next) = offset(current, df, dr) {
+                    if let Some(piece) = self.piece_at(next) {
+                        return piece.color == by
+                            && (piece.kind == kind || piece.kind == PieceKind::Queen);
+                    }
+                    current = next;
+                }
+                false
+            })
+        };
+        slider_hits(&ROOK_DIRS, PieceKind::Rook) || slider_hits(&BISHOP_DIRS, PieceKind::Bishop)
+    }
+
+    /// All moves that follow piece movement rules but may leave the mover's king in check.
+    pub fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(64);
+        let us = self.side_to_move;
+
+        for (from, piece) in self.pieces() {
+            if piece.color != us {
+                continue;
+            }
+            match piece.kind {
+                PieceKind::Pawn => self.pawn_moves(from, &mut moves),
+                PieceKind::Knight => self.step_moves(from, &KNIGHT_STEPS, &mut moves),
+                PieceKind::Bishop => self.slide_moves(from, &BISHOP_DIRS, &mut moves),
+                PieceKind::Rook => self.slide_moves(from, &ROOK_DIRS, &mut moves),
+                PieceKind::Queen => {
+                    self.slide_moves(from, &ROOK_DIRS, &mut moves);
+                    self.slide_moves(from, &BISHOP_DIRS, &mut moves);
+                }
+                PieceKind::King => {
+                    self.step_moves(from, &KING_STEPS, &mut moves);
+                    self.castle_moves(from, &mut moves);
+                }
+            }
+        }
+        moves
+    }
+
+    /// All legal moves for the side to move.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut scratch = self.clone();
+        let us = self.side_to_move;
+        self.pseudo_legal_moves()
+            .into_iter()
+            .filter(|&mv| {
+                let undo = scratch.make_move(mv);
+                let legal = !scratch.is_in_check(us);
+                scratch.unmake_move(mv, undo);
+                legal
+            })
+            .collect()
+    }
+
+    /// Legal moves of the piece standing on `from`.
+    pub fn legal_moves_from(&self, from: u8) -> Vec<Move> {
+        self.legal_moves()
+            .into_iter()
+            .filter(|mv| mv.from == from)
+            .collect()
+    }
+
+    pub fn is_legal(&self, mv: Move) -> bool {
+        self.legal_moves().contains(&mv)
+    }
+
+    fn is_enemy(&self, sq: u8) -> bool {
+        self.piece_at(sq)
+            .is_some_and(|piece| piece.color != self.side_to_move)
+    }
+
+    fn step_moves(&self, from: u8, steps: &[(i8, i8)], moves: &mut Vec<Move>) {
+        for &(df, dr) in steps {
+            if let Some(to) = offset(from, df, dr) {
+                if self.piece_at(to).is_none() || self.is_enemy(to) {
+                    moves.push(Move::new(from, to));
+                }
+            }
+        }
+    }
+
+    fn slide_moves(&self, from: u8, dirs: &[(i8, i8)], moves: &mut Vec<Move>) {
+        for &(df, dr) in dirs {
+            let mut current = from;
+            while let Some(to) = offset(current, df, dr) {
+                match self.piece_at(to) {
+                    None => moves.push(Move::new(from, to)),
+                    Some(_) => {
+                        if self.is_enemy(to) {
+                            moves.push(Move::new(from, to));
+                        }
+                        break;
+                    }
+                }
+                current = to;
+            }
+        }
+    }
+
+    fn pawn_moves(&self, from: u8, moves: &mut Vec<Move>) {
+        let us = self.side_to_move;
+        let (forward, start_rank, last_rank) = match us {
+            PieceColor::White => (1, 1, 7),
+            PieceColor::Black => (-1, 6, 0),
+        };
+
+        let push = |moves: &mut Vec<Move>, to: u8, kind: MoveKind| {
+            if rank_of(to) == last_rank {
+                for promotion in [
+                    PieceKind::Queen,
+                    PieceKind::Rook,
+                    PieceKind::Bishop,
+                    PieceKind::Knight,
+                ] {
+                    moves.push(Move {
+                        from,
+                        to,
+                        promotion: Some(promotion),
+                        kind,
+                    });
+                }
+            } else {
+                moves.push(Move {
+                    from,
+                    to,
+                    promotion: None,
+                    kind,
+                });
+            }
+        };
+
+        if let Some(one) = offset(from, 0, forward) {
+            if self.piece_at(one).is_none() {
+                push(moves, one, MoveKind::Normal);
+                if rank_of(from) == start_rank {
+                    if let Some(two) = offset(one, 0, forward) {
+                        if self.piece_at(two).is_none() {
+                            push(moves, two, MoveKind::DoublePush);
+                        }
+                    }
+                }
+            }
+        }
+
+        for df in [-1, 1] {
+            let Some(to) = offset(from, df, forward) else {
+                continue;
+            };
+            if self.is_enemy(to) {
+                push(moves, to, MoveKind::Normal);
+            } else if self.en_passant == Some(to) {
+                push(moves, to, MoveKind::EnPassant);
+            }
+        }
+    }
+
+    fn castle_moves(&self, king_from: u8, moves: &mut Vec<Move>) {
+        let us = self.side_to_move;
+        let back_rank = us.back_rank();
+        if rank_of(king_from) != back_rank {
+            return;
+        }
+
+        for side in [CastleSide::King, CastleSide::Queen] {
+            let Some(rook_file) = self.castling.rook_file(us, side) else {
+                continue;
+            };
+            let rook_from = square(rook_file, back_rank);
+            if self.piece_at(rook_from) != Some(Piece::new(us, PieceKind::Rook)) {
+                continue;
+            }
+            let king_to = square(side.king_target_file(), back_rank);
+            let rook_to = square(side.rook_target_file(), back_rank);
+
+            // Every square either piece passes over or lands on must be empty,
+            // apart from the castling king and rook themselves.
+            let files = [
+                file_of(king_from),
+                file_of(king_to),
+                rook_file,
+                file_of(rook_to),
+            ];
+            let low = *files.iter().min().unwrap();
+            let high = *files.iter().max().unwrap();
+            let blocked = (low..=high)
+                .map(|file| square(file, back_rank))
+                .any(|sq| sq != king_from && sq != rook_from && self.piece_at(sq).is_some());
+            if blocked {
+                continue;
+            }
+
+            // The king may not start in, pass through or land on an attacked square.
+            let (start, end) = if king_from <= king_to {
+                (king_from, king_to)
+            } else {
+                (king_to, king_from)
+            };
+            if (start..=end).any(|sq| self.is_attacked(sq, us.opposite())) {
+                continue;
+            }
+
+            moves.push(Move {
+                from: king_from,
+                to: king_to,
+                promotion: None,
+                kind: MoveKind::Castle(side),
+            });
+        }
+    }
+
+    /// Square of the rook that castles with `side` for `color`, using the current rights.
+    pub fn castling_rook_square(&self, color: PieceColor, side: CastleSide) -> Option<u8> {
+        self.castling
+            .rook_file(color, side)
+            .map(|file| square(file, color.back_rank()))
+    }
+
+    /// For a castling move, the square of the rook it castles with. Clicking or sending that
+    /// square is how castling is entered when the king itself would not move, as in Chess960.
+    pub fn castling_rook_of(&self, mv: Move) -> Option<u8> {
+        match mv.kind {
+            MoveKind::Castle(side) => self.castling_rook_square(self.side_to_move, side),
+            _ => None,
+        }
+    }
+
+    /// Whether any remaining castling right needs the Chess960 conventions, because the king is
+    /// off the e-file or the rook is off the corner.
+    pub fn has_chess960_castling(&self) -> bool {
+        [PieceColor::White, PieceColor::Black]
+            .into_iter()
+            .any(|color| {
+                let king_on_e_file = self
+                    .king_square(color)
+                    .is_some_and(|king| king == square(4, color.back_rank()));
+                [(CastleSide::King, 7), (CastleSide::Queen, 0)]
+                    .into_iter()
+                    .any(|(side, corner)| {
+                        self.castling
+                            .rook_file(color, side)
+                            .is_some_and(|file| file != corner || !king_on_e_file)
+                    })
+            })
+    }
+
+    /// Play `mv` without checking legality. Returns what is needed to take it back.
+    pub fn make_move(&mut self, mv: Move) -> Undo {
+        let us = self.side_to_move;
+        let piece = self.board[mv.from as usize].expect("make_move from an empty square");
+        let mut undo = Undo {
+            captured: None,
+            castling: self.castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+        };
+        self.en_passant = None;
+
+        match mv.kind {
+            MoveKind::Castle(side) => {
+                let rook_from = self
+                    .castling_rook_square(us, side)
+                    .expect("castling without the right");
+                let back_rank = us.back_rank();
+                let rook = self.board[rook_from as usize].take();
+                self.board[mv.from as usize] = None;
+                self.board[mv.to as usize] = Some(piece);
+                self.board[square(side.rook_target_file(), back_rank) as usize] = rook;
+            }
+            MoveKind::EnPassant => {
+                let captured_sq = square(file_of(mv.to), rank_of(mv.from));
+                undo.captured = self.board[captured_sq as usize].take();
+                self.board[mv.from as usize] = None;
+                self.board[mv.to as usize] = Some(piece);
+            }
+            MoveKind::Normal | MoveKind::DoublePush => {
+                undo.captured = self.board[mv.to as usize];
+                self.board[mv.from as usize] = None;
+                self.board[mv.to as usize] = Some(match mv.promotion {
+                    Some(kind) => Piece::new(us, kind),
+                    None => piece,
+                });
+                if mv.kind == MoveKind::DoublePush {
+                    self.set_en_passant_after_double_push(mv);
+                }
+            }
+        }
+
+        if piece.kind == PieceKind::King {
+            self.castling.clear(us);
+        }
+        self.castling.clear_rook_square(mv.from);
+        self.castling.clear_rook_square(mv.to);
+
+        if piece.kind == PieceKind::Pawn || undo.captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if us == PieceColor::Black {
+            self.fullmove_number += 1;
+        }
+        self.side_to_move = us.opposite();
+        undo
+    }
+
+    /// Take back `mv`, which must be the last move made with `make_move`.
+    pub fn unmake_move(&mut self, mv: Move, undo: Undo) {
+        let us = self.side_to_move.opposite();
+        self.side_to_move = us;
+        if us == PieceColor::Black {
+            self.fullmove_number -= 1;
+        }
+        self.castling = undo.castling;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+
+        match mv.kind {
+            MoveKind::Castle(side) => {
+                let rook_from = self
+                    .castling_rook_square(us, side)
+                    .expect("castling without the right");
+                let rook_to = square(side.rook_target_file(), us.back_rank());
+                let king = self.board[mv.to as usize].take();
+                let rook = self.board[rook_to as usize].take();
+                self.board[rook_from as usize] = rook;
+                self.board[mv.from as usize] = king;
+            }
+            MoveKind::EnPassant => {
+                let captured_sq = square(file_of(mv.to), rank_of(mv.from));
+                self.board[mv.from as usize] = self.board[mv.to as usize].take();
+                self.board[captured_sq as usize] = undo.captured;
+            }
+            MoveKind::Normal | MoveKind::DoublePush => {
+                let moved = self.board[mv.to as usize].expect("unmake_move to an empty square");
+                self.board[mv.from as usize] = Some(match mv.promotion {
+                    Some(_) => Piece::new(us, PieceKind::Pawn),
+                    None => moved,
+                });
+                self.board[mv.to as usize] = undo.captured;
+            }
+        }
+    }
+
+    /// Only record an en passant square when an enemy pawn could actually capture onto it,
+    /// so identical positions compare equal regardless of how they were reached.
+    fn set_en_passant_after_double_push(&mut self, mv: Move) {
+        let us = self.side_to_move;
+        let enemy_pawn = Some(Piece::new(us.opposite(), PieceKind::Pawn));
+        let capturable = [-1, 1]
+            .into_iter()
+            .filter_map(|df| offset(mv.to, df, 0))
+            .any(|sq| self.piece_at(sq) == enemy_pawn);
+        if capturable {
+            self.en_passant = Some(square(
+                file_of(mv.from),
+                (rank_of(mv.from) + rank_of(mv.to)) / 2,
+            ));
+        }
+    }
+}
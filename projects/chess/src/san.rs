@@ -0,0 +1,187 @@
+// Standard Algebraic Notation for moves, e.g. `Nf3`, `exd5`, `O-O`, `e8=Q+`.
+use crate::rules::{
+    file_of, rank_of, square_name, CastleSide, Move, MoveKind, PieceKind, Position,
+};
+
+pub fn piece_letter(kind: PieceKind) -> Option<char> {
+    match kind {
+        PieceKind::Pawn => None,
+        PieceKind::Knight => Some('N'),
+        PieceKind::Bishop => Some('B'),
+        PieceKind::Rook => Some('R'),
+        PieceKind::Queen => Some('Q'),
+        PieceKind::King => Some('K'),
+    }
+}
+
+/// SAN for `mv`, which must be legal in `position`.
+pub fn move_to_san(position: &Position, mv: Move) -> String {
+    let mut san = match mv.kind {
+        MoveKind::Castle(CastleSide::King) => "O-O".to_string(),
+        MoveKind::Castle(CastleSide::Queen) => "O-O-O".to_string(),
+        _ => move_body(position, mv),
+    };
+
+    let mut after = position.clone();
+    after.make_move(mv);
+    if after.in_check() {
+        san.push(if after.legal_moves().is_empty() {
+            '#'
+        } else {
+            '+'
+        });
+    }
+    san
+}
+
+fn move_body(position: &Position, mv: Move) -> String {
+    let piece = position
+        .piece_at(mv.from)
+        .expect("SAN for a move from an empty square");
+    let is_capture = position.piece_at(mv.to).is_some() || mv.kind == MoveKind::EnPassant;
+    let mut san = String::new();
+
+    match piece_letter(piece.kind) {
+        None => {
+            if is_capture {
+                san.push((b'a' + file_of(mv.from)) as char);
+            }
+        }
+        Some(letter) => {
+            san.push(letter);
+            san.push_str(&disambiguation(position, mv, piece.kind));
+        }
+    }
+
+    if is_capture {
+        san.push('x');
+    }
+    san.push_str(&square_name(mv.to));
+    if let Some(kind) = mv.promotion {
+        san.push('=');
+        san.extend(piece_letter(kind));
+    }
+    san
+}
+
+/// The file, rank or full square needed to tell `mv` apart from other moves of the same piece type.
+fn disambiguation(position: &Position, mv: Move, kind: PieceKind) -> String {
+    let rivals: Vec<u8> = position
+        .legal_moves()
+        .into_iter()
+        .filter(|other| {
+            other.to == mv.to
+                && other.from != mv.from
+                && position.piece_at(other.from).map(|p| p.kind) == Some(kind)
+        })
+        .map(|other| other.from)
+        .collect();
+
+    if rivals.is_empty() {
+        String::new()
+    } else if rivals.iter().all(|&sq| file_of(sq) != file_of(mv.from)) {
+        ((b'a' + file_of(mv.from)) as char).to_string()
+    } else if rivals.iter().all(|&sq| rank_of(sq) != rank_of(mv.from)) {
+        (rank_of(mv.from) + 1).to_string()
+    } else {
+        square_name(mv.from)
+    }
+}
+
+/// Find the legal move in `position` that `san` describes.
+/// Check marks, annotations and a missing `=` before the promotion piece are tolerated.
+pub fn parse_san(position: &Position, san: &str) -> Option<Move> {
+    let wanted = normalize(san);
+    position
+        .legal_moves()
+        .into_iter()
+        .find(|&mv| normalize(&move_to_san(position, mv)) == wanted)
+}
+
+fn normalize(san: &str) -> String {
+    san.trim()
+        .replace('0', "O")
+        .chars()
+        .filter(|c| !matches!(c, '+' | '#' | '!' | '?' | '='))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::parse_square;
+
+    /// SAN of the move from `from` to `to` in `fen`, promoting to a queen if it promotes.
+    fn san(fen: &str, from: &str, to: &str) -> String {
+        let position = Position::from_fen(fen).unwrap();
+        let (from, to) = (parse_square(from).unwrap(), parse_square(to).unwrap());
+        let mv = position
+            .legal_moves()
+            .into_iter()
+            .find(|mv| {
+                mv.from == from
+                    && mv.to == to
+                    && matches!(mv.promotion, None | Some(PieceKind::Queen))
+            })
+            .unwrap();
+        let san = move_to_san(&position, mv);
+        assert_eq!(parse_san(&position, &san), Some(mv));
+        san
+    }
+
+    #[test]
+    fn plain_moves_and_captures() {
+        let fen = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+        assert_eq!(san(fen, "g1", "f3"), "Nf3");
+        assert_eq!(san(fen, "e4", "e5"), "e5");
+        assert_eq!(san(fen, "e4", "d5"), "exd5");
+        assert_eq!(san(fen, "f1", "b5"), "Bb5+");
+        let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+        assert_eq!(san(fen, "e5", "d6"), "exd6");
+    }
+
+    #[test]
+    fn castling_and_promotion() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(san(fen, "e1", "g1"), "O-O");
+        assert_eq!(san(fen, "e1", "c1"), "O-O-O");
+        let fen = "3rk3/2P5/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(san(fen, "c7", "c8"), "c8=Q");
+        assert_eq!(san(fen, "c7", "d8"), "cxd8=Q+");
+    }
+
+    #[test]
+    fn disambiguation_by_file_rank_or_square() {
+        let fen = "4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1";
+        assert_eq!(san(fen, "b1", "d2"), "Nbd2");
+        assert_eq!(san(fen, "f3", "d2"), "Nfd2");
+        let fen = "k7/8/8/8/8/4R3/8/4RK2 w - - 0 1";
+        assert_eq!(san(fen, "e1", "e2"), "R1e2");
+        assert_eq!(san(fen, "e3", "e2"), "R3e2");
+        let fen = "7k/8/8/8/Q1Q5/8/Q7/4K3 w - - 0 1";
+        assert_eq!(san(fen, "a4", "b3"), "Qa4b3");
+        // A pinned knight is no rival.
+        let fen = "4k3/8/8/8/1b6/8/3N4/4K1N1 w - - 0 1";
+        assert_eq!(san(fen, "g1", "f3"), "Nf3");
+    }
+
+    #[test]
+    fn check_and_mate_suffixes() {
+        let fen = "6k1/8/8/8/8/8/8/R5K1 w - - 0 1";
+        assert_eq!(san(fen, "a1", "a8"), "Ra8+");
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        assert_eq!(san(fen, "a1", "a8"), "Ra8#");
+    }
+
+    #[test]
+    fn lenient_parsing() {
+        let position = Position::from_fen("3rk3/2P5/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let strict = parse_san(&position, "cxd8=Q+");
+        assert!(strict.is_some());
+        assert_eq!(parse_san(&position, "cxd8Q"), strict);
+        assert_eq!(parse_san(&position, "cxd8=Q!?"), strict);
+        assert_eq!(parse_san(&position, "Kf3"), None);
+        let position = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(parse_san(&position, "0-0"), parse_san(&position, "O-O"));
+    }
+}
@@ -0,0 +1,495 @@
+// Computer player: iterative-deepening alpha-beta over `Position` with a transposition table,
+// scored by material plus piece-square tables.
+use std::time::{Duration, Instant};
+
+use crate::rules::{Move, MoveKind, PieceColor, PieceKind, Position};
+
+const INFINITY: i32 = 1_000_000;
+const MATE: i32 = 100_000;
+/// Scores beyond this are "mate in N" rather than material.
+const MATE_THRESHOLD: i32 = MATE - 1_000;
+
+const TT_SIZE: usize = 1 << 18;
+
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const KING_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+pub fn piece_value(kind: PieceKind) -> i32 {
+    match kind {
+        PieceKind::Pawn => 100,
+        PieceKind::Knight => 320,
+        PieceKind::Bishop => 330,
+        PieceKind::Rook => 500,
+        PieceKind::Queen => 900,
+        PieceKind::King => 0,
+    }
+}
+
+/// Material plus piece-square bonus, from the point of view of the side to move.
+pub fn evaluate(position: &Position) -> i32 {
+    let mut score = 0;
+    for (sq, piece) in position.pieces() {
+        let table = match piece.kind {
+            PieceKind::Pawn => &PAWN_TABLE,
+            PieceKind::Knight => &KNIGHT_TABLE,
+            PieceKind::Bishop => &BISHOP_TABLE,
+            PieceKind::Rook => &ROOK_TABLE,
+            PieceKind::Queen => &QUEEN_TABLE,
+            PieceKind::King => &KING_TABLE,
+        };
+        // Tables are written from White's side with rank 8 on top.
+        let index = match piece.color {
+            PieceColor::White => (sq ^ 56) as usize,
+            PieceColor::Black => sq as usize,
+        };
+        let value = piece_value(piece.kind) + table[index];
+        if piece.color == position.side_to_move() {
+            score += value;
+        } else {
+            score -= value;
+        }
+    }
+    score
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub max_depth: u32,
+    pub time_limit: Option<Duration>,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits {
+            max_depth: 5,
+            time_limit: Some(Duration::from_secs(2)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    /// Centipawns for the side to move.
+    pub score: i32,
+    /// Deepest iteration that finished.
+    pub depth: u32,
+    pub nodes: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Copy)]
+struct TtEntry {
+    key: u64,
+    depth: u32,
+    score: i32,
+    bound: Bound,
+    best_move: Option<Move>,
+}
+
+pub struct Searcher {
+    table: Vec<Option<TtEntry>>,
+    nodes: u64,
+    deadline: Option<Instant>,
+    stopped: bool,
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Searcher {
+            table: vec![None; TT_SIZE],
+            nodes: 0,
+            deadline: None,
+            stopped: false,
+        }
+    }
+}
+
+/// Search `position` with a fresh transposition table.
+pub fn search(position: &Position, limits: SearchLimits) -> SearchResult {
+    Searcher::default().search(position, limits)
+}
+
+impl Searcher {
+    /// Deepen one ply at a time until `limits` run out, keeping the last completed iteration.
+    pub fn search(&mut self, position: &Position, limits: SearchLimits) -> SearchResult {
+        self.nodes = 0;
+        self.stopped = false;
+        self.deadline = limits.time_limit.map(|limit| Instant::now() + limit);
+
+        let mut position = position.clone();
+        let mut result = SearchResult {
+            best_move: position.legal_moves().first().copied(),
+            score: 0,
+            depth: 0,
+            nodes: 0,
+        };
+
+        for depth in 1..=limits.max_depth.max(1) {
+            let (score, best_move) = self.root(&mut position, depth);
+            if self.stopped {
+                break;
+            }
+            result.best_move = best_move.or(result.best_move);
+            result.score = score;
+            result.depth = depth;
+            // No point searching deeper once a forced mate has been found.
+            if score.abs() >= MATE_THRESHOLD {
+                break;
+            }
+        }
+        result.nodes = self.nodes;
+        result
+    }
+
+    fn root(&mut self, position: &mut Position, depth: u32) -> (i32, Option<Move>) {
+        let mut moves = position.legal_moves();
+        let tt_move = self
+            .probe(position.zobrist_hash())
+            .and_then(|entry| entry.best_move);
+        order_moves(position, &mut moves, tt_move);
+
+        let mut alpha = -INFINITY;
+        let mut best_move = None;
+        for mv in moves {
+            let undo = position.make_move(mv);
+            let score = -self.negamax(position, depth - 1, 1, -INFINITY, -alpha);
+            position.unmake_move(mv, undo);
+            if self.stopped {
+                break;
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = Some(mv);
+            }
+        }
+
+        if !self.stopped {
+            self.store(
+                position.zobrist_hash(),
+                depth,
+                alpha,
+                Bound::Exact,
+                best_move,
+                0,
+            );
+        }
+        (alpha, best_move)
+    }
+
+    fn negamax(
+        &mut self,
+        position: &mut Position,
+        depth: u32,
+        ply: u32,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        if self.out_of_time() {
+            return 0;
+        }
+        if position.halfmove_clock() >= 100 {
+            return 0;
+        }
+
+        let hash = position.zobrist_hash();
+        let mut tt_move = None;
+        if let Some(entry) = self.probe(hash) {
+            tt_move = entry.best_move;
+            if entry.depth >= depth {
+                let score = score_from_table(entry.score, ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+        }
+
+        if depth == 0 {
+            return self.quiesce(position, alpha, beta);
+        }
+
+        let mut moves = position.legal_moves();
+        if moves.is_empty() {
+            return if position.in_check() {
+                -MATE + ply as i32
+            } else {
+                0
+            };
+        }
+        order_moves(position, &mut moves, tt_move);
+
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        for mv in moves {
+            let undo = position.make_move(mv);
+            let score = -self.negamax(position, depth - 1, ply + 1, -beta, -alpha);
+            position.unmake_move(mv, undo);
+            if self.stopped {
+                return 0;
+            }
+            if score > best {
+                best = score;
+                best_move = Some(mv);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best <= original_alpha {
+            Bound::Upper
+        } else if best >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.store(hash, depth, best, bound, best_move, ply);
+        best
+    }
+
+    /// Resolve captures so the static evaluation is not taken in the middle of an exchange.
+    fn quiesce(&mut self, position: &mut Position, mut alpha: i32, beta: i32) -> i32 {
+        if self.out_of_time() {
+            return 0;
+        }
+
+        let stand_pat = evaluate(position);
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves: Vec<Move> = position
+            .legal_moves()
+            .into_iter()
+            .filter(|&mv| is_capture(position, mv) || mv.promotion.is_some())
+            .collect();
+        order_moves(position, &mut moves, None);
+
+        for mv in moves {
+            let undo = position.make_move(mv);
+            let score = -self.quiesce(position, -beta, -alpha);
+            position.unmake_move(mv, undo);
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+
+    /// Counts a node and checks the clock every few thousand of them.
+    fn out_of_time(&mut self) -> bool {
+        self.nodes += 1;
+        if self.nodes & 2047 == 0 {
+            if let Some(deadline) = self.deadline {
+                if Instant::now() >= deadline {
+                    self.stopped = true;
+                }
+            }
+        }
+        self.stopped
+    }
+
+    fn probe(&self, hash: u64) -> Option<TtEntry> {
+        self.table[hash as usize % TT_SIZE].filter(|entry| entry.key == hash)
+    }
+
+    fn store(
+        &mut self,
+        hash: u64,
+        depth: u32,
+        score: i32,
+        bound: Bound,
+        best_move: Option<Move>,
+        ply: u32,
+    ) {
+        let slot = &mut self.table[hash as usize % TT_SIZE];
+        if slot.is_some_and(|existing| existing.key == hash && existing.depth > depth) {
+            return;
+        }
+        *slot = Some(TtEntry {
+            key: hash,
+            depth,
+            score: score_to_table(score, ply),
+            bound,
+            best_move,
+        });
+    }
+}
+
+/// Mate scores are stored relative to the node rather than the root so they stay valid
+/// when the same position is reached at a different ply.
+fn score_to_table(score: i32, ply: u32) -> i32 {
+    if score >= MATE_THRESHOLD {
+        score + ply as i32
+    } else if score <= -MATE_THRESHOLD {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: i32, ply: u32) -> i32 {
+    if score >= MATE_THRESHOLD {
+        score - ply as i32
+    } else if score <= -MATE_THRESHOLD {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+fn is_capture(position: &Position, mv: Move) -> bool {
+    mv.kind == MoveKind::EnPassant
+        || (!matches!(mv.kind, MoveKind::Castle(_)) && position.piece_at(mv.to).is_some())
+}
+
+/// Transposition-table move first, then captures by most valuable victim / least valuable
+/// attacker, then promotions, then quiet moves.
+fn order_moves(position: &Position, moves: &mut [Move], tt_move: Option<Move>) {
+    moves.sort_by_cached_key(|&mv| {
+        if Some(mv) == tt_move {
+            return i32::MIN;
+        }
+        let mut score = 0;
+        if is_capture(position, mv) {
+            let victim = position
+                .piece_at(mv.to)
+                .map_or(PieceKind::Pawn, |piece| piece.kind);
+            let attacker = position
+                .piece_at(mv.from)
+                .map_or(PieceKind::Pawn, |piece| piece.kind);
+            score += 10_000 + 10 * piece_value(victim) - piece_value(attacker);
+        }
+        if let Some(kind) = mv.promotion {
+            score += 9_000 + piece_value(kind);
+        }
+        -score
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn depth(max_depth: u32) -> SearchLimits {
+        SearchLimits {
+            max_depth,
+            time_limit: None,
+        }
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        let position = Position::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let result = search(&position, depth(3));
+        let mv = result.best_move.unwrap();
+        assert_eq!((mv.from, mv.to), (0, 56));
+        assert!(result.score >= MATE_THRESHOLD);
+
+        let mut after = position.clone();
+        after.make_move(mv);
+        assert!(after.in_check() && after.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn sees_its_own_mate_coming() {
+        let position = Position::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 b - - 0 1").unwrap();
+        let result = search(&position, depth(3));
+        assert!(result.best_move.is_some());
+        // g6, h6 or f6 give the king air; anything else is mated.
+        assert!(result.score > -MATE_THRESHOLD);
+    }
+
+    #[test]
+    fn takes_a_free_queen() {
+        let position = Position::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+        let result = search(&position, depth(2));
+        let mv = result.best_move.unwrap();
+        assert_eq!((mv.from, mv.to), (3, 35));
+    }
+}
@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+
+use crate::board::SelectedSquare;
+use crate::game::{ChessGame, Player, Players};
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum TakebackButton {
+    Undo,
+    Redo,
+}
+
+pub fn spawn_takeback_buttons(mut commands: Commands) {
+    commands
+        .spawn(Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            left: Val::Px(10.0),
+            column_gap: Val::Px(8.0),
+            ..default()
+        })
+        .with_children(|parent| {
+            for (button, label) in [
+                (TakebackButton::Undo, "Undo"),
+                (TakebackButton::Redo, "Redo"),
+            ] {
+                parent
+                    .spawn((
+                        Button,
+                        Node {
+                            padding: UiRect::axes(Val::Px(16.0), Val::Px(6.0)),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                        button,
+                    ))
+                    .with_children(|button| {
+                        button.spawn((
+                            Text::new(label),
+                            TextFont {
+                                font_size: 20.0,
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+/// Take back or redo moves with Ctrl+Z / Ctrl+Y (or Backspace) or the buttons.
+///
+/// Against the computer or an engine this goes back to the human's previous turn rather than
+/// handing the move to the opponent. Taken-back moves stay in the record until a different
+/// move is played, so they can be redone.
+pub fn takeback(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut buttons: Query<(Ref<Interaction>, &TakebackButton, &mut BackgroundColor)>,
+    players: Res<Players>,
+    mut game: ResMut<ChessGame>,
+    mut selected: ResMut<SelectedSquare>,
+) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let mut action =
+        if keys.just_pressed(KeyCode::Backspace) || (ctrl && keys.just_pressed(KeyCode::KeyZ)) {
+            Some(TakebackButton::Undo)
+        } else if ctrl && keys.just_pressed(KeyCode::KeyY) {
+            Some(TakebackButton::Redo)
+        } else {
+            None
+        };
+    for (interaction, button, mut background) in buttons.iter_mut() {
+        let color = match *interaction {
+            Interaction::Pressed => {
+                if interaction.is_changed() {
+                    action = Some(*button);
+                }
+                continue;
+            }
+            Interaction::Hovered => Color::srgb(0.35, 0.35, 0.35),
+            Interaction::None => Color::srgb(0.2, 0.2, 0.2),
+        };
+        if background.0 != color {
+            background.0 = color;
+        }
+    }
+
+    let Some(action) = action else {
+        return;
+    };
+    let step = |game: &mut ChessGame| match action {
+        TakebackButton::Undo => game.step_back(),
+        TakebackButton::Redo => game.step_forward(),
+    };
+    // Only touch the resource when there is something to step over, so nothing resyncs otherwise.
+    let can_step = match action {
+        TakebackButton::Undo => game.cursor > 0,
+        TakebackButton::Redo => game.cursor < game.history.len(),
+    };
+    if !can_step {
+        return;
+    }
+    let any_human = players.white == Player::Human || players.black == Player::Human;
+    while step(&mut game) {
+        if !any_human || players.get(game.position.side_to_move()) == Player::Human {
+            break;
+        }
+    }
+    selected.entity = None;
+}
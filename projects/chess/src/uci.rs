@@ -0,0 +1,207 @@
+// Talking to an external engine over the Universal Chess Interface.
+use std::ffi::OsStr;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use crate::fen::STARTING_FEN;
+use crate::rules::{parse_square, square_name, Move, PieceKind, Position};
+use crate::search::SearchLimits;
+
+/// Long algebraic notation as UCI uses it: `e2e4`, `e7e8q`, castling as the king's two squares.
+pub fn move_to_uci(mv: Move) -> String {
+    let mut text = format!("{}{}", square_name(mv.from), square_name(mv.to));
+    if let Some(kind) = mv.promotion {
+        text.push(match kind {
+            PieceKind::Knight => 'n',
+            PieceKind::Bishop => 'b',
+            PieceKind::Rook => 'r',
+            _ => 'q',
+        });
+    }
+    text
+}
+
+/// In `UCI_Chess960` mode castling is sent as the king capturing its own rook.
+pub fn move_to_uci_chess960(position: &Position, mv: Move) -> String {
+    match position.castling_rook_of(mv) {
+        Some(rook) => format!("{}{}", square_name(mv.from), square_name(rook)),
+        None => move_to_uci(mv),
+    }
+}
+
+/// The legal move in `position` that `text` names, or `None` if the engine sent something illegal.
+/// Castling is accepted both as the king's two squares and as king-takes-rook.
+pub fn move_from_uci(position: &Position, text: &str) -> Option<Move> {
+    let text = text.trim();
+    let from = parse_square(text.get(0..2)?)?;
+    let to = parse_square(text.get(2..4)?)?;
+    let promotion = match text.get(4..) {
+        None | Some("") => None,
+        Some("q") => Some(PieceKind::Queen),
+        Some("r") => Some(PieceKind::Rook),
+        Some("b") => Some(PieceKind::Bishop),
+        Some("n") => Some(PieceKind::Knight),
+        Some(_) => return None,
+    };
+    position.legal_moves().into_iter().find(|&mv| {
+        mv.from == from
+            && (mv.to == to || position.castling_rook_of(mv) == Some(to))
+            && mv.promotion == promotion
+    })
+}
+
+/// `position startpos|fen <FEN> [moves ...]` for a game that began at `start`.
+pub fn position_command(start: &Position, moves: &[Move]) -> String {
+    position_command_in(start, moves, false)
+}
+
+fn position_command_in(start: &Position, moves: &[Move], chess960: bool) -> String {
+    let fen = start.to_fen();
+    let mut command = if fen == STARTING_FEN {
+        "position startpos".to_string()
+    } else {
+        format!("position fen {fen}")
+    };
+    if !moves.is_empty() {
+        command.push_str(" moves");
+        let mut position = start.clone();
+        for &mv in moves {
+            command.push(' ');
+            if chess960 {
+                command.push_str(&move_to_uci_chess960(&position, mv));
+            } else {
+                command.push_str(&move_to_uci(mv));
+            }
+            position.make_move(mv);
+        }
+    }
+    command
+}
+
+/// A time limit wins over a depth limit, since that is what keeps the game moving.
+pub fn go_command(limits: SearchLimits) -> String {
+    match limits.time_limit {
+        Some(limit) => format!("go movetime {}", limit.as_millis()),
+        None => format!("go depth {}", limits.max_depth),
+    }
+}
+
+/// The move in a `bestmove <move> [ponder <move>]` line. `0000` and `(none)` mean no move.
+pub fn parse_bestmove(line: &str) -> Option<Option<String>> {
+    let mut words = line.split_whitespace();
+    if words.next()? != "bestmove" {
+        return None;
+    }
+    Some(
+        words
+            .next()
+            .filter(|mv| *mv != "0000" && *mv != "(none)")
+            .map(str::to_string),
+    )
+}
+
+/// A running engine process. Dropping it sends `quit` and reaps the process.
+pub struct UciEngine {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    pub name: Option<String>,
+    chess960: bool,
+}
+
+impl UciEngine {
+    /// Launch `program` and complete the `uci`/`uciok` handshake.
+    pub fn start<S: AsRef<OsStr>>(program: S, args: &[String]) -> io::Result<Self> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let mut engine = UciEngine {
+            child,
+            stdin,
+            stdout,
+            name: None,
+            chess960: false,
+        };
+
+        engine.send("uci")?;
+        loop {
+            let line = engine.read_line()?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = Some(name.trim().to_string());
+            } else if line.trim() == "uciok" {
+                break;
+            }
+        }
+        engine.wait_ready()?;
+        Ok(engine)
+    }
+
+    pub fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{command}")?;
+        self.stdin.flush()
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.stdout.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "engine closed its output",
+            ));
+        }
+        Ok(line)
+    }
+
+    pub fn wait_ready(&mut self) -> io::Result<()> {
+        self.send("isready")?;
+        while self.read_line()?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    pub fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.wait_ready()
+    }
+
+    /// Switch `UCI_Chess960` on or off, which changes how castling moves are written.
+    pub fn set_chess960(&mut self, enabled: bool) -> io::Result<()> {
+        if self.chess960 == enabled {
+            return Ok(());
+        }
+        self.send(&format!("setoption name UCI_Chess960 value {enabled}"))?;
+        self.chess960 = enabled;
+        self.wait_ready()
+    }
+
+    /// Ask for a move in the game that began at `start` and continued with `moves`.
+    /// Returns the engine's answer unchecked; pass it to `move_from_uci` before playing it.
+    pub fn best_move(
+        &mut self,
+        start: &Position,
+        moves: &[Move],
+        limits: SearchLimits,
+    ) -> io::Result<Option<String>> {
+        self.send(&position_command_in(start, moves, self.chess960))?;
+        self.send(&go_command(limits))?;
+        loop {
+            if let Some(best) = parse_bestmove(&self.read_line()?) {
+                return Ok(best);
+            }
+        }
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        if !matches!(self.child.try_wait(), Ok(Some(_))) {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+}
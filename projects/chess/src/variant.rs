@@ -0,0 +1,199 @@
+// Starting setups: standard chess, Chess960 and setups read from a config file.
+use std::fmt;
+
+use crate::fen::FenError;
+use crate::rules::{PieceKind, Position};
+
+/// Scharnagl number of the standard setup among the 960.
+pub const STANDARD_CHESS960_ID: u16 = 518;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Variant {
+    Standard,
+    /// Fischer Random, numbered 0-959 after Scharnagl.
+    Chess960 {
+        id: u16,
+    },
+    /// Any other setup, e.g. from a FEN or a custom back rank.
+    Custom(Position),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariantError {
+    BadLine(String),
+    UnknownKey(String),
+    BadValue { key: String, value: String },
+    BadFen(FenError),
+}
+
+impl fmt::Display for VariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VariantError::BadLine(line) => write!(f, "expected `key = value`, found {line:?}"),
+            VariantError::UnknownKey(key) => write!(f, "unknown setup key {key:?}"),
+            VariantError::BadValue { key, value } => write!(f, "bad value {value:?} for {key}"),
+            VariantError::BadFen(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for VariantError {}
+
+impl Variant {
+    /// Chess960 setup picked by `seed`, so the same seed always gives the same game.
+    pub fn chess960_from_seed(seed: u64) -> Self {
+        Variant::Chess960 {
+            id: chess960_id_from_seed(seed),
+        }
+    }
+
+    pub fn start_position(&self) -> Position {
+        match self {
+            Variant::Standard => Position::starting(),
+            Variant::Chess960 { id } => Position::from_back_rank(chess960_back_rank(*id)),
+            Variant::Custom(position) => position.clone(),
+        }
+    }
+
+    /// Read a setup file of `key = value` lines; `#` starts a comment. One of:
+    ///
+    /// ```text
+    /// variant = standard
+    /// variant = chess960      # with `id = <0-959>` or `seed = <number>`, else id 518
+    /// back_rank = RNBQKBNR    # White's pieces a to h, mirrored for Black behind full pawn rows
+    /// fen = <FEN>
+    /// ```
+    pub fn parse_config(text: &str) -> Result<Variant, VariantError> {
+        let mut variant = Variant::Standard;
+        let mut chess960 = false;
+        let mut id = None;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| VariantError::BadLine(line.to_string()))?;
+            let bad_value = || VariantError::BadValue {
+                key: key.to_string(),
+                value: value.to_string(),
+            };
+            match key {
+                "variant" => match value {
+                    "standard" => chess960 = false,
+                    "chess960" | "fischerandom" => chess960 = true,
+                    _ => return Err(bad_value()),
+                },
+                "id" => {
+                    id = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|&id: &u16| id < 960)
+                            .ok_or_else(bad_value)?,
+                    )
+                }
+                "seed" => {
+                    id = Some(chess960_id_from_seed(
+                        value.parse().map_err(|_| bad_value())?,
+                    ))
+                }
+                "back_rank" => {
+                    variant = Variant::Custom(Position::from_back_rank(
+                        parse_back_rank(value).ok_or_else(bad_value)?,
+                    ))
+                }
+                "fen" => {
+                    variant =
+                        Variant::Custom(Position::from_fen(value).map_err(VariantError::BadFen)?)
+                }
+                _ => return Err(VariantError::UnknownKey(key.to_string())),
+            }
+        }
+        if chess960 {
+            variant = Variant::Chess960 {
+                id: id.unwrap_or(STANDARD_CHESS960_ID),
+            };
+        }
+        Ok(variant)
+    }
+}
+
+/// One splitmix64 step spreads nearby seeds over all 960 setups.
+pub fn chess960_id_from_seed(seed: u64) -> u16 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z % 960) as u16
+}
+
+/// `RNBQKBNR`-style letters, a-file first, with exactly one king.
+fn parse_back_rank(text: &str) -> Option<[PieceKind; 8]> {
+    let kinds: Vec<PieceKind> = text
+        .chars()
+        .map(|c| match c.to_ascii_uppercase() {
+            'R' => Some(PieceKind::Rook),
+            'N' => Some(PieceKind::Knight),
+            'B' => Some(PieceKind::Bishop),
+            'Q' => Some(PieceKind::Queen),
+            'K' => Some(PieceKind::King),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    let back_rank: [PieceKind; 8] = kinds.try_into().ok()?;
+    let kings = back_rank
+        .iter()
+        .filter(|&&kind| kind == PieceKind::King)
+        .count();
+    (kings == 1).then_some(back_rank)
+}
+
+/// Back rank of Chess960 setup `id` (0-959), a-file first, by Scharnagl's numbering:
+/// the light-squared bishop, the dark-squared bishop, the queen and the knights are placed
+/// in turn from the digits of `id`, and the rook, king and rook fill the three squares left.
+pub fn chess960_back_rank(id: u16) -> [PieceKind; 8] {
+    assert!(id < 960, "Chess960 setups are numbered 0-959");
+    let mut rank = [None; 8];
+    let mut n = id as usize;
+
+    rank[n % 4 * 2 + 1] = Some(PieceKind::Bishop);
+    n /= 4;
+    rank[n % 4 * 2] = Some(PieceKind::Bishop);
+    n /= 4;
+
+    fn place_on_nth_empty(rank: &mut [Option<PieceKind>; 8], nth: usize, kind: PieceKind) {
+        let file = (0..8)
+            .filter(|&file| rank[file].is_none())
+            .nth(nth)
+            .unwrap();
+        rank[file] = Some(kind);
+    }
+    place_on_nth_empty(&mut rank, n % 6, PieceKind::Queen);
+    n /= 6;
+
+    // The ten ways to put two knights on the five squares left, in Scharnagl's order.
+    const KNIGHTS: [(usize, usize); 10] = [
+        (0, 1),
+        (0, 2),
+        (0, 3),
+        (0, 4),
+        (1, 2),
+        (1, 3),
+        (1, 4),
+        (2, 3),
+        (2, 4),
+        (3, 4),
+    ];
+    let (first, second) = KNIGHTS[n];
+    // Place the second knight first so the first one's index still counts the same squares.
+    place_on_nth_empty(&mut rank, second, PieceKind::Knight);
+    place_on_nth_empty(&mut rank, first, PieceKind::Knight);
+
+    for kind in [PieceKind::Rook, PieceKind::King, PieceKind::Rook] {
+        place_on_nth_empty(&mut rank, 0, kind);
+    }
+    rank.map(|kind| kind.expect("all eight squares are filled"))
+}
@@ -0,0 +1,116 @@
+// Zobrist hashing: a 64-bit fingerprint of a position for transposition tables and repetition checks.
+use crate::rules::{CastleSide, PieceColor, Position};
+
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+/// 12 piece types x 64 squares, then side to move, 2 x 2 x 8 castling rook files and 8 en passant files.
+const KEY_COUNT: usize = 12 * 64 + 1 + 32 + 8;
+
+const KEYS: [u64; KEY_COUNT] = {
+    let mut keys = [0; KEY_COUNT];
+    let mut state = 0x5EED_C4E5_u64;
+    let mut i = 0;
+    while i < KEY_COUNT {
+        let (next, key) = splitmix64(state);
+        state = next;
+        keys[i] = key;
+        i += 1;
+    }
+    keys
+};
+
+const SIDE_KEY: usize = 12 * 64;
+const CASTLING_KEYS: usize = SIDE_KEY + 1;
+const EN_PASSANT_KEYS: usize = CASTLING_KEYS + 32;
+
+impl Position {
+    pub fn zobrist_hash(&self) -> u64 {
+        let mut hash = 0;
+        for (sq, piece) in self.pieces() {
+            let piece_index = piece.color.index() * 6 + piece.kind.index();
+            hash ^= KEYS[piece_index * 64 + sq as usize];
+        }
+        if self.side_to_move() == PieceColor::Black {
+            hash ^= KEYS[SIDE_KEY];
+        }
+        for color in [PieceColor::White, PieceColor::Black] {
+            for side in [CastleSide::King, CastleSide::Queen] {
+                if let Some(file) = self.castling().rook_file(color, side) {
+                    let index = (color.index() * 2 + side.index()) * 8 + file as usize;
+                    hash ^= KEYS[CASTLING_KEYS + index];
+                }
+            }
+        }
+        if let Some(sq) = self.en_passant() {
+            hash ^= KEYS[EN_PASSANT_KEYS + (sq % 8) as usize];
+        }
+        hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rules::Position;
+
+    /// Every move from `fen` and back, checking the hash against one worked out from scratch.
+    fn check_moves(fen: &str) {
+        let mut position = Position::from_fen(fen).unwrap();
+        let before = position.zobrist_hash();
+        for mv in position.legal_moves() {
+            let undo = position.make_move(mv);
+            let fresh = Position::from_fen(&position.to_fen()).unwrap();
+            assert_eq!(
+                position.zobrist_hash(),
+                fresh.zobrist_hash(),
+                "{fen} {mv:?}"
+            );
+            assert_ne!(position.zobrist_hash(), before, "{fen} {mv:?}");
+            position.unmake_move(mv, undo);
+            assert_eq!(position.zobrist_hash(), before, "{fen} {mv:?}");
+        }
+    }
+
+    #[test]
+    fn hash_after_make_and_unmake_matches_a_fresh_one() {
+        check_moves(crate::fen::STARTING_FEN);
+        // Castling both ways, promotions and an en passant capture.
+        check_moves("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        check_moves("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3");
+        check_moves("n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1");
+    }
+
+    #[test]
+    fn transpositions_share_a_hash() {
+        let mut a = Position::starting();
+        let mut b = Position::starting();
+        for name in ["g1f3", "g8f6", "b1c3", "b8c6"] {
+            play(&mut a, name);
+        }
+        for name in ["b1c3", "b8c6", "g1f3", "g8f6"] {
+            play(&mut b, name);
+        }
+        assert_eq!(a.zobrist_hash(), b.zobrist_hash());
+
+        // The same pieces with the other side to move are a different position.
+        let white = Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let black = Position::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_ne!(white.zobrist_hash(), black.zobrist_hash());
+    }
+
+    fn play(position: &mut Position, name: &str) {
+        let from = crate::rules::parse_square(&name[..2]).unwrap();
+        let to = crate::rules::parse_square(&name[2..]).unwrap();
+        let mv = position
+            .legal_moves()
+            .into_iter()
+            .find(|mv| mv.from == from && mv.to == to)
+            .unwrap();
+        position.make_move(mv);
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashSet;
+
+use chess::perft::perft;
+use chess::rules::{file_of, PieceColor, PieceKind, Position};
+use chess::san::parse_san;
+use chess::variant::{chess960_back_rank, chess960_id_from_seed, Variant, STANDARD_CHESS960_ID};
+
+#[test]
+fn numbering_covers_all_960_legal_setups() {
+    let mut seen = HashSet::new();
+    for id in 0..960 {
+        let rank = chess960_back_rank(id);
+        assert!(seen.insert(rank), "setup {id} repeats");
+
+        let files = |kind| {
+            (0..8)
+                .filter(|&file| rank[file] == kind)
+                .collect::<Vec<usize>>()
+        };
+        let bishops = files(PieceKind::Bishop);
+        assert_eq!(bishops.len(), 2);
+        assert_ne!(
+            bishops[0] % 2,
+            bishops[1] % 2,
+            "bishops share a color in {id}"
+        );
+        let rooks = files(PieceKind::Rook);
+        let king = files(PieceKind::King)[0];
+        assert!(
+            rooks[0] < king && king < rooks[1],
+            "king outside rooks in {id}"
+        );
+    }
+}
+
+#[test]
+fn standard_setup_is_number_518() {
+    let position = Variant::Chess960 {
+        id: STANDARD_CHESS960_ID,
+    }
+    .start_position();
+    assert_eq!(position, Position::starting());
+}
+
+#[test]
+fn seeds_are_deterministic() {
+    assert_eq!(
+        Variant::chess960_from_seed(42),
+        Variant::chess960_from_seed(42)
+    );
+    let ids: HashSet<_> = (0..100).map(chess960_id_from_seed).collect();
+    assert!(ids.len() > 80);
+}
+
+/// Reference counts from the Chess960 perft tables.
+#[test]
+fn perft_matches_reference_counts() {
+    for (fen, counts) in [
+        (
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+            [21, 528, 12189],
+        ),
+        (
+            "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+            [21, 807, 18002],
+        ),
+    ] {
+        let mut position = Position::from_fen(fen).unwrap();
+        for (depth, &count) in counts.iter().enumerate() {
+            assert_eq!(perft(&mut position, depth as u32 + 1), count, "{fen}");
+        }
+    }
+}
+
+#[test]
+fn castling_follows_chess960_rules() {
+    // King on b1 with rooks on a1 and h1: O-O-O puts the king on c1 and the rook on d1.
+    let mut position = Position::from_fen("4k3/8/8/8/8/8/8/RK5R w HA - 0 1").unwrap();
+    assert_eq!(position.to_fen(), "4k3/8/8/8/8/8/8/RK5R w KQ - 0 1");
+    let castle = parse_san(&position, "O-O-O").unwrap();
+    position.make_move(castle);
+    assert_eq!(position.to_fen(), "4k3/8/8/8/8/8/8/2KR3R b - - 1 1");
+
+    // With the king already on g1 only the rook moves.
+    let mut position = Position::from_fen("4k3/8/8/8/8/8/8/R5KR w HA - 0 1").unwrap();
+    let castle = parse_san(&position, "O-O").unwrap();
+    assert_eq!(castle.from, castle.to);
+    position.make_move(castle);
+    assert_eq!(position.to_fen(), "4k3/8/8/8/8/8/8/R4RK1 b - - 1 1");
+}
+
+#[test]
+fn inner_castling_rook_is_written_by_file() {
+    let position = Position::from_fen("4k3/8/8/8/8/8/8/1R2K1RR w G - 0 1").unwrap();
+    assert_eq!(position.to_fen(), "4k3/8/8/8/8/8/8/1R2K1RR w G - 0 1");
+}
+
+#[test]
+fn setups_are_read_from_config() {
+    let variant = Variant::parse_config("# Fischer random\nvariant = chess960\nid = 0\n").unwrap();
+    assert_eq!(
+        variant.start_position().to_fen(),
+        "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+    );
+
+    let custom = Variant::parse_config("back_rank = RNBKQBNR").unwrap();
+    let position = custom.start_position();
+    assert_eq!(file_of(position.king_square(PieceColor::White).unwrap()), 3);
+    assert!(position.has_chess960_castling());
+    assert!(!Position::starting().has_chess960_castling());
+
+    assert!(Variant::parse_config("variant = chess960\nid = 960").is_err());
+    assert!(Variant::parse_config("colour = red").is_err());
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use chess::clock::{format_clock, Bonus, ChessClock, TimeControl};
+use chess::rules::PieceColor::{Black, White};
+
+fn secs(seconds: u64) -> Duration {
+    Duration::from_secs(seconds)
+}
+
+#[test]
+fn parses_time_controls() {
+    assert_eq!(TimeControl::parse("blitz"), Some(TimeControl::blitz()));
+    let fischer = TimeControl::parse("3+2").unwrap();
+    assert_eq!(fischer.base, secs(180));
+    assert_eq!(fischer.bonus, Bonus::Increment(secs(2)));
+    let bronstein = TimeControl::parse("5+d3").unwrap();
+    assert_eq!(bronstein.bonus, Bonus::Delay(secs(3)));
+    assert_eq!(bronstein.to_string(), "5+d3");
+    assert_eq!(TimeControl::parse("fast"), None);
+}
+
+#[test]
+fn fischer_increment_is_added_in_full() {
+    let mut clock = ChessClock::new(TimeControl::parse("1+5").unwrap());
+    clock.tick(White, secs(2));
+    clock.press(White);
+    assert_eq!(clock.remaining(White), secs(63));
+    assert_eq!(clock.remaining(Black), secs(60));
+}
+
+#[test]
+fn bronstein_delay_gives_back_at_most_the_time_used() {
+    let mut clock = ChessClock::new(TimeControl::parse("1+d3").unwrap());
+    clock.tick(White, secs(2));
+    clock.press(White);
+    assert_eq!(clock.remaining(White), secs(60));
+
+    clock.tick(Black, secs(5));
+    clock.press(Black);
+    assert_eq!(clock.remaining(Black), secs(58));
+}
+
+#[test]
+fn running_out_flags_and_stops_the_clock() {
+    let mut clock = ChessClock::new(TimeControl::parse("1+10").unwrap());
+    clock.tick(White, secs(59));
+    assert_eq!(clock.flagged(), None);
+    clock.tick(White, secs(2));
+    assert_eq!(clock.flagged(), Some(White));
+    assert_eq!(clock.remaining(White), Duration::ZERO);
+
+    clock.press(White);
+    clock.tick(Black, secs(5));
+    assert_eq!(clock.remaining(White), Duration::ZERO);
+    assert_eq!(clock.remaining(Black), secs(60));
+}
+
+#[test]
+fn formats_remaining_time() {
+    assert_eq!(format_clock(secs(300)), "5:00");
+    assert_eq!(format_clock(secs(61)), "1:01");
+    assert_eq!(format_clock(Duration::from_millis(9_450)), "0:09.4");
+}
@@ -0,0 +1,150 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+use chess::net::{Message, NetError, NetGame, PROTOCOL_VERSION};
+use chess::rules::{PieceColor, Position};
+use chess::uci::move_from_uci;
+
+const WAIT: Duration = Duration::from_secs(5);
+
+/// A hosted game and a guest connected to it over localhost.
+fn connect(start: Position) -> (NetGame, NetGame) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let host = thread::spawn(move || NetGame::host(&listener, PieceColor::White, start).unwrap());
+    let guest = NetGame::join(addr).unwrap();
+    (host.join().unwrap(), guest)
+}
+
+#[test]
+fn moves_travel_both_ways() {
+    let (mut host, mut guest) = connect(Position::starting());
+    assert_eq!(host.color, PieceColor::White);
+    assert_eq!(guest.color, PieceColor::Black);
+
+    for (mover, text) in [(0, "e2e4"), (1, "e7e5"), (0, "g1f3"), (1, "b8c6")] {
+        let (from, to) = if mover == 0 {
+            (&mut host, &mut guest)
+        } else {
+            (&mut guest, &mut host)
+        };
+        let mv = move_from_uci(from.position(), text).unwrap();
+        from.send_move(mv).unwrap();
+        assert_eq!(to.wait_move(WAIT).unwrap(), mv);
+    }
+    assert_eq!(host.position(), guest.position());
+}
+
+#[test]
+fn guest_plays_from_the_hosts_start() {
+    let start = Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+    let (host, guest) = connect(start.clone());
+    assert_eq!(host.position(), &start);
+    assert_eq!(guest.start, start);
+}
+
+#[test]
+fn illegal_and_out_of_turn_moves_are_not_sent() {
+    let (mut host, mut guest) = connect(Position::starting());
+    let e2e4 = move_from_uci(host.position(), "e2e4").unwrap();
+    assert!(matches!(
+        guest.send_move(e2e4),
+        Err(NetError::IllegalMove(_))
+    ));
+    host.send_move(e2e4).unwrap();
+    assert!(matches!(
+        host.send_move(e2e4),
+        Err(NetError::IllegalMove(_))
+    ));
+    assert!(guest.poll_move().is_ok());
+}
+
+/// A raw socket posing as the host, to send what a well-behaved peer never would.
+fn fake_host(lines: &'static [&'static str]) -> NetGame {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let hello = Message::Hello {
+            version: PROTOCOL_VERSION,
+            host_color: PieceColor::White,
+            start: Position::starting().to_fen(),
+        };
+        writeln!(stream, "{}", hello.to_line()).unwrap();
+        let mut welcome = String::new();
+        reader.read_line(&mut welcome).unwrap();
+        for line in lines {
+            writeln!(stream, "{line}").unwrap();
+        }
+        // Hold the connection open until the guest hangs up.
+        let mut rest = String::new();
+        while reader.read_line(&mut rest).is_ok_and(|read| read > 0) {}
+    });
+    NetGame::join(addr).unwrap()
+}
+
+#[test]
+fn wrong_hash_is_a_desync() {
+    let mut guest = fake_host(&["move e2e4 0000000000000001"]);
+    assert!(matches!(
+        guest.wait_move(WAIT),
+        Err(NetError::Desync { theirs: 1, .. })
+    ));
+}
+
+#[test]
+fn illegal_move_from_the_peer_is_refused() {
+    let mut guest = fake_host(&["move e2e5 0000000000000000"]);
+    assert!(matches!(
+        guest.wait_move(WAIT),
+        Err(NetError::IllegalMove(_))
+    ));
+}
+
+#[test]
+fn version_mismatch_is_refused() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let host = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let hello = Message::Hello {
+            version: PROTOCOL_VERSION + 1,
+            host_color: PieceColor::White,
+            start: Position::starting().to_fen(),
+        };
+        writeln!(stream, "{}", hello.to_line()).unwrap();
+        let mut reply = String::new();
+        BufReader::new(stream).read_line(&mut reply).unwrap();
+        Message::parse(&reply)
+    });
+    assert!(matches!(
+        NetGame::join(addr),
+        Err(NetError::VersionMismatch { .. })
+    ));
+    assert!(matches!(host.join().unwrap(), Some(Message::Reject { .. })));
+}
+
+#[test]
+fn messages_round_trip() {
+    for message in [
+        Message::Hello {
+            version: 1,
+            host_color: PieceColor::Black,
+            start: Position::starting().to_fen(),
+        },
+        Message::Welcome { version: 1 },
+        Message::Reject {
+            reason: "bye for now".to_string(),
+        },
+        Message::Move {
+            mv: "e7e8q".to_string(),
+            hash: 0xDEAD_BEEF,
+        },
+    ] {
+        assert_eq!(Message::parse(&message.to_line()), Some(message));
+    }
+    assert_eq!(Message::parse("teleport e2e8"), None);
+}
@@ -0,0 +1,90 @@
+use chess::rules::{parse_square, Move, Position};
+use chess::search::SearchLimits;
+use chess::uci::{
+    move_from_uci, move_to_uci, move_to_uci_chess960, parse_bestmove, position_command, UciEngine,
+};
+
+const SCRIPTED: &str = env!("CARGO_BIN_EXE_scripted_uci");
+
+fn script(moves: &[&str]) -> UciEngine {
+    let args: Vec<String> = moves.iter().map(|mv| mv.to_string()).collect();
+    UciEngine::start(SCRIPTED, &args).expect("scripted engine starts")
+}
+
+fn e2e4() -> Move {
+    let start = Position::starting();
+    move_from_uci(&start, "e2e4").unwrap()
+}
+
+#[test]
+fn handshake_reads_engine_name() {
+    let engine = script(&[]);
+    assert_eq!(engine.name.as_deref(), Some("Scripted"));
+}
+
+#[test]
+fn engine_reply_is_checked_against_legal_moves() {
+    let mut engine = script(&["e7e5", "e7e4"]);
+    let start = Position::starting();
+    let mut position = start.clone();
+    let first = e2e4();
+    position.make_move(first);
+
+    let reply = engine
+        .best_move(&start, &[first], SearchLimits::default())
+        .unwrap()
+        .unwrap();
+    let reply = move_from_uci(&position, &reply).expect("e7e5 is legal");
+    assert_eq!(reply.from, parse_square("e7").unwrap());
+    assert_eq!(reply.to, parse_square("e5").unwrap());
+    position.make_move(reply);
+
+    // The second scripted answer is a black pawn move on White's turn.
+    let illegal = engine
+        .best_move(&start, &[first, reply], SearchLimits::default())
+        .unwrap()
+        .unwrap();
+    assert_eq!(move_from_uci(&position, &illegal), None);
+}
+
+#[test]
+fn exhausted_script_has_no_move() {
+    let mut engine = script(&[]);
+    let best = engine
+        .best_move(&Position::starting(), &[], SearchLimits::default())
+        .unwrap();
+    assert_eq!(best, None);
+}
+
+#[test]
+fn protocol_lines() {
+    let start = Position::starting();
+    assert_eq!(position_command(&start, &[]), "position startpos");
+    assert_eq!(
+        position_command(&start, &[e2e4()]),
+        "position startpos moves e2e4"
+    );
+
+    let promotion = Position::from_fen("8/P6k/8/8/8/8/8/K7 w - - 0 1").unwrap();
+    let mv = move_from_uci(&promotion, "a7a8n").unwrap();
+    assert_eq!(move_to_uci(mv), "a7a8n");
+    assert_eq!(
+        position_command(&promotion, &[]),
+        "position fen 8/P6k/8/8/8/8/8/K7 w - - 0 1"
+    );
+
+    assert_eq!(
+        parse_bestmove("bestmove e2e4 ponder e7e5"),
+        Some(Some("e2e4".to_string()))
+    );
+    assert_eq!(parse_bestmove("bestmove (none)"), Some(None));
+    assert_eq!(parse_bestmove("info depth 3"), None);
+}
+
+#[test]
+fn chess960_castling_is_sent_as_king_takes_rook() {
+    let position = Position::from_fen("4k3/8/8/8/8/8/8/R5KR w HA - 0 1").unwrap();
+    let castle = move_from_uci(&position, "g1h1").expect("king takes rook castles");
+    assert_eq!(move_to_uci_chess960(&position, castle), "g1h1");
+    assert_eq!(move_from_uci(&position, &move_to_uci(castle)), Some(castle));
+}
@@ -0,0 +1,32 @@
+use chess::rules::Position;
+
+/// Positions with castling rights, en passant captures, promotions and non-zero clocks.
+const POSITIONS: [&str; 4] = [
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 3 17",
+    "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+    "r3k2r/1P4P1/8/8/8/8/1p4p1/R3K2R b KQkq - 7 40",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 12 60",
+];
+
+fn check_every_line(position: &mut Position, depth: u32) {
+    if depth == 0 {
+        return;
+    }
+    for mv in position.legal_moves() {
+        let before = position.clone();
+        let fen = position.to_fen();
+        let undo = position.make_move(mv);
+        check_every_line(position, depth - 1);
+        position.unmake_move(mv, undo);
+        assert_eq!(*position, before, "unmaking {mv:?} from {fen}");
+        assert_eq!(position.to_fen(), fen);
+    }
+}
+
+#[test]
+fn unmake_restores_the_position_exactly() {
+    for fen in POSITIONS {
+        let mut position = Position::from_fen(fen).unwrap();
+        check_every_line(&mut position, 2);
+    }
+}
@@ -0,0 +1,631 @@
+// Abilities beyond the plain attack, defined per scenario: what they reach, what they hit and
+// what they do. Resolving one is plain Rust on a list of combatants; the systems at the bottom
+// let the player pick one with the number keys, preview its area and click to use it.
+use std::fmt;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::action::Action;
+use crate::combat::{attack, TurnActions};
+use crate::pathfinding::{manhattan, Coord, Grid};
+use crate::status::{StatusKind, Statuses};
+use crate::unit::Stats;
+use crate::{HoveredTile, Map, SelectedUnit, Tile, TilePos, Unit};
+
+/// Energy a unit gets back at the start of each of its turns.
+pub const ENERGY_PER_TURN: i32 = 1;
+
+/// The tiles an ability lands on, around the tile it is aimed at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Area {
+    /// Just the tile aimed at.
+    #[default]
+    Single,
+    /// Every tile within `radius` steps of it.
+    Burst { radius: u32 },
+    /// `length` tiles in a straight line from the user towards it, stopped by walls and
+    /// mountains.
+    Line { length: u32 },
+}
+
+/// Which units in the area an ability affects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Affects {
+    #[default]
+    Enemies,
+    /// The user's side, the user included.
+    Allies,
+    Everyone,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Effect {
+    /// An attack with `bonus` added to the user's attack.
+    Damage {
+        bonus: i32,
+    },
+    /// Hit points back, up to the maximum.
+    Heal {
+        amount: i32,
+    },
+    /// Shoved up to `tiles` tiles straight away from the user.
+    Push {
+        tiles: u32,
+    },
+    Inflict {
+        status: StatusKind,
+        turns: u32,
+    },
+}
+
+/// An ability as written in scenario files.
+///
+/// ```ron
+/// (name: "Fireball", range: 4, area: Burst(radius: 1), cooldown: 3, cost: 3,
+///  affects: Everyone, effects: [Damage(bonus: 0)])
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ability {
+    pub name: String,
+    /// How many steps away it can be aimed; 0 for the user's own tile.
+    pub range: u32,
+    #[serde(default)]
+    pub area: Area,
+    /// Turns of the user's it cannot be used again for.
+    #[serde(default)]
+    pub cooldown: u32,
+    /// Energy it takes.
+    #[serde(default)]
+    pub cost: i32,
+    #[serde(default)]
+    pub affects: Affects,
+    /// Applied in order to each affected unit.
+    pub effects: Vec<Effect>,
+}
+
+/// An ability a unit has, and how long until it can use it again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbilitySlot {
+    pub ability: Ability,
+    pub cooldown: u32,
+}
+
+/// A unit's abilities, used with the number keys in this order.
+#[derive(Component, Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Abilities(pub Vec<AbilitySlot>);
+
+impl Abilities {
+    /// A turn of the unit's has passed.
+    pub fn cool_down(&mut self) {
+        for slot in &mut self.0 {
+            slot.cooldown = slot.cooldown.saturating_sub(1);
+        }
+    }
+}
+
+/// A unit as abilities see it.
+#[derive(Debug, Clone)]
+pub struct Combatant {
+    pub side: Unit,
+    pub position: Coord,
+    pub stats: Stats,
+    pub statuses: Statuses,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbilityError {
+    /// Turns left before it can be used again.
+    Cooldown(u32),
+    NotEnoughEnergy {
+        needed: i32,
+        left: i32,
+    },
+    OutOfRange,
+    /// Nobody it would affect is in the area.
+    NoTarget,
+}
+
+impl fmt::Display for AbilityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AbilityError::Cooldown(turns) => write!(f, "ready again in {turns} turns"),
+            AbilityError::NotEnoughEnergy { needed, left } => {
+                write!(f, "needs {needed} energy, {left} left")
+            }
+            AbilityError::OutOfRange => write!(f, "out of range"),
+            AbilityError::NoTarget => write!(f, "nobody there to affect"),
+        }
+    }
+}
+
+/// One step along the axis `to` lies furthest along from `from`.
+fn direction(from: Coord, to: Coord) -> (i64, i64) {
+    let dx = to.0 as i64 - from.0 as i64;
+    let dy = to.1 as i64 - from.1 as i64;
+    if dx.abs() >= dy.abs() {
+        (dx.signum(), 0)
+    } else {
+        (0, dy.signum())
+    }
+}
+
+fn step(grid: &Grid, (x, y): Coord, (dx, dy): (i64, i64)) -> Option<Coord> {
+    let (x, y) = (x as i64 + dx, y as i64 + dy);
+    (x >= 0 && y >= 0)
+        .then_some((x as u32, y as u32))
+        .filter(|&tile| grid.in_bounds(tile))
+}
+
+/// Tiles within `range` steps of `from` that an ability can be aimed at.
+pub fn tiles_in_range(grid: &Grid, from: Coord, range: u32) -> Vec<Coord> {
+    (0..grid.height)
+        .flat_map(|y| (0..grid.width).map(move |x| (x, y)))
+        .filter(|&tile| manhattan(from, tile) <= range)
+        .collect()
+}
+
+/// The tiles `area` covers when used from `from` and aimed at `target`.
+pub fn area_tiles(grid: &Grid, area: Area, from: Coord, target: Coord) -> Vec<Coord> {
+    match area {
+        Area::Single => vec![target],
+        Area::Burst { radius } => tiles_in_range(grid, target, radius),
+        Area::Line { length } => {
+            let direction = direction(from, target);
+            if direction == (0, 0) {
+                return Vec::new();
+            }
+            let mut tiles = Vec::new();
+            let mut tile = from;
+            while tiles.len() < length as usize {
+                match step(grid, tile, direction) {
+                    Some(next) if !grid.terrain(next).blocks_sight() => {
+                        tiles.push(next);
+                        tile = next;
+                    }
+                    _ => break,
+                }
+            }
+            tiles
+        }
+    }
+}
+
+/// Use `slot` as `units[user]`, aimed at `target`, and return the indices of the units it
+/// affected. Pays the energy and starts the cooldown; nothing changes on an error.
+pub fn use_ability(
+    grid: &Grid,
+    slot: &mut AbilitySlot,
+    units: &mut [Combatant],
+    user: usize,
+    target: Coord,
+) -> Result<Vec<usize>, AbilityError> {
+    let ability = &slot.ability;
+    let me = units[user].clone();
+    if slot.cooldown > 0 {
+        return Err(AbilityError::Cooldown(slot.cooldown));
+    }
+    if me.stats.energy < ability.cost {
+        return Err(AbilityError::NotEnoughEnergy {
+            needed: ability.cost,
+            left: me.stats.energy,
+        });
+    }
+    if manhattan(me.position, target) > ability.range {
+        return Err(AbilityError::OutOfRange);
+    }
+
+    let area = area_tiles(grid, ability.area, me.position, target);
+    let mut affected: Vec<usize> = (0..units.len())
+        .filter(|&index| {
+            let unit = &units[index];
+            let side_ok = match ability.affects {
+                Affects::Enemies => unit.side != me.side,
+                Affects::Allies => unit.side == me.side,
+                Affects::Everyone => true,
+            };
+            side_ok && unit.stats.hp > 0 && area.contains(&unit.position)
+        })
+        .collect();
+    if affected.is_empty() {
+        return Err(AbilityError::NoTarget);
+    }
+    // Push the furthest first so those behind do not block those in front.
+    affected.sort_by_key(|&index| std::cmp::Reverse(manhattan(me.position, units[index].position)));
+
+    for &index in &affected {
+        for effect in &ability.effects {
+            match *effect {
+                Effect::Damage { bonus } => {
+                    let attacker = Stats {
+                        attack: me.stats.attack + bonus,
+                        ..me.stats.clone()
+                    };
+                    let cover = grid.terrain(units[index].position);
+                    attack(&attacker, &mut units[index].stats, cover);
+                }
+                Effect::Heal { amount } => {
+                    let stats = &mut units[index].stats;
+                    stats.hp = (stats.hp + amount).min(stats.max_hp);
+                }
+                Effect::Push { tiles } => {
+                    let direction = direction(me.position, units[index].position);
+                    for _ in 0..tiles {
+                        let Some(next) = step(grid, units[index].position, direction) else {
+                            break;
+                        };
+                        let taken = units
+                            .iter()
+                            .any(|unit| unit.stats.hp > 0 && unit.position == next);
+                        if !grid.is_passable(next) || taken {
+                            break;
+                        }
+                        units[index].position = next;
+                    }
+                }
+                Effect::Inflict { status, turns } => {
+                    let unit = &mut units[index];
+                    unit.statuses.apply(&mut unit.stats, status, turns);
+                }
+            }
+        }
+    }
+
+    units[user].stats.energy -= ability.cost;
+    slot.cooldown = ability.cooldown;
+    Ok(affected)
+}
+
+/// The ability the player is aiming, as the unit using it and its slot.
+#[derive(Resource, Default)]
+pub struct Aiming(pub Option<(Entity, usize)>);
+
+pub const NUMBER_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+/// Number keys pick one of the selected unit's abilities to aim; the same key again or Escape
+/// puts it away.
+pub fn choose_ability(
+    keys: Res<ButtonInput<KeyCode>>,
+    selected: Res<SelectedUnit>,
+    mut aiming: ResMut<Aiming>,
+    units: Query<(&Abilities, &TurnActions)>,
+) {
+    let Some(entity) = selected.0 else {
+        aiming.0 = None;
+        return;
+    };
+    // Put away once the unit changes, or has used it or attacked otherwise.
+    let done = |aimer: Entity| {
+        aimer != entity || units.get(aimer).is_ok_and(|(_, actions)| actions.attacked)
+    };
+    if aiming.0.is_some_and(|(aimer, _)| done(aimer)) {
+        aiming.0 = None;
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        aiming.0 = None;
+        return;
+    }
+    let Some(slot) = NUMBER_KEYS.iter().position(|&key| keys.just_pressed(key)) else {
+        return;
+    };
+    let Ok((abilities, actions)) = units.get(entity) else {
+        return;
+    };
+    let Some(ability) = abilities.0.get(slot) else {
+        return;
+    };
+    if aiming.0 == Some((entity, slot)) {
+        aiming.0 = None;
+    } else if actions.attacked {
+        info!("Already attacked this turn");
+    } else {
+        info!("Aiming {}", ability.ability.name);
+        aiming.0 = Some((entity, slot));
+    }
+}
+
+/// A click on the map uses the ability being aimed there. The click is used up either way, so
+/// it does not also move or attack.
+pub fn cast_ability(
+    mut buttons: ResMut<ButtonInput<MouseButton>>,
+    hovered: Res<HoveredTile>,
+    tiles: Query<&TilePos, With<Tile>>,
+    aiming: Res<Aiming>,
+    mut actions: EventWriter<Action>,
+) {
+    let Some((_, slot)) = aiming.0 else { return };
+    if !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+    buttons.clear_just_pressed(MouseButton::Left);
+    let Some(target) = hovered.0.and_then(|tile| tiles.get(tile).ok()) else {
+        return;
+    };
+    actions.send(Action::Ability {
+        slot,
+        target: (target.x, target.y),
+    });
+}
+
+/// While aiming, shade the tiles the ability can be aimed at and, under the cursor, the area
+/// it would land on.
+pub fn preview_ability_area(
+    aiming: Res<Aiming>,
+    hovered: Res<HoveredTile>,
+    map: Res<Map>,
+    units: Query<(&TilePos, &Abilities), Without<Tile>>,
+    mut tiles: Query<(Entity, &TilePos, &mut Sprite), With<Tile>>,
+) {
+    let Some((user, slot)) = aiming.0 else { return };
+    let Ok((pos, abilities)) = units.get(user) else {
+        return;
+    };
+    let Some(slot) = abilities.0.get(slot) else {
+        return;
+    };
+    let from = (pos.x, pos.y);
+    let ability = &slot.ability;
+
+    let aimed = hovered
+        .0
+        .and_then(|tile| tiles.get(tile).ok())
+        .map(|(_, tile, _)| (tile.x, tile.y))
+        .filter(|&tile| manhattan(from, tile) <= ability.range);
+    let area = aimed
+        .map(|target| area_tiles(&map.0, ability.area, from, target))
+        .unwrap_or_default();
+    for (_, tile, mut sprite) in tiles.iter_mut() {
+        let tile = (tile.x, tile.y);
+        if area.contains(&tile) {
+            sprite.color = sprite.color.mix(&Color::srgb(1.0, 0.45, 0.1), 0.7);
+        } else if manhattan(from, tile) <= ability.range {
+            sprite.color = sprite.color.mix(&Color::srgb(0.9, 0.8, 0.3), 0.3);
+        }
+    }
+}
+
+/// List of the selected unit's abilities, energy and statuses in the bottom-left corner.
+#[derive(Component)]
+pub struct AbilityText;
+
+pub fn spawn_ability_text(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 20.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        },
+        AbilityText,
+    ));
+}
+
+pub fn update_ability_text(
+    selected: Res<SelectedUnit>,
+    aiming: Res<Aiming>,
+    units: Query<(&Abilities, &Stats, &Statuses)>,
+    mut text_query: Query<&mut Text, With<AbilityText>>,
+) {
+    let Ok(mut text) = text_query.get_single_mut() else {
+        return;
+    };
+    let Some((abilities, stats, statuses)) = selected.0.and_then(|unit| units.get(unit).ok())
+    else {
+        text.0.clear();
+        return;
+    };
+    let mut lines = vec![format!("Energy {}/{}", stats.energy, stats.max_energy)];
+    for status in &statuses.0 {
+        lines.push(format!("{:?} for {} turns", status.kind, status.turns));
+    }
+    for (index, slot) in abilities.0.iter().enumerate() {
+        let marker = if aiming.0.is_some_and(|(_, aimed)| aimed == index) {
+            ">"
+        } else {
+            " "
+        };
+        let ability = &slot.ability;
+        let mut line = format!(
+            "{marker}{} {} ({} energy)",
+            index + 1,
+            ability.name,
+            ability.cost
+        );
+        if slot.cooldown > 0 {
+            line += &format!(", ready in {}", slot.cooldown);
+        }
+        lines.push(line);
+    }
+    if !abilities.0.is_empty() {
+        lines.push("(number to aim, click to use, Esc to cancel)".to_string());
+    }
+    text.0 = lines.join("\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::Terrain;
+
+    fn unit(side: Unit, position: Coord) -> Combatant {
+        Combatant {
+            side,
+            position,
+            stats: Stats {
+                hp: 10,
+                max_hp: 10,
+                attack: 4,
+                defense: 1,
+                movement: 3,
+                energy: 3,
+                max_energy: 3,
+                ..default()
+            },
+            statuses: Statuses::default(),
+        }
+    }
+
+    fn slot(ability: Ability) -> AbilitySlot {
+        AbilitySlot {
+            ability,
+            cooldown: 0,
+        }
+    }
+
+    fn ability(range: u32, area: Area, affects: Affects, effects: Vec<Effect>) -> Ability {
+        Ability {
+            name: "Test".to_string(),
+            range,
+            area,
+            cooldown: 2,
+            cost: 2,
+            affects,
+            effects,
+        }
+    }
+
+    #[test]
+    fn areas() {
+        let mut grid = Grid::new(10, 10);
+        assert_eq!(
+            area_tiles(&grid, Area::Single, (0, 0), (3, 3)),
+            vec![(3, 3)]
+        );
+        assert_eq!(
+            area_tiles(&grid, Area::Burst { radius: 1 }, (0, 0), (5, 5)).len(),
+            5
+        );
+        assert_eq!(
+            area_tiles(&grid, Area::Burst { radius: 1 }, (0, 0), (0, 0)).len(),
+            3
+        );
+        let line = area_tiles(&grid, Area::Line { length: 3 }, (2, 2), (2, 7));
+        assert_eq!(line, vec![(2, 3), (2, 4), (2, 5)]);
+        grid.set_terrain((2, 4), Terrain::Wall);
+        let line = area_tiles(&grid, Area::Line { length: 3 }, (2, 2), (2, 7));
+        assert_eq!(line, vec![(2, 3)]);
+    }
+
+    #[test]
+    fn fireball_hits_everyone_in_the_burst_and_costs_energy() {
+        let grid = Grid::new(10, 10);
+        let fireball = ability(
+            4,
+            Area::Burst { radius: 1 },
+            Affects::Everyone,
+            vec![Effect::Damage { bonus: 1 }],
+        );
+        let mut units = [
+            unit(Unit::Player, (0, 0)),
+            unit(Unit::Enemy, (4, 0)),
+            unit(Unit::Player, (4, 1)),
+            unit(Unit::Enemy, (6, 0)),
+        ];
+        let mut slot = slot(fireball);
+        let hit = use_ability(&grid, &mut slot, &mut units, 0, (4, 0)).unwrap();
+        assert_eq!(hit.len(), 2);
+        assert_eq!((units[1].stats.hp, units[2].stats.hp), (6, 6));
+        assert_eq!(units[3].stats.hp, 10);
+        assert_eq!(units[0].stats.energy, 1);
+        assert_eq!(slot.cooldown, 2);
+
+        let again = use_ability(&grid, &mut slot, &mut units, 0, (4, 0));
+        assert_eq!(again, Err(AbilityError::Cooldown(2)));
+        slot.cooldown = 0;
+        let again = use_ability(&grid, &mut slot, &mut units, 0, (4, 0));
+        assert_eq!(
+            again,
+            Err(AbilityError::NotEnoughEnergy { needed: 2, left: 1 })
+        );
+        assert_eq!(units[1].stats.hp, 6);
+    }
+
+    #[test]
+    fn heal_and_shield_only_touch_allies() {
+        let grid = Grid::new(10, 10);
+        let mut units = [unit(Unit::Player, (0, 0)), unit(Unit::Player, (1, 0))];
+        units[0].stats.energy = 10;
+        units[1].stats.hp = 3;
+        let heal = ability(
+            1,
+            Area::Single,
+            Affects::Allies,
+            vec![Effect::Heal { amount: 5 }],
+        );
+        use_ability(&grid, &mut slot(heal.clone()), &mut units, 0, (1, 0)).unwrap();
+        assert_eq!(units[1].stats.hp, 8);
+        use_ability(&grid, &mut slot(heal), &mut units, 1, (0, 0)).unwrap();
+        assert_eq!(units[0].stats.hp, 10);
+
+        let guard = ability(
+            0,
+            Area::Single,
+            Affects::Allies,
+            vec![Effect::Inflict {
+                status: StatusKind::Shield(2),
+                turns: 2,
+            }],
+        );
+        use_ability(&grid, &mut slot(guard), &mut units, 0, (0, 0)).unwrap();
+        assert_eq!(units[0].stats.defense, 3);
+
+        let shot = ability(
+            3,
+            Area::Single,
+            Affects::Enemies,
+            vec![Effect::Damage { bonus: 0 }],
+        );
+        let result = use_ability(&grid, &mut slot(shot.clone()), &mut units, 0, (1, 0));
+        assert_eq!(result, Err(AbilityError::NoTarget));
+        let result = use_ability(&grid, &mut slot(shot), &mut units, 0, (5, 0));
+        assert_eq!(result, Err(AbilityError::OutOfRange));
+    }
+
+    #[test]
+    fn push_back_stops_at_walls_and_units() {
+        let mut grid = Grid::new(10, 10);
+        grid.set_terrain((5, 0), Terrain::Water);
+        let shove = ability(
+            1,
+            Area::Single,
+            Affects::Enemies,
+            vec![Effect::Push { tiles: 3 }],
+        );
+        let mut units = [
+            unit(Unit::Player, (1, 0)),
+            unit(Unit::Enemy, (2, 0)),
+            unit(Unit::Player, (2, 1)),
+            unit(Unit::Enemy, (2, 3)),
+        ];
+        use_ability(&grid, &mut slot(shove.clone()), &mut units, 0, (2, 0)).unwrap();
+        assert_eq!(units[1].position, (4, 0));
+        use_ability(&grid, &mut slot(shove), &mut units, 2, (2, 2)).unwrap_err();
+        let line = ability(
+            3,
+            Area::Line { length: 2 },
+            Affects::Enemies,
+            vec![Effect::Push { tiles: 1 }],
+        );
+        units[2].position = (2, 1);
+        units[3].position = (2, 2);
+        units[0].position = (2, 3);
+        use_ability(&grid, &mut slot(line), &mut units, 2, (2, 4)).unwrap();
+        // Blocked by the unit behind it.
+        assert_eq!(units[3].position, (2, 2));
+    }
+}
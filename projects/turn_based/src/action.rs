@@ -0,0 +1,236 @@
+// What units do on their turns, as typed actions. Player input and the AI only decide on an
+// action; `perform_actions` carries every one of them out, so a battle replays exactly from
+// the list of actions taken.
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::ability::{use_ability, Abilities, Combatant};
+use crate::combat::{attack, in_range, TurnActions};
+use crate::logic::{CurrentTurn, EndTurnEvent};
+use crate::pathfinding::{find_path, path_cost, Coord, Grid};
+use crate::replay::ReplayLog;
+use crate::status::Statuses;
+use crate::unit::{Stats, Walking};
+use crate::{Map, TilePos, Unit};
+
+/// One thing the acting unit does. Targets are tiles rather than entities, which differ from
+/// one run of a match to the next.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    Move {
+        to: Coord,
+    },
+    /// Attack the unit standing on `target`.
+    Attack {
+        target: Coord,
+    },
+    /// Use the ability in `slot`, aimed at `target`.
+    Ability {
+        slot: usize,
+        target: Coord,
+    },
+    EndTurn,
+}
+
+type CombatantQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Unit,
+        &'static mut TilePos,
+        &'static mut Stats,
+        &'static mut Statuses,
+        Option<&'static mut Abilities>,
+        &'static mut TurnActions,
+    ),
+>;
+
+/// Carry out the actions ordered for the acting unit, adding each one that happens to the
+/// log. Its turn ends on `EndTurn` or once it has moved and attacked; anything still ordered
+/// after that was meant for the turn that just ended and is dropped.
+pub fn perform_actions(
+    mut actions: EventReader<Action>,
+    current: Res<CurrentTurn>,
+    map: Res<Map>,
+    mut units: CombatantQuery,
+    mut commands: Commands,
+    mut end_turn: EventWriter<EndTurnEvent>,
+    mut log: Option<ResMut<ReplayLog>>,
+) {
+    let ordered: Vec<Action> = actions.read().copied().collect();
+    let Some(actor) = current.0 else { return };
+    for action in ordered {
+        let alive = units
+            .get(actor)
+            .is_ok_and(|(_, _, _, stats, ..)| stats.hp > 0);
+        let done = alive
+            && match action {
+                Action::Move { to } => move_unit(&map.0, &mut units, &mut commands, actor, to),
+                Action::Attack { target } => attack_unit(&map.0, &mut units, actor, target),
+                Action::Ability { slot, target } => {
+                    cast(&map.0, &mut units, &mut commands, actor, slot, target)
+                }
+                Action::EndTurn => true,
+            };
+        if !done {
+            continue;
+        }
+        if let Some(log) = log.as_deref_mut() {
+            log.actions.push(action);
+        }
+        let finished = action == Action::EndTurn
+            || units
+                .get(actor)
+                .is_ok_and(|(.., actions)| actions.moved && actions.attacked);
+        if finished {
+            end_turn.send(EndTurnEvent);
+            break;
+        }
+    }
+}
+
+fn move_unit(
+    grid: &Grid,
+    units: &mut CombatantQuery,
+    commands: &mut Commands,
+    actor: Entity,
+    to: Coord,
+) -> bool {
+    // Units in the fog still stand in the way, even where the highlight did not show them.
+    let blocked: HashSet<Coord> = units
+        .iter()
+        .filter(|(entity, _, _, stats, ..)| *entity != actor && stats.hp > 0)
+        .map(|(_, _, pos, ..)| (pos.x, pos.y))
+        .collect();
+    let Ok((_, _, mut pos, stats, .., mut actions)) = units.get_mut(actor) else {
+        return false;
+    };
+    if actions.moved {
+        info!("Already moved this turn");
+        return false;
+    }
+    let start = (pos.x, pos.y);
+    if to == start {
+        return false;
+    }
+    let path =
+        find_path(grid, start, to, &blocked).filter(|path| path_cost(grid, path) <= stats.movement);
+    let Some(path) = path else {
+        info!("Tile out of reach");
+        return false;
+    };
+
+    commands.entity(actor).insert(Walking::along(&path));
+    (pos.x, pos.y) = to;
+    actions.moved = true;
+    true
+}
+
+fn attack_unit(grid: &Grid, units: &mut CombatantQuery, actor: Entity, target: Coord) -> bool {
+    let Ok((_, side, from, attacker, .., actions)) = units.get(actor) else {
+        return false;
+    };
+    if actions.attacked {
+        info!("Already attacked this turn");
+        return false;
+    }
+    let (side, from, attacker) = (*side, from.clone(), attacker.clone());
+    let Some(defender) = units
+        .iter()
+        .find(|(_, unit, pos, stats, ..)| {
+            **unit != side && stats.hp > 0 && (pos.x, pos.y) == target
+        })
+        .map(|(entity, ..)| entity)
+    else {
+        return false;
+    };
+    let Ok((_, _, to, mut stats, ..)) = units.get_mut(defender) else {
+        return false;
+    };
+    if !in_range(&attacker, &from, &to) {
+        info!("Target out of range");
+        return false;
+    }
+
+    let dealt = attack(&attacker, &mut stats, grid.terrain(target));
+    info!(
+        "{:?} hits {:?} for {} ({} hp left)",
+        actor, defender, dealt, stats.hp
+    );
+    if let Ok((.., mut actions)) = units.get_mut(actor) {
+        actions.attacked = true;
+    }
+    true
+}
+
+fn cast(
+    grid: &Grid,
+    units: &mut CombatantQuery,
+    commands: &mut Commands,
+    actor: Entity,
+    slot: usize,
+    target: Coord,
+) -> bool {
+    // The living in position order: no two share a tile, and unlike entity ids the order is
+    // the same every time the match is played.
+    let mut entities: Vec<(Coord, Entity)> = units
+        .iter()
+        .filter(|(_, _, _, stats, ..)| stats.hp > 0)
+        .map(|(entity, _, pos, ..)| ((pos.x, pos.y), entity))
+        .collect();
+    entities.sort();
+    let mut combatants: Vec<Combatant> = entities
+        .iter()
+        .filter_map(|&(_, entity)| units.get(entity).ok())
+        .map(|(_, unit, pos, stats, statuses, ..)| Combatant {
+            side: *unit,
+            position: (pos.x, pos.y),
+            stats: stats.clone(),
+            statuses: statuses.clone(),
+        })
+        .collect();
+    let Some(index) = entities.iter().position(|&(_, entity)| entity == actor) else {
+        return false;
+    };
+    let Ok((.., Some(mut abilities), actions)) = units.get_mut(actor) else {
+        return false;
+    };
+    if actions.attacked {
+        info!("Already attacked this turn");
+        return false;
+    }
+    let Some(slot) = abilities.0.get_mut(slot) else {
+        return false;
+    };
+    let name = slot.ability.name.clone();
+    match use_ability(grid, slot, &mut combatants, index, target) {
+        Ok(affected) => info!("{:?} uses {} on {} units", actor, name, affected.len()),
+        Err(err) => {
+            info!("Can't use {name}: {err}");
+            return false;
+        }
+    }
+
+    for (&(_, entity), combatant) in entities.iter().zip(combatants) {
+        let Ok((_, _, mut pos, mut stats, mut statuses, _, mut actions)) = units.get_mut(entity)
+        else {
+            continue;
+        };
+        let from = (pos.x, pos.y);
+        if from != combatant.position {
+            commands
+                .entity(entity)
+                .insert(Walking::along(&[from, combatant.position]));
+            (pos.x, pos.y) = combatant.position;
+        }
+        *stats = combatant.stats;
+        *statuses = combatant.statuses;
+        if entity == actor {
+            actions.attacked = true;
+        }
+    }
+    true
+}
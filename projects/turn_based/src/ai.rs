@@ -0,0 +1,400 @@
+// Enemy decision making: list every move/attack pair a unit could make this turn, rate them,
+// and let the faction's strategy pick one. No ECS access, so plans can be tested on their own.
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::combat::damage;
+use crate::pathfinding::{manhattan, reachable, Coord, Grid};
+use crate::unit::Stats;
+use crate::Unit;
+
+/// A unit as the AI sees it.
+#[derive(Debug, Clone)]
+pub struct UnitView {
+    pub side: Unit,
+    pub position: Coord,
+    pub stats: Stats,
+}
+
+/// What the acting unit will do: walk to `destination`, then attack `units[target]` if any.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Plan {
+    pub destination: Coord,
+    pub target: Option<usize>,
+}
+
+/// One possible plan and what it is expected to lead to.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub plan: Plan,
+    /// Damage the attack deals, after the target's terrain bonus.
+    pub damage: i32,
+    /// The attack leaves the target at 0 hp.
+    pub kills: bool,
+    /// Damage the surviving opponents could deal to the unit at `destination` next turn.
+    pub threat: i32,
+    /// Defense bonus of the terrain at `destination`.
+    pub cover: i32,
+    /// Manhattan distance from `destination` to the nearest surviving opponent, or to the
+    /// middle of the map when no opponent is in sight.
+    pub distance: u32,
+}
+
+/// How a faction picks among the candidates.
+pub trait Strategy: Send + Sync {
+    /// Index into `candidates`, which is never empty.
+    fn choose(&self, candidates: &[Candidate], rng: &mut ChaCha12Rng) -> usize;
+}
+
+/// Rates every candidate as a weighted sum and takes the best, the earliest on ties.
+pub struct Weighted {
+    pub damage: i32,
+    pub kill: i32,
+    pub threat: i32,
+    pub cover: i32,
+    pub distance: i32,
+}
+
+impl Weighted {
+    pub fn score(&self, candidate: &Candidate) -> i32 {
+        self.damage * candidate.damage + self.kill * candidate.kills as i32
+            - self.threat * candidate.threat
+            + self.cover * candidate.cover
+            - self.distance * candidate.distance as i32
+    }
+}
+
+impl Strategy for Weighted {
+    fn choose(&self, candidates: &[Candidate], _rng: &mut ChaCha12Rng) -> usize {
+        let mut best = 0;
+        for (index, candidate) in candidates.iter().enumerate() {
+            if self.score(candidate) > self.score(&candidates[best]) {
+                best = index;
+            }
+        }
+        best
+    }
+}
+
+/// Any candidate at all, attacking only because some candidates happen to.
+pub struct Wander;
+
+impl Strategy for Wander {
+    fn choose(&self, candidates: &[Candidate], rng: &mut ChaCha12Rng) -> usize {
+        rng.gen_range(0..candidates.len())
+    }
+}
+
+/// Charges in and goes for kills, mostly ignoring what hits back.
+pub static AGGRESSIVE: Weighted = Weighted {
+    damage: 10,
+    kill: 50,
+    threat: 2,
+    cover: 3,
+    distance: 4,
+};
+
+/// Fights from cover and keeps out of reach unless a hit is worth it.
+pub static DEFENSIVE: Weighted = Weighted {
+    damage: 6,
+    kill: 40,
+    threat: 8,
+    cover: 6,
+    distance: 1,
+};
+
+/// The strategy a faction plays with, as written in scenario files.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Tactics {
+    #[default]
+    Aggressive,
+    Defensive,
+    Random,
+}
+
+impl Tactics {
+    pub fn strategy(self) -> &'static dyn Strategy {
+        match self {
+            Tactics::Aggressive => &AGGRESSIVE,
+            Tactics::Defensive => &DEFENSIVE,
+            Tactics::Random => &Wander,
+        }
+    }
+}
+
+/// Randomness for AI decisions, seeded so the same seed always plays the same match. ChaCha
+/// rather than `StdRng`, whose stream may change between versions of rand and which cannot
+/// say how far along it is.
+#[derive(Resource)]
+pub struct AiRng {
+    pub seed: u64,
+    pub rng: ChaCha12Rng,
+}
+
+impl AiRng {
+    pub fn new(seed: u64) -> Self {
+        AiRng {
+            seed,
+            rng: ChaCha12Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// The stream of `seed` with the first `draws` words already used up.
+    pub fn resume(seed: u64, draws: u64) -> Self {
+        let mut ai_rng = AiRng::new(seed);
+        ai_rng.rng.set_word_pos(draws as u128);
+        ai_rng
+    }
+
+    /// Words drawn from the stream so far.
+    pub fn draws(&self) -> u64 {
+        self.rng.get_word_pos() as u64
+    }
+
+    /// Seed from `--seed <number>`, or a fresh random one.
+    pub fn from_args() -> Self {
+        let seed = std::env::args()
+            .skip_while(|arg| arg != "--seed")
+            .nth(1)
+            .and_then(|seed| seed.parse().ok())
+            .unwrap_or_else(rand::random);
+        info!("AI seed {seed}");
+        AiRng::new(seed)
+    }
+}
+
+/// Every plan `units[actor]` could carry out this turn, in a fixed order. `units` holds only
+/// what the actor's side can see.
+pub fn candidates(grid: &Grid, units: &[UnitView], actor: usize) -> Vec<Candidate> {
+    let me = &units[actor];
+    let opponents: Vec<usize> = (0..units.len())
+        .filter(|&index| units[index].side != me.side && units[index].stats.hp > 0)
+        .collect();
+    let others = |except: usize| -> HashSet<Coord> {
+        units
+            .iter()
+            .enumerate()
+            .filter(|&(index, unit)| index != except && unit.stats.hp > 0)
+            .map(|(_, unit)| unit.position)
+            .collect()
+    };
+
+    // Tiles each opponent could attack next turn, ignoring where everyone else will have moved.
+    let reach_of: Vec<(usize, HashSet<Coord>)> = opponents
+        .iter()
+        .map(|&index| {
+            let opponent = &units[index];
+            let mut blocked = others(index);
+            blocked.remove(&me.position);
+            let moves = reachable(grid, opponent.position, opponent.stats.movement, &blocked);
+            let attackable = moves
+                .keys()
+                .flat_map(|&from| tiles_within(grid, from, opponent.stats.range))
+                .collect();
+            (index, attackable)
+        })
+        .collect();
+
+    let mut destinations: Vec<Coord> =
+        reachable(grid, me.position, me.stats.movement, &others(actor))
+            .into_keys()
+            .collect();
+    destinations.sort();
+
+    let mut candidates = Vec::new();
+    for destination in destinations {
+        let cover = grid.terrain(destination).defense_bonus();
+        // With nobody in sight, head for the middle of the map to look for them.
+        let nearest = opponents
+            .iter()
+            .map(|&index| manhattan(destination, units[index].position))
+            .min()
+            .unwrap_or_else(|| manhattan(destination, (grid.width / 2, grid.height / 2)));
+        let targets = opponents
+            .iter()
+            .copied()
+            .filter(|&index| manhattan(destination, units[index].position) <= me.stats.range);
+        for target in targets.map(Some).chain([None]) {
+            let (dealt, kills) = match target {
+                Some(index) => {
+                    let defender = &units[index];
+                    let cover = grid.terrain(defender.position);
+                    let dealt = damage(&me.stats, &defender.stats, cover);
+                    (dealt, dealt >= defender.stats.hp)
+                }
+                None => (0, false),
+            };
+            let threat = reach_of
+                .iter()
+                .filter(|(index, attackable)| {
+                    !(kills && target == Some(*index)) && attackable.contains(&destination)
+                })
+                .map(|(index, _)| {
+                    damage(&units[*index].stats, &me.stats, grid.terrain(destination))
+                })
+                .sum();
+            candidates.push(Candidate {
+                plan: Plan {
+                    destination,
+                    target,
+                },
+                damage: dealt,
+                kills,
+                threat,
+                cover,
+                distance: nearest,
+            });
+        }
+    }
+    candidates
+}
+
+/// What `units[actor]` does this turn under `strategy`.
+pub fn plan_turn(
+    grid: &Grid,
+    units: &[UnitView],
+    actor: usize,
+    strategy: &dyn Strategy,
+    rng: &mut ChaCha12Rng,
+) -> Plan {
+    let candidates = candidates(grid, units, actor);
+    if candidates.is_empty() {
+        return Plan {
+            destination: units[actor].position,
+            target: None,
+        };
+    }
+    candidates[strategy.choose(&candidates, rng)].plan
+}
+
+fn tiles_within(grid: &Grid, (x, y): Coord, range: u32) -> impl Iterator<Item = Coord> + '_ {
+    let (range, cx, cy) = (range as i64, x as i64, y as i64);
+    (-range..=range)
+        .flat_map(move |dx| {
+            let rest = range - dx.abs();
+            (-rest..=rest).map(move |dy| (cx + dx, cy + dy))
+        })
+        .filter(|&(x, y)| x >= 0 && y >= 0)
+        .map(|(x, y)| (x as u32, y as u32))
+        .filter(|&tile| grid.in_bounds(tile))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::Terrain;
+
+    fn seeded(seed: u64) -> ChaCha12Rng {
+        ChaCha12Rng::seed_from_u64(seed)
+    }
+
+    fn unit(side: Unit, position: Coord, hp: i32, attack: i32) -> UnitView {
+        UnitView {
+            side,
+            position,
+            stats: Stats {
+                hp,
+                max_hp: hp,
+                attack,
+                defense: 0,
+                movement: 3,
+                range: 1,
+                ..default()
+            },
+        }
+    }
+
+    #[test]
+    fn aggressive_goes_for_the_kill() {
+        let grid = Grid::new(10, 10);
+        let units = [
+            unit(Unit::Enemy, (5, 5), 6, 3),
+            unit(Unit::Player, (7, 5), 10, 4),
+            unit(Unit::Player, (3, 5), 3, 4),
+        ];
+        let plan = plan_turn(&grid, &units, 0, &AGGRESSIVE, &mut seeded(0));
+        assert_eq!(plan.target, Some(2));
+        assert_eq!(manhattan(plan.destination, units[2].position), 1);
+    }
+
+    #[test]
+    fn aggressive_closes_in_when_nothing_is_in_reach() {
+        let grid = Grid::new(10, 10);
+        let units = [
+            unit(Unit::Enemy, (0, 0), 6, 3),
+            unit(Unit::Player, (9, 9), 10, 4),
+        ];
+        let plan = plan_turn(&grid, &units, 0, &AGGRESSIVE, &mut seeded(0));
+        assert_eq!(plan.target, None);
+        assert_eq!(manhattan(plan.destination, (0, 0)), 3);
+    }
+
+    #[test]
+    fn searches_the_middle_when_nobody_is_in_sight() {
+        let grid = Grid::new(10, 10);
+        let units = [unit(Unit::Enemy, (9, 9), 6, 3)];
+        let plan = plan_turn(&grid, &units, 0, &AGGRESSIVE, &mut seeded(0));
+        assert_eq!(manhattan(plan.destination, (5, 5)), 5);
+    }
+
+    #[test]
+    fn defensive_keeps_out_of_reach_and_takes_cover() {
+        let mut grid = Grid::new(10, 10);
+        grid.set_terrain((1, 1), Terrain::Forest);
+        let units = [
+            unit(Unit::Enemy, (2, 2), 6, 3),
+            unit(Unit::Player, (8, 2), 10, 4),
+        ];
+        let plan = plan_turn(&grid, &units, 0, &DEFENSIVE, &mut seeded(0));
+        let threatened = candidates(&grid, &units, 0)
+            .into_iter()
+            .find(|candidate| candidate.plan == plan)
+            .unwrap()
+            .threat;
+        assert_eq!(threatened, 0);
+        assert_eq!(plan.destination, (1, 1));
+    }
+
+    #[test]
+    fn walls_and_units_limit_the_candidates() {
+        let mut grid = Grid::new(10, 10);
+        grid.set_terrain((1, 0), Terrain::Wall);
+        let units = [
+            unit(Unit::Enemy, (0, 0), 6, 3),
+            unit(Unit::Player, (0, 1), 10, 4),
+        ];
+        let candidates = candidates(&grid, &units, 0);
+        // Boxed in: stay put, with or without hitting the unit in the way.
+        assert_eq!(candidates.len(), 2);
+        assert!(candidates.iter().all(|c| c.plan.destination == (0, 0)));
+        assert_eq!(candidates[0].plan.target, Some(1));
+        assert_eq!(candidates[0].damage, 3);
+    }
+
+    #[test]
+    fn same_seed_same_plans() {
+        let grid = Grid::new(10, 10);
+        let units = [
+            unit(Unit::Enemy, (5, 5), 6, 3),
+            unit(Unit::Player, (6, 5), 10, 4),
+            unit(Unit::Player, (1, 8), 10, 4),
+        ];
+        let play = |seed| {
+            let mut rng = seeded(seed);
+            (0..20)
+                .map(|_| plan_turn(&grid, &units, 0, Tactics::Random.strategy(), &mut rng))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(play(7), play(7));
+        assert_ne!(play(7), play(8));
+        for tactics in [Tactics::Aggressive, Tactics::Defensive] {
+            let first = plan_turn(&grid, &units, 0, tactics.strategy(), &mut seeded(1));
+            let again = plan_turn(&grid, &units, 0, tactics.strategy(), &mut seeded(2));
+            assert_eq!(first, again);
+        }
+    }
+}
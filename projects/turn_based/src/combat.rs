@@ -0,0 +1,190 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::logic::{CurrentTurn, TurnQueue};
+use crate::pathfinding::manhattan;
+use crate::terrain::Terrain;
+use crate::unit::Stats;
+use crate::{SelectedUnit, TilePos};
+
+/// What a unit has already done this turn: it may move once and attack once.
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TurnActions {
+    pub moved: bool,
+    pub attacked: bool,
+}
+
+/// Damage dealt by one attack: attack minus defense, where the defender's defense includes the
+/// bonus of the terrain it stands on. Never negative.
+pub fn damage(attacker: &Stats, defender: &Stats, cover: Terrain) -> i32 {
+    (attacker.attack - defender.defense - cover.defense_bonus()).max(0)
+}
+
+pub fn in_range(attacker: &Stats, from: &TilePos, to: &TilePos) -> bool {
+    manhattan((from.x, from.y), (to.x, to.y)) <= attacker.range
+}
+
+/// Hit `defender`, standing on `cover`, and report the damage dealt.
+pub fn attack(attacker: &Stats, defender: &mut Stats, cover: Terrain) -> i32 {
+    let dealt = damage(attacker, defender, cover);
+    defender.hp = (defender.hp - dealt).max(0);
+    dealt
+}
+
+/// Remove units whose hp reached 0, from the board and from the turn order.
+pub fn despawn_dead(
+    mut commands: Commands,
+    units: Query<(Entity, &Stats)>,
+    mut selected: ResMut<SelectedUnit>,
+    mut queue: ResMut<TurnQueue>,
+    mut current: ResMut<CurrentTurn>,
+) {
+    for (entity, stats) in units.iter() {
+        if stats.hp <= 0 {
+            info!("{:?} died", entity);
+            commands.entity(entity).despawn_recursive();
+            if selected.0 == Some(entity) {
+                selected.0 = None;
+            }
+            queue.remove(entity);
+            if current.0 == Some(entity) {
+                current.0 = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::action::{perform_actions, Action};
+    use crate::logic::EndTurnEvent;
+    use crate::pathfinding::Grid;
+    use crate::status::Statuses;
+    use crate::{Map, Unit};
+
+    fn stats(hp: i32, attack: i32, defense: i32, range: u32) -> Stats {
+        Stats {
+            hp,
+            max_hp: hp,
+            attack,
+            defense,
+            movement: 3,
+            range,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn damage_is_attack_minus_defense_and_cover() {
+        let attacker = stats(10, 5, 0, 1);
+        assert_eq!(damage(&attacker, &stats(10, 0, 2, 1), Terrain::Plains), 3);
+        assert_eq!(damage(&attacker, &stats(10, 0, 2, 1), Terrain::Forest), 2);
+        assert_eq!(damage(&attacker, &stats(10, 0, 2, 1), Terrain::Mountain), 1);
+    }
+
+    #[test]
+    fn damage_and_hp_never_go_below_zero() {
+        let weak = stats(10, 1, 0, 1);
+        let mut tough = stats(10, 0, 4, 1);
+        assert_eq!(damage(&weak, &tough, Terrain::Mountain), 0);
+        assert_eq!(attack(&weak, &mut tough, Terrain::Plains), 0);
+        assert_eq!(tough.hp, 10);
+
+        let strong = stats(10, 9, 0, 1);
+        let mut frail = stats(3, 0, 1, 1);
+        assert_eq!(attack(&strong, &mut frail, Terrain::Plains), 8);
+        assert_eq!(frail.hp, 0);
+    }
+
+    #[test]
+    fn range_is_counted_in_steps() {
+        let origin = TilePos { x: 2, y: 2 };
+        assert_eq!(manhattan((2, 2), (4, 1)), 3);
+        let archer = stats(10, 3, 0, 3);
+        assert!(in_range(&archer, &origin, &TilePos { x: 4, y: 1 }));
+        assert!(in_range(&archer, &origin, &TilePos { x: 2, y: 5 }));
+        assert!(!in_range(&archer, &origin, &TilePos { x: 4, y: 4 }));
+        let swordsman = stats(10, 3, 0, 1);
+        assert!(in_range(&swordsman, &origin, &TilePos { x: 2, y: 1 }));
+        assert!(!in_range(&swordsman, &origin, &TilePos { x: 3, y: 3 }));
+    }
+
+    fn spawn(world: &mut World, side: Unit, (x, y): (u32, u32), stats: Stats) -> Entity {
+        world
+            .spawn((
+                side,
+                TilePos { x, y },
+                stats,
+                Statuses::default(),
+                TurnActions::default(),
+            ))
+            .id()
+    }
+
+    fn order(world: &mut World, actions: &[Action]) -> usize {
+        for &action in actions {
+            world.send_event(action);
+        }
+        world.run_system_once(perform_actions).unwrap();
+        world.resource_mut::<Events<EndTurnEvent>>().drain().count()
+    }
+
+    #[test]
+    fn one_move_and_one_attack_a_turn() {
+        let mut world = World::new();
+        world.insert_resource(Map(Grid::new(8, 8)));
+        world.init_resource::<Events<Action>>();
+        world.init_resource::<Events<EndTurnEvent>>();
+        let actor = spawn(&mut world, Unit::Player, (0, 0), stats(10, 5, 0, 1));
+        let target = spawn(&mut world, Unit::Enemy, (3, 0), stats(10, 0, 0, 1));
+        world.insert_resource(CurrentTurn(Some(actor)));
+
+        // Out of range until it has moved next to the target.
+        assert_eq!(order(&mut world, &[Action::Attack { target: (3, 0) }]), 0);
+        assert_eq!(world.get::<Stats>(target).unwrap().hp, 10);
+        assert_eq!(
+            order(
+                &mut world,
+                &[Action::Move { to: (2, 0) }, Action::Move { to: (1, 0) }]
+            ),
+            0
+        );
+        let pos = world.get::<TilePos>(actor).unwrap();
+        assert_eq!((pos.x, pos.y), (2, 0));
+
+        // The attack uses up the turn; the second is dropped.
+        assert_eq!(
+            order(
+                &mut world,
+                &[
+                    Action::Attack { target: (3, 0) },
+                    Action::Attack { target: (3, 0) }
+                ]
+            ),
+            1
+        );
+        assert_eq!(world.get::<Stats>(target).unwrap().hp, 5);
+        let actions = world.get::<TurnActions>(actor).unwrap();
+        assert!(actions.moved && actions.attacked);
+    }
+
+    #[test]
+    fn the_dead_leave_the_board_and_the_queue() {
+        let mut world = World::new();
+        let alive = world.spawn(stats(5, 0, 0, 1)).id();
+        let dead = world.spawn(stats(0, 0, 0, 1)).id();
+        world.insert_resource(TurnQueue(vec![alive, dead]));
+        world.insert_resource(CurrentTurn(Some(dead)));
+        world.insert_resource(SelectedUnit(Some(dead)));
+
+        world.run_system_once(despawn_dead).unwrap();
+        assert!(world.get_entity(dead).is_err());
+        assert!(world.get_entity(alive).is_ok());
+        assert_eq!(world.resource::<TurnQueue>().0, vec![alive]);
+        assert_eq!(world.resource::<CurrentTurn>().0, None);
+        assert_eq!(world.resource::<SelectedUnit>().0, None);
+    }
+}
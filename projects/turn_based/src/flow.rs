@@ -0,0 +1,383 @@
+// How a match runs from start to finish: pick a scenario, deploy, fight, see how it went.
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::ability::{Aiming, NUMBER_KEYS};
+use crate::fog::FogOfWar;
+use crate::logic::{CurrentTurn, EndTurnEvent, Round, TurnQueue};
+use crate::pathfinding::Coord;
+use crate::save::Roster;
+use crate::scenario::{scenario_path, ActiveScenario, MatchResult, Scenario, SCENARIOS};
+use crate::unit::Stats;
+use crate::{HoveredTile, Map, ScenarioEntities, SelectedUnit, Tile, TilePos, Unit};
+
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MatchState {
+    #[default]
+    MainMenu,
+    /// The player arranges their units before the first turn.
+    Deployment,
+    Battle,
+    Victory,
+    Defeat,
+    Results,
+    /// Watching a battle again, one action at a time.
+    Replay,
+}
+
+/// How the match has gone for the player's side so far.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchStats {
+    /// Turns the player's units have taken.
+    pub turns: u32,
+    pub damage_dealt: i32,
+    pub damage_taken: i32,
+    pub units_lost: u32,
+    pub enemies_defeated: u32,
+}
+
+impl MatchStats {
+    /// A unit on `side` went from `before` to `after` hp.
+    pub fn record(&mut self, side: Unit, before: i32, after: i32) {
+        let damage = (before - after).max(0);
+        let died = before > 0 && after <= 0;
+        match side {
+            Unit::Enemy => {
+                self.damage_dealt += damage;
+                self.enemies_defeated += died as u32;
+            }
+            Unit::Player => {
+                self.damage_taken += damage;
+                self.units_lost += died as u32;
+            }
+        }
+    }
+}
+
+/// The board has to be set up from the scenario before the match can start.
+#[derive(Resource)]
+pub struct StartPending;
+
+/// Tiles the player may deploy their units on.
+#[derive(Resource, Default)]
+pub struct DeploymentZone(pub Vec<Coord>);
+
+#[derive(Component)]
+pub struct MenuText;
+
+/// Enter moves the match on to what comes next.
+pub fn press_enter(
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<State<MatchState>>,
+    pending: Option<Res<StartPending>>,
+    mut next: ResMut<NextState<MatchState>>,
+) {
+    if !keys.just_pressed(KeyCode::Enter) {
+        return;
+    }
+    let to = match state.get() {
+        MatchState::MainMenu => MatchState::Deployment,
+        // Nothing to fight with until the board is up.
+        MatchState::Deployment if pending.is_none() => MatchState::Battle,
+        MatchState::Victory | MatchState::Defeat => MatchState::Results,
+        MatchState::Results => MatchState::MainMenu,
+        MatchState::Deployment | MatchState::Battle | MatchState::Replay => return,
+    };
+    next.set(to);
+}
+
+/// Back at the menu there is no match: clear the board and everything about the last one.
+pub fn clear_board(
+    mut commands: Commands,
+    old: ScenarioEntities,
+    mut selected: ResMut<SelectedUnit>,
+) {
+    for entity in old.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    selected.0 = None;
+    commands.insert_resource(TurnQueue::default());
+    commands.insert_resource(CurrentTurn::default());
+    commands.insert_resource(MatchResult::default());
+    commands.insert_resource(FogOfWar::default());
+    commands.insert_resource(Map::default());
+    commands.remove_resource::<Roster>();
+}
+
+pub fn spawn_main_menu(mut commands: Commands, active: Res<ActiveScenario>) {
+    commands.spawn((
+        Text::new(menu_text(&active)),
+        TextFont {
+            font_size: 32.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(80.0),
+            left: Val::Px(80.0),
+            ..default()
+        },
+        MenuText,
+        StateScoped(MatchState::MainMenu),
+    ));
+}
+
+fn menu_text(active: &ActiveScenario) -> String {
+    let picked = active.0.path().map(|path| path.to_string());
+    let mut text = "Pick a scenario\n\n".to_string();
+    for (index, name) in SCENARIOS.iter().enumerate() {
+        let marker = if picked.as_deref() == Some(scenario_path(name).as_str()) {
+            ">"
+        } else {
+            " "
+        };
+        text += &format!("{marker} {}  {name}\n", index + 1);
+    }
+    text + "\n(Enter to start, R to watch the last battle)"
+}
+
+/// Number keys pick which scenario to play.
+pub fn pick_scenario(
+    keys: Res<ButtonInput<KeyCode>>,
+    asset_server: Res<AssetServer>,
+    mut active: ResMut<ActiveScenario>,
+    mut menu: Query<&mut Text, With<MenuText>>,
+) {
+    let Some(name) = NUMBER_KEYS
+        .iter()
+        .position(|&key| keys.just_pressed(key))
+        .and_then(|index| SCENARIOS.get(index))
+    else {
+        return;
+    };
+    active.0 = asset_server.load(scenario_path(name));
+    for mut text in menu.iter_mut() {
+        text.0 = menu_text(&active);
+    }
+}
+
+pub fn start_deployment(mut commands: Commands) {
+    commands.insert_resource(StartPending);
+}
+
+/// Editing the scenario file starts its match over from deployment.
+pub fn restart_on_reload(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<Scenario>>,
+    active: Res<ActiveScenario>,
+    state: Res<State<MatchState>>,
+    mut next: ResMut<NextState<MatchState>>,
+) {
+    let edited = events
+        .read()
+        .any(|event| matches!(event, AssetEvent::Modified { id } if *id == active.0.id()));
+    if !edited {
+        return;
+    }
+    match state.get() {
+        MatchState::Deployment => commands.insert_resource(StartPending),
+        MatchState::Battle | MatchState::Victory | MatchState::Defeat => {
+            next.set(MatchState::Deployment)
+        }
+        MatchState::MainMenu | MatchState::Results | MatchState::Replay => {}
+    }
+}
+
+/// Units as deployment moves them: position on the grid and on screen, and their sprite to
+/// show which is picked up.
+type DeployQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Unit,
+        &'static mut TilePos,
+        &'static mut Transform,
+        &'static mut Sprite,
+    ),
+    Without<Tile>,
+>;
+
+/// Click one of the player's units, then a tile in the zone to put it there; clicking
+/// another of the player's units swaps the two.
+pub fn deploy_units(
+    buttons: Res<ButtonInput<MouseButton>>,
+    hovered: Res<HoveredTile>,
+    zone: Res<DeploymentZone>,
+    tiles: Query<(&TilePos, &Transform), With<Tile>>,
+    mut units: DeployQuery,
+    mut picked: Local<Option<Entity>>,
+) {
+    if !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Some((tile_pos, tile_transform)) = hovered.0.and_then(|tile| tiles.get(tile).ok()) else {
+        return;
+    };
+    let target = (tile_pos.x, tile_pos.y);
+    let occupant = units
+        .iter()
+        .find(|(_, _, pos, ..)| (pos.x, pos.y) == target)
+        .map(|(entity, unit, ..)| (entity, *unit));
+
+    // Left over from an earlier match, or nothing picked yet.
+    let Some(unit) = picked.take().filter(|&unit| units.contains(unit)) else {
+        if let Some((entity, Unit::Player)) = occupant {
+            if let Ok((.., mut sprite)) = units.get_mut(entity) {
+                sprite.color.set_alpha(0.6);
+            }
+            *picked = Some(entity);
+        }
+        return;
+    };
+    let Ok((_, _, mut pos, mut transform, mut sprite)) = units.get_mut(unit) else {
+        return;
+    };
+    sprite.color.set_alpha(1.0);
+    if !zone.0.contains(&target) {
+        info!("Units can only be deployed in the highlighted zone");
+        return;
+    }
+    let from = (pos.clone(), transform.translation);
+    match occupant {
+        Some((other, Unit::Player)) if other != unit => {}
+        Some(_) => return,
+        None => {}
+    }
+    *pos = tile_pos.clone();
+    transform.translation.x = tile_transform.translation.x;
+    transform.translation.y = tile_transform.translation.y;
+
+    if let Some((other, _)) = occupant {
+        if let Ok((_, _, mut pos, mut transform, _)) = units.get_mut(other) {
+            *pos = from.0;
+            transform.translation = from.1;
+        }
+    }
+}
+
+pub fn highlight_deployment_zone(
+    zone: Res<DeploymentZone>,
+    mut tiles: Query<(&TilePos, &mut Sprite), With<Tile>>,
+) {
+    for (tile_pos, mut sprite) in tiles.iter_mut() {
+        if zone.0.contains(&(tile_pos.x, tile_pos.y)) {
+            sprite.color = sprite.color.mix(&Color::srgb(0.9, 0.8, 0.3), 0.35);
+        }
+    }
+}
+
+/// Count up what happened to each unit's hp since the last frame, and whose turns ended.
+/// Runs before the dead are despawned so their last hit is counted.
+pub fn tally_match_stats(
+    mut events: EventReader<EndTurnEvent>,
+    current: Res<CurrentTurn>,
+    units: Query<(Entity, &Unit, &Stats)>,
+    mut last_hp: Local<HashMap<Entity, i32>>,
+    mut stats: ResMut<MatchStats>,
+) {
+    if events.read().count() > 0 {
+        let side = current.0.and_then(|entity| units.get(entity).ok());
+        if let Some((_, Unit::Player, _)) = side {
+            stats.turns += 1;
+        }
+    }
+    let mut seen = HashMap::new();
+    for (entity, unit, unit_stats) in units.iter() {
+        // Units new to the board, freshly spawned or loaded, start from where they are.
+        if let Some(&before) = last_hp.get(&entity) {
+            if before != unit_stats.hp {
+                stats.record(*unit, before, unit_stats.hp);
+            }
+        }
+        seen.insert(entity, unit_stats.hp);
+    }
+    *last_hp = seen;
+}
+
+/// Put down whatever the player had in hand once the battle is over.
+pub fn leave_battle(
+    mut selected: ResMut<SelectedUnit>,
+    mut aiming: ResMut<Aiming>,
+    mut sprites: Query<&mut Sprite, With<Unit>>,
+) {
+    if let Some(mut sprite) = selected
+        .0
+        .take()
+        .and_then(|unit| sprites.get_mut(unit).ok())
+    {
+        sprite.color.set_alpha(1.0);
+    }
+    aiming.0 = None;
+}
+
+pub fn spawn_results(
+    mut commands: Commands,
+    result: Res<MatchResult>,
+    round: Res<Round>,
+    stats: Res<MatchStats>,
+) {
+    let outcome = match result.0 {
+        Some(Unit::Player) => "Victory",
+        Some(Unit::Enemy) => "Defeat",
+        None => "Abandoned",
+    };
+    let summary = format!(
+        "{outcome}\n\n\
+         Turns taken: {}\n\
+         Rounds played: {}\n\
+         Damage dealt: {}\n\
+         Damage taken: {}\n\
+         Enemies defeated: {}\n\
+         Units lost: {}\n\n\
+         (Enter for the main menu, R to watch the replay)",
+        stats.turns,
+        round.completed,
+        stats.damage_dealt,
+        stats.damage_taken,
+        stats.enemies_defeated,
+        stats.units_lost,
+    );
+    commands.spawn((
+        Text::new(summary),
+        TextFont {
+            font_size: 32.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(80.0),
+            left: Val::Px(80.0),
+            padding: UiRect::all(Val::Px(16.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+        StateScoped(MatchState::Results),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_count_damage_and_deaths_by_side() {
+        let mut stats = MatchStats::default();
+        stats.record(Unit::Enemy, 8, 5);
+        stats.record(Unit::Enemy, 5, 0);
+        stats.record(Unit::Player, 10, 12);
+        stats.record(Unit::Player, 12, -2);
+        assert_eq!(
+            stats,
+            MatchStats {
+                turns: 0,
+                damage_dealt: 8,
+                damage_taken: 14,
+                units_lost: 1,
+                enemies_defeated: 1,
+            }
+        );
+    }
+}
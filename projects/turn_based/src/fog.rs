@@ -0,0 +1,184 @@
+// Fog of war: what each side can see from where its units stand, and what it has seen before.
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::pathfinding::{Coord, Grid};
+use crate::unit::Stats;
+use crate::{Map, TilePos, Unit};
+
+/// Tiles one side sees now and has ever seen.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vision {
+    pub visible: HashSet<Coord>,
+    pub explored: HashSet<Coord>,
+}
+
+impl Vision {
+    /// Look again from `eyes`, each a unit's position and sight radius.
+    pub fn update(&mut self, grid: &Grid, eyes: impl IntoIterator<Item = (Coord, u32)>) {
+        self.visible.clear();
+        for (from, radius) in eyes {
+            self.visible.extend(visible_tiles(grid, from, radius));
+        }
+        self.explored.extend(self.visible.iter().copied());
+    }
+
+    pub fn is_visible(&self, tile: Coord) -> bool {
+        self.visible.contains(&tile)
+    }
+
+    pub fn is_explored(&self, tile: Coord) -> bool {
+        self.explored.contains(&tile)
+    }
+}
+
+/// Vision of both sides. Allied units share what they see.
+#[derive(Resource, Debug, Default, PartialEq, Eq)]
+pub struct FogOfWar {
+    pub player: Vision,
+    pub enemy: Vision,
+}
+
+impl FogOfWar {
+    pub fn side(&self, side: Unit) -> &Vision {
+        match side {
+            Unit::Player => &self.player,
+            Unit::Enemy => &self.enemy,
+        }
+    }
+}
+
+/// Look again from wherever the living units now stand. Only touches the resource when what
+/// either side sees has changed, so systems can redraw on `is_changed`.
+pub fn update_fog(
+    map: Res<Map>,
+    units: Query<(&Unit, &TilePos, &Stats)>,
+    mut fog: ResMut<FogOfWar>,
+) {
+    let eyes = |side: Unit| {
+        units
+            .iter()
+            .filter(move |(unit, _, stats)| **unit == side && stats.hp > 0)
+            .map(|(_, pos, stats)| ((pos.x, pos.y), stats.sight))
+    };
+    let mut next = FogOfWar {
+        player: fog.player.clone(),
+        enemy: fog.enemy.clone(),
+    };
+    next.player.update(&map.0, eyes(Unit::Player));
+    next.enemy.update(&map.0, eyes(Unit::Enemy));
+    fog.set_if_neq(next);
+}
+
+/// Hide enemy units the player's side cannot see.
+pub fn hide_unseen_enemies(
+    fog: Res<FogOfWar>,
+    mut units: Query<(&Unit, &TilePos, &mut Visibility)>,
+) {
+    for (unit, pos, mut visibility) in units.iter_mut() {
+        let seen = *unit == Unit::Player || fog.player.is_visible((pos.x, pos.y));
+        visibility.set_if_neq(if seen {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
+/// Tiles within a roughly round `radius` of `from` that nothing blocks the view to.
+pub fn visible_tiles(grid: &Grid, from: Coord, radius: u32) -> HashSet<Coord> {
+    let (fx, fy, r) = (from.0 as i64, from.1 as i64, radius as i64);
+    let mut tiles = HashSet::new();
+    for y in (fy - r).max(0)..=(fy + r) {
+        for x in (fx - r).max(0)..=(fx + r) {
+            let tile = (x as u32, y as u32);
+            let (dx, dy) = (x - fx, y - fy);
+            // `r * (r + 1)` rather than `r * r` keeps lone tiles from poking out of the sides.
+            let within = dx * dx + dy * dy <= r * (r + 1);
+            if within && grid.in_bounds(tile) && line_of_sight(grid, from, tile) {
+                tiles.insert(tile);
+            }
+        }
+    }
+    tiles
+}
+
+/// Whether `to` can be seen from `from`: no tile strictly between them on a Bresenham line
+/// blocks sight. A wall or mountain can itself be seen, but not past.
+pub fn line_of_sight(grid: &Grid, from: Coord, to: Coord) -> bool {
+    let (mut x, mut y) = (from.0 as i64, from.1 as i64);
+    let (x1, y1) = (to.0 as i64, to.1 as i64);
+    let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+    let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+    let mut err = dx + dy;
+    loop {
+        if (x, y) == (x1, y1) {
+            return true;
+        }
+        if (x, y) != (from.0 as i64, from.1 as i64)
+            && grid.terrain((x as u32, y as u32)).blocks_sight()
+        {
+            return false;
+        }
+        let doubled = 2 * err;
+        if doubled >= dy {
+            err += dy;
+            x += sx;
+        }
+        if doubled <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::Terrain;
+
+    #[test]
+    fn open_ground_is_seen_within_the_radius() {
+        let grid = Grid::new(10, 10);
+        let tiles = visible_tiles(&grid, (5, 5), 2);
+        // The 5x5 square minus its four corners.
+        assert_eq!(tiles.len(), 21);
+        assert!(tiles.contains(&(5, 7)));
+        assert!(tiles.contains(&(6, 6)));
+        assert!(!tiles.contains(&(7, 7)));
+        assert_eq!(visible_tiles(&grid, (0, 0), 1).len(), 4);
+    }
+
+    #[test]
+    fn walls_and_mountains_block_sight_but_forest_does_not() {
+        let mut grid = Grid::new(10, 10);
+        grid.set_terrain((3, 0), Terrain::Wall);
+        grid.set_terrain((0, 3), Terrain::Mountain);
+        grid.set_terrain((2, 2), Terrain::Forest);
+        assert!(line_of_sight(&grid, (0, 0), (3, 0)));
+        assert!(!line_of_sight(&grid, (0, 0), (5, 0)));
+        assert!(!line_of_sight(&grid, (0, 0), (0, 5)));
+        assert!(line_of_sight(&grid, (0, 0), (4, 4)));
+        // Blocked both ways.
+        assert!(!line_of_sight(&grid, (5, 0), (0, 0)));
+
+        let tiles = visible_tiles(&grid, (0, 0), 6);
+        assert!(tiles.contains(&(3, 0)));
+        assert!(!tiles.contains(&(4, 0)));
+        assert!(tiles.contains(&(4, 1)));
+    }
+
+    #[test]
+    fn explored_tiles_stay_explored() {
+        let grid = Grid::new(10, 10);
+        let mut vision = Vision::default();
+        vision.update(&grid, [((0, 0), 2)]);
+        assert!(vision.is_visible((2, 0)));
+        vision.update(&grid, [((9, 9), 2)]);
+        assert!(!vision.is_visible((2, 0)));
+        assert!(vision.is_explored((2, 0)));
+        assert!(vision.is_visible((9, 7)));
+        assert!(!vision.is_explored((5, 5)));
+    }
+}
@@ -1,48 +1,179 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
+use crate::ability::{Abilities, ENERGY_PER_TURN};
+use crate::combat::TurnActions;
+use crate::status::Statuses;
 use crate::unit::Stats;
 
-#[derive(Resource)]
-pub struct TurnQueue(Vec<Entity>);
+/// Units in the order they act, fastest first; the unit acting now is at the front.
+#[derive(Resource, Default)]
+pub struct TurnQueue(pub Vec<Entity>);
 
-#[derive(Resource)]
-pub struct CurrentTurn(Option<Entity>);
-#[derive(Event)]
-pub struct EndTurnEvent;
-pub fn setup_turn_queue(
-    mut commands: Commands,
-    units: Query<(Entity, &Stats)>,
-) {
-    let mut queue: Vec<(Entity, u32)> = units.iter().map(|(e, stats)| (e, stats.speed)).collect();
-
-    // sort by speed descending
-    queue.sort_by_key(|&(_, speed)| std::cmp::Reverse(speed));
+impl TurnQueue {
+    pub fn remove(&mut self, entity: Entity) {
+        self.0.retain(|&queued| queued != entity);
+    }
+}
 
-    let ordered_entities = queue.into_iter().map(|(e, _)| e).collect();
+/// The unit whose turn it is, if one has started.
+#[derive(Resource, Default)]
+pub struct CurrentTurn(pub Option<Entity>);
 
-    commands.insert_resource(TurnQueue(ordered_entities));
-    commands.insert_resource(CurrentTurn(None));
+/// Rounds played so far; a round ends once every unit queued when it began has acted or died.
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Round {
+    pub completed: u32,
+    /// Units yet to act this round, filled from the queue as its first turn ends. Saves keep
+    /// these as indices of their own.
+    #[serde(skip)]
+    pub waiting: Vec<Entity>,
 }
 
+#[derive(Event)]
+pub struct EndTurnEvent;
+
+type TurnUnitQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut TurnActions,
+        &'static mut Stats,
+        &'static mut Statuses,
+        Option<&'static mut Abilities>,
+    ),
+>;
+
+/// Start the next unit's turn: it gets some energy back and its statuses take hold. A unit
+/// that is stunned, or dies of poison, loses its turn to the one after it without acting.
 pub fn begin_turn(
     mut current: ResMut<CurrentTurn>,
     mut queue: ResMut<TurnQueue>,
+    mut round: ResMut<Round>,
+    mut units: TurnUnitQuery,
 ) {
-    if current.0.is_none() {
-        if let Some(next) = queue.0.first().copied() {
+    if current.0.is_some() {
+        return;
+    }
+    // Skip anything that died or was removed since it was queued
+    queue
+        .0
+        .retain(|&entity| units.get(entity).is_ok_and(|(_, stats, ..)| stats.hp > 0));
+    // Once around the queue at most, should everyone be stunned.
+    for _ in 0..queue.0.len() {
+        let Some(next) = queue.0.first().copied() else {
+            return;
+        };
+        let Ok((_, mut stats, statuses, _)) = units.get_mut(next) else {
+            return;
+        };
+        stats.energy = (stats.energy + ENERGY_PER_TURN).min(stats.max_energy);
+        if !statuses.start_turn(&mut stats) && stats.hp > 0 {
             current.0 = Some(next);
-            println!("Turn begins for {:?}", next);
+            info!("Turn begins for {:?}", next);
+            return;
         }
+        info!("{:?} loses its turn", next);
+        finish_turn(next, &mut queue, &mut round, &mut units);
     }
 }
 
 pub fn end_turn(
+    mut events: EventReader<EndTurnEvent>,
     mut current: ResMut<CurrentTurn>,
     mut queue: ResMut<TurnQueue>,
+    mut round: ResMut<Round>,
+    mut units: TurnUnitQuery,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    if let Some(done_unit) = current.0.take() {
+        finish_turn(done_unit, &mut queue, &mut round, &mut units);
+    }
+}
+
+/// Send `done_unit` to the back of the queue, fresh for its next turn, with statuses and
+/// cooldowns one turn shorter.
+fn finish_turn(
+    done_unit: Entity,
+    queue: &mut TurnQueue,
+    round: &mut Round,
+    units: &mut TurnUnitQuery,
 ) {
-    if let Some(done_unit) = current.0 {
-        queue.0.remove(0);
-        queue.0.push(done_unit); // rotate to back
-        current.0 = None;
+    if let Ok((mut actions, mut stats, mut statuses, abilities)) = units.get_mut(done_unit) {
+        *actions = TurnActions::default();
+        statuses.end_turn(&mut stats);
+        if let Some(mut abilities) = abilities {
+            abilities.cool_down();
+        }
+    }
+    if round.waiting.is_empty() {
+        round.waiting = queue.0.clone();
+    }
+    queue.remove(done_unit);
+    queue.0.push(done_unit); // rotate to back
+
+    // The dead no longer hold the round up.
+    round.waiting.retain(|&unit| {
+        unit != done_unit && units.get(unit).is_ok_and(|(_, stats, ..)| stats.hp > 0)
+    });
+    if round.waiting.is_empty() {
+        round.completed += 1;
+        info!("Round {} over", round.completed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn no_unit_acts_twice_in_a_round() {
+        let mut world = World::new();
+        let units: Vec<Entity> = (0..3)
+            .map(|_| {
+                world
+                    .spawn((
+                        TurnActions::default(),
+                        Stats { hp: 5, ..default() },
+                        Statuses::default(),
+                    ))
+                    .id()
+            })
+            .collect();
+        world.insert_resource(TurnQueue(units.clone()));
+        world.init_resource::<CurrentTurn>();
+        world.init_resource::<Round>();
+        world.init_resource::<Events<EndTurnEvent>>();
+        let take_turn = |world: &mut World| {
+            world.run_system_once(begin_turn).unwrap();
+            let actor = world.resource::<CurrentTurn>().0.unwrap();
+            world.send_event(EndTurnEvent);
+            world.run_system_once(end_turn).unwrap();
+            actor
+        };
+        let play_round = |world: &mut World, acted: &mut Vec<Entity>| {
+            let round = world.resource::<Round>().completed;
+            while world.resource::<Round>().completed == round {
+                acted.push(take_turn(world));
+            }
+        };
+
+        let mut acted = vec![take_turn(&mut world)];
+        // The last unit in line dies before its turn comes.
+        world.get_mut::<Stats>(units[2]).unwrap().hp = 0;
+        play_round(&mut world, &mut acted);
+        assert_eq!(acted, units[..2]);
+        assert_eq!(world.resource::<Round>().completed, 1);
+
+        let mut acted = vec![take_turn(&mut world)];
+        // One that has had its turn dies; the other still gets one.
+        world.get_mut::<Stats>(acted[0]).unwrap().hp = 0;
+        play_round(&mut world, &mut acted);
+        assert_eq!(acted, units[..2]);
+        assert_eq!(world.resource::<Round>().completed, 2);
     }
-}
\ No newline at end of file
+}
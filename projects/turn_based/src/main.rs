@@ -1,22 +1,42 @@
 mod unit;
 mod logic;
+mod ability;
+mod action;
+mod ai;
+mod combat;
+mod flow;
+mod fog;
+mod pathfinding;
+mod replay;
+mod save;
+mod scenario;
+mod status;
+mod terrain;
+mod turn_order;
 
 use bevy::prelude::Color;
+use bevy::ecs::system::SystemParam;
 use bevy::{input::mouse::*, prelude::*};
-
-use bevy::color::Color::Srgba;
-use bevy::text::cosmic_text::Wrap::Word;
-use logic::*;
+use serde::{Deserialize, Serialize};
+
+use ability::*;
+use action::*;
+use ai::*;
+use combat::*;
+use flow::*;
+use fog::*;
 use logic::*;
-use rand::seq::SliceRandom;
-use rand::thread_rng;
+use pathfinding::*;
+use replay::*;
+use save::*;
+use scenario::*;
 use std::collections::HashSet;
+use terrain::Terrain;
+use turn_order::*;
 use unit::*;
 
 
 const TILE_SIZE: f32 = 64.0;
-const GRID_WIDTH: u32 = 10;
-const GRID_HEIGHT: u32 = 10;
 
 fn is_player_turn(turn: Res<Turn>) -> bool {
     *turn == Turn::Player
@@ -28,20 +48,121 @@ fn is_ai_turn(turn: Res<Turn>) -> bool {
 
 fn main() {
     App::new()
-        .add_plugins(DefaultPlugins)
+        // Watch the assets folder so edited scenarios reload while the game runs.
+        .add_plugins(DefaultPlugins.set(AssetPlugin {
+            watch_for_changes_override: Some(true),
+            ..default()
+        }))
+        .init_asset::<Scenario>()
+        .init_asset_loader::<ScenarioLoader>()
         .insert_resource(HoveredTile(None))
         .insert_resource(SelectedUnit(None))
+        .init_resource::<Aiming>()
         .insert_resource(Turn::Player)
-        .insert_resource(AIDone(true))
-        .insert_resource(PlayerDone(false))
+        .insert_resource(AiRng::from_args())
+        .init_resource::<Map>()
+        .init_resource::<TurnQueue>()
+        .init_resource::<CurrentTurn>()
+        .init_resource::<Round>()
+        .init_resource::<MatchResult>()
+        .init_resource::<FogOfWar>()
+        .init_resource::<MatchStats>()
+        .init_resource::<DeploymentZone>()
+        .init_state::<MatchState>()
+        .enable_state_scoped_entities::<MatchState>()
         .add_event::<EndTurnEvent>()
-        .add_systems(Startup, (setup, setup_turn_queue))
+        .add_event::<Action>()
+        .add_systems(Startup, (setup, spawn_turn_order_bar, spawn_ability_text))
+        .add_systems(OnEnter(MatchState::MainMenu), (clear_board, spawn_main_menu))
+        .add_systems(OnEnter(MatchState::Deployment), start_deployment)
+        .add_systems(OnEnter(MatchState::Battle), start_recording)
+        .add_systems(OnExit(MatchState::Battle), leave_battle)
+        .add_systems(OnEnter(MatchState::Victory), write_replay)
+        .add_systems(OnEnter(MatchState::Defeat), write_replay)
+        .add_systems(OnEnter(MatchState::Results), spawn_results)
+        // In PreUpdate so the new map and units exist by the time the turn loop looks at them.
+        .add_systems(
+            PreUpdate,
+            (
+                restart_on_reload,
+                spawn_scenario.run_if(resource_exists::<StartPending>),
+                quick_load.run_if(in_state(MatchState::Battle)),
+                fit_camera_to_map,
+            )
+                .chain(),
+        )
+        .add_systems(Update, press_enter)
+        .add_systems(Update, pick_scenario.run_if(in_state(MatchState::MainMenu)))
+        .add_systems(
+            Update,
+            watch_replay.run_if(in_state(MatchState::MainMenu).or(in_state(MatchState::Results))),
+        )
+        .add_systems(
+            Update,
+            quick_save
+                .run_if(is_player_turn)
+                .run_if(in_state(MatchState::Battle)),
+        )
         .add_systems(Update, highlight_tile_under_cursor)
-        .add_systems(Update, (handle_clicks.run_if(is_player_turn), highlight_reachable_tiles.after(handle_clicks),))
-        .add_systems(Update, end_player_turn)
-        .add_systems(Update, ai_turn_system.run_if(is_ai_turn))
+        .add_systems(
+            Update,
+            deploy_units
+                .after(highlight_tile_under_cursor)
+                .run_if(in_state(MatchState::Deployment)),
+        )
+        // One unit acts at a time, in speed order: pick it, let it act, bury the dead, pass on.
+        // The player, the AI or a replay decides on its actions; only `perform_actions` carries
+        // them out. The match is decided as soon as any action settles it.
+        .add_systems(
+            Update,
+            (
+                begin_turn,
+                start_unit_turn,
+                (
+                    (
+                        (cast_ability, handle_clicks).chain().run_if(is_player_turn),
+                        ai_turn_system.run_if(is_ai_turn),
+                    )
+                        .run_if(in_state(MatchState::Battle)),
+                    play_replay.run_if(in_state(MatchState::Replay)),
+                ),
+                perform_actions,
+                tally_match_stats,
+                despawn_dead,
+                end_turn,
+                check_victory.run_if(in_state(MatchState::Battle)),
+            )
+                .chain()
+                .run_if(in_state(MatchState::Battle).or(in_state(MatchState::Replay))),
+        )
+        // Look again once units have moved, before anything is drawn from what they see.
+        .add_systems(
+            Update,
+            (update_fog, hide_unseen_enemies)
+                .chain()
+                .after(despawn_dead)
+                .before(highlight_reachable_tiles),
+        )
+        // Also runs on the AI's turns, to put away what the player was aiming.
+        .add_systems(Update, choose_ability.after(start_unit_turn).before(cast_ability))
+        .add_systems(Update, highlight_reachable_tiles.after(handle_clicks))
+        .add_systems(
+            Update,
+            highlight_deployment_zone
+                .after(highlight_reachable_tiles)
+                .after(highlight_tile_under_cursor)
+                .run_if(in_state(MatchState::Deployment)),
+        )
+        .add_systems(
+            Update,
+            preview_ability_area
+                .after(highlight_reachable_tiles)
+                .after(highlight_tile_under_cursor),
+        )
+        .add_systems(Update, update_ability_text.after(handle_clicks))
         .add_systems(Update, update_turn_text)
-        .add_systems(Update, end_ai_turn)
+        .add_systems(Update, update_turn_order_bar.after(end_turn))
+        .add_systems(Update, walk_units)
         .run();
 }
 
@@ -57,10 +178,14 @@ struct TilePos {
 #[derive(Resource)]
 struct HoveredTile(Option<Entity>);
 
+/// The grid units move on.
+#[derive(Resource, Default)]
+struct Map(Grid);
+
 #[derive(Resource)]
 struct SelectedUnit(Option<Entity>);
 
-#[derive(Component)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 enum Unit {
     Player,
     Enemy,
@@ -75,115 +200,12 @@ enum Turn {
     AI,
 }
 
-#[derive(Resource, Default)]
-struct PlayerDone(bool);
-
-#[derive(Resource, Default)]
-struct AIDone(bool);
-
-fn setup(mut commands: Commands) {
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     // Spawn 2D camera
     commands.spawn(Camera2d);
 
-    // Center offset
-    let offset_x = -(GRID_WIDTH as f32 * TILE_SIZE) / 2.0 + TILE_SIZE / 2.0;
-    let offset_y = -(GRID_HEIGHT as f32 * TILE_SIZE) / 2.0 + TILE_SIZE / 2.0;
-
-    // Spawn grid tiles
-    for y in 0..GRID_HEIGHT {
-        for x in 0..GRID_WIDTH {
-            commands.spawn((
-                Sprite {
-                    color: Color::srgb(0.2, 0.2, 0.8),
-                    custom_size: Some(Vec2::splat(TILE_SIZE - 2.0)), // 2px gap between tiles
-                    ..default()
-                },
-                Transform::from_xyz(
-                    x as f32 * TILE_SIZE + offset_x,
-                    y as f32 * TILE_SIZE + offset_y,
-                    0.0,
-                ),
-                Tile,
-                TilePos { x, y },
-            ));
-        }
-    }
-
-    spawn_unit(
-        &mut commands,
-        1,
-        1,
-        Unit::Player,
-        Color::srgb(0.2, 1.0, 0.2),
-        offset_x,
-        offset_y,
-        Stats {
-            hp: 10,
-            max_hp: 10,
-            attack: 4,
-            defense: 1,
-            movement: 3,
-            range: 1,
-            ..default()
-        },
-    );
-
-    spawn_unit(
-        &mut commands,
-        2,
-        2,
-        Unit::Player,
-        Color::srgb(0.2, 1.0, 0.2),
-        offset_x,
-        offset_y,
-        Stats {
-            hp: 10,
-            max_hp: 10,
-            attack: 4,
-            defense: 1,
-            movement: 3,
-            range: 1,
-            ..default()
-        },
-    );
-
-    spawn_unit(
-        &mut commands,
-        8,
-        8,
-        Unit::Enemy,
-        Color::srgb(1.0, 0.2, 0.2),
-        offset_x,
-        offset_y,
-        Stats {
-            hp: 6,
-            max_hp: 6,
-            attack: 3,
-            defense: 0,
-            movement: 2,
-            range: 1,
-            ..default()
-        },
-    );
-
-    spawn_unit(
-        &mut commands,
-        5,
-        8,
-        Unit::Enemy,
-        Color::srgb(1.0, 0.2, 0.4),
-        offset_x,
-        offset_y,
-        Stats {
-            hp: 6,
-            max_hp: 6,
-            attack: 3,
-            defense: 0,
-            movement: 2,
-            range: 1,
-            ..default()
-        },
-    );
+    // The map and units are spawned by `spawn_scenario` once a match starts on it
+    commands.insert_resource(ActiveScenario(asset_server.load(scenario_path_from_args())));
 
     commands.spawn((
         // Accepts a `String` or any type that converts into a `String`, such as `&str`
@@ -206,12 +228,116 @@ fn setup(mut commands: Commands) {
     ));
 }
 
+/// Everything a scenario spawns, cleared before it is spawned again.
+type ScenarioEntities<'w, 's> = Query<'w, 's, Entity, Or<(With<Tile>, With<Unit>)>>;
+
+/// Set the board up for a new match, as soon as the scenario has loaded.
+fn spawn_scenario(
+    mut commands: Commands,
+    active: Res<ActiveScenario>,
+    scenarios: Res<Assets<Scenario>>,
+    old: ScenarioEntities,
+    mut selected: ResMut<SelectedUnit>,
+    ai_rng: Res<AiRng>,
+) {
+    let Some(scenario) = scenarios.get(&active.0) else { return };
+    info!("Starting {}", scenario.name);
+
+    for entity in old.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    // A new match is a save of the opening position. Every restart plays out the same way for
+    // the same seed.
+    let path = active.0.path().map(|path| path.to_string()).unwrap_or_default();
+    if let Err(err) = SaveGame::from_scenario(&path, scenario, ai_rng.seed).restore(&mut commands) {
+        error!("{err}");
+    }
+    commands.insert_resource(DeploymentZone(scenario.deployment_zone()));
+    commands.remove_resource::<StartPending>();
+    selected.0 = None;
+}
+
+/// Zoom out far enough for big maps to fit the default window.
+fn fit_camera_to_map(
+    map: Res<Map>,
+    mut cameras: Query<&mut OrthographicProjection, With<Camera2d>>,
+) {
+    if !map.is_changed() {
+        return;
+    }
+    let extent = map.0.width.max(map.0.height) as f32 * TILE_SIZE;
+    for mut projection in cameras.iter_mut() {
+        projection.scale = (extent / 680.0).max(1.0);
+    }
+}
+
+/// Decide the match once the scenario's victory conditions say so.
+fn check_victory(
+    active: Res<ActiveScenario>,
+    scenarios: Res<Assets<Scenario>>,
+    round: Res<Round>,
+    units: Query<(&Unit, &TilePos, &Stats, Has<Vip>)>,
+    mut result: ResMut<MatchResult>,
+    mut next: ResMut<NextState<MatchState>>,
+) {
+    let Some(scenario) = scenarios.get(&active.0) else { return };
+    let survivors: Vec<Survivor> = units
+        .iter()
+        .filter(|(_, _, stats, _)| stats.hp > 0)
+        .map(|(unit, pos, _, vip)| Survivor {
+            side: *unit,
+            position: (pos.x, pos.y),
+            vip,
+        })
+        .collect();
+    // Nothing spawned yet.
+    if survivors.is_empty() {
+        return;
+    }
+    if let Some(winner) = scenario.winner(&survivors, round.completed) {
+        info!("{:?} side wins", winner);
+        result.0 = Some(winner);
+        next.set(match winner {
+            Unit::Player => MatchState::Victory,
+            Unit::Enemy => MatchState::Defeat,
+        });
+    }
+}
+
+/// Unhighlighted color of a tile, as the player's side sees it: dark until explored, and
+/// dimmed while out of sight.
+fn tile_color(grid: &Grid, vision: &Vision, tile: Coord) -> Color {
+    let color = match grid.terrain(tile) {
+        Terrain::Plains => Color::srgb(0.55, 0.6, 0.35),
+        Terrain::Forest => Color::srgb(0.1, 0.35, 0.15),
+        Terrain::Water => Color::srgb(0.15, 0.3, 0.75),
+        Terrain::Mountain => Color::srgb(0.5, 0.4, 0.3),
+        Terrain::Wall => Color::srgb(0.25, 0.25, 0.25),
+    };
+    if vision.is_visible(tile) {
+        color
+    } else if vision.is_explored(tile) {
+        color.mix(&Color::BLACK, 0.55)
+    } else {
+        Color::srgb(0.05, 0.05, 0.07)
+    }
+}
+
+/// World position of the centre of a tile, at height `z`, with the map centred on the origin.
+fn tile_to_world(grid: &Grid, (x, y): Coord, z: f32) -> Vec3 {
+    let offset_x = -(grid.width as f32 * TILE_SIZE) / 2.0 + TILE_SIZE / 2.0;
+    let offset_y = -(grid.height as f32 * TILE_SIZE) / 2.0 + TILE_SIZE / 2.0;
+    Vec3::new(x as f32 * TILE_SIZE + offset_x, y as f32 * TILE_SIZE + offset_y, z)
+}
 
 fn highlight_tile_under_cursor(
     windows: Query<&Window>,
     camera_q: Query<(&Camera, &GlobalTransform)>,
-    mut tiles: Query<(Entity, &mut Sprite, &Transform), With<Tile>>,
+    mut tiles: Query<(Entity, &mut Sprite, &Transform, &TilePos), With<Tile>>,
     mut hovered: ResMut<HoveredTile>,
+    map: Res<Map>,
+    fog: Res<FogOfWar>,
 ) {
     let window = windows.single();
     let (camera, cam_transform) = camera_q.single();
@@ -224,7 +350,7 @@ fn highlight_tile_under_cursor(
             // Find tile under cursor
             let mut new_hovered = None;
 
-            for (entity, mut sprite, transform) in &mut tiles {
+            for (entity, mut sprite, transform, tile_pos) in &mut tiles {
                 let pos = transform.translation.truncate();
                 let half_size = TILE_SIZE / 2.0;
 
@@ -238,7 +364,7 @@ fn highlight_tile_under_cursor(
                     new_hovered = Some(entity);
                 } else if Some(entity) == hovered.0 {
                     // Restore color for previously hovered tile
-                    sprite.color = Color::srgb(0.2, 0.2, 0.8);
+                    sprite.color = tile_color(&map.0, &fog.player, (tile_pos.x, tile_pos.y));
                 }
             }
 
@@ -247,17 +373,30 @@ fn highlight_tile_under_cursor(
     }
 }
 
+/// Turn the player's keys and clicks into actions for the selected unit.
 fn handle_clicks(
     buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
     windows: Query<&Window>,
     camera_q: Query<(&Camera, &GlobalTransform)>,
-    turn: Res<Turn>,
-    mut selected: ResMut<SelectedUnit>,
-    mut unit_query: Query<(Entity, &mut TilePos, &mut Sprite, &Stats), (With<Unit>, Without<Tile>)>,
-    mut tile_query: Query<(&TilePos, &Transform, &mut Sprite), (With<Tile>, Without<Unit>)>,
-    mut unit_transforms: Query<&mut Transform, With<Unit>>,
-    mut player_done: ResMut<PlayerDone>,
+    selected: Res<SelectedUnit>,
+    unit_query: Query<(&TilePos, &Stats, &Unit)>,
+    tile_query: Query<&TilePos, With<Tile>>,
+    map: Res<Map>,
+    fog: Res<FogOfWar>,
+    mut actions: EventWriter<Action>,
 ) {
+    // The acting unit is selected when its turn starts
+    if selected.0.is_none() {
+        return;
+    }
+
+    // Space ends the unit's turn early; it also ends once the unit has moved and attacked
+    if keys.just_pressed(KeyCode::Space) {
+        actions.send(Action::EndTurn);
+        return;
+    }
+
     if !buttons.just_pressed(MouseButton::Left) {
         return;
     }
@@ -269,205 +408,217 @@ fn handle_clicks(
     let Ok(ray) = camera.viewport_to_world(cam_transform, cursor_pos) else { return };
     let cursor_world = ray.origin.truncate();
 
-    // Only handle player input during player turn
-    if *turn != Turn::Player {
-        return;
-    }
-
-    // Try to select a unit
-    if try_select_unit(
-        cursor_world,
-        &mut selected,
-        &mut unit_query,
-    ) {
-        return; // successfully selected, exit early
-    }
-
-    // If already selected, try to move it
-    if let Some(selected_entity) = selected.0 {
-        if try_move_selected_unit(
-            selected_entity,
-            cursor_world,
-            &mut unit_query,
-            &mut tile_query,
-            &mut unit_transforms,
-        ) {
-            selected.0 = None;
-            player_done.0 = true;
-        }
+    // Clicking an enemy in sight attacks it, clicking a tile moves there
+    let enemy = unit_query.iter().find(|(pos, stats, unit)| {
+        **unit == Unit::Enemy
+            && stats.hp > 0
+            && fog.player.is_visible((pos.x, pos.y))
+            && tile_contains(&map.0, pos, cursor_world)
+    });
+    if let Some((pos, ..)) = enemy {
+        actions.send(Action::Attack { target: (pos.x, pos.y) });
+    } else if let Some(tile) = tile_query.iter().find(|tile| tile_contains(&map.0, tile, cursor_world)) {
+        actions.send(Action::Move { to: (tile.x, tile.y) });
     }
 }
 
-fn try_select_unit(
-    cursor_world: Vec2,
-    selected: &mut ResMut<SelectedUnit>,
-    unit_query: &mut Query<(Entity, &mut TilePos, &mut Sprite, &Stats), (With<Unit>, Without<Tile>)>,
-) -> bool {
-    for (entity, pos, mut sprite, _) in unit_query.iter_mut() {
-        let world_x = pos.x as f32 * TILE_SIZE - (GRID_WIDTH as f32 * TILE_SIZE) / 2.0 + TILE_SIZE / 2.0;
-        let world_y = pos.y as f32 * TILE_SIZE - (GRID_HEIGHT as f32 * TILE_SIZE) / 2.0 + TILE_SIZE / 2.0;
-        let half = TILE_SIZE * 0.5;
-
-        if cursor_world.x >= world_x - half && cursor_world.x <= world_x + half &&
-            cursor_world.y >= world_y - half && cursor_world.y <= world_y + half {
+fn tile_contains(grid: &Grid, tile: &TilePos, cursor_world: Vec2) -> bool {
+    let Vec3 { x: world_x, y: world_y, .. } = tile_to_world(grid, (tile.x, tile.y), 0.0);
+    let half = TILE_SIZE * 0.5;
 
-            // Deselect old unit (no need to reset alpha here unless you store previous selection)
-            selected.0 = Some(entity);
-            sprite.color.set_alpha(0.6);
-            return true;
-        }
-    }
-    false
+    cursor_world.x >= world_x - half && cursor_world.x <= world_x + half &&
+        cursor_world.y >= world_y - half && cursor_world.y <= world_y + half
 }
 
-fn try_move_selected_unit(
-    selected_entity: Entity,
-    cursor_world: Vec2,
-    unit_query: &mut Query<(Entity, &mut TilePos, &mut Sprite, &Stats), (With<Unit>, Without<Tile>)>,
-    tile_query: &mut Query<(&TilePos, &Transform, &mut Sprite), (With<Tile>, Without<Unit>)>,
-    unit_transforms: &mut Query<&mut Transform, With<Unit>>,
-) -> bool {
-    let Ok((_, mut unit_pos, _, stats)) = unit_query.get_mut(selected_entity) else {
-        return false;
-    };
-
-    for (tile_pos, tile_transform, _) in tile_query.iter_mut() {
-        let world_pos = tile_transform.translation.truncate();
-        let half = TILE_SIZE * 0.5;
-
-        if cursor_world.x >= world_pos.x - half && cursor_world.x <= world_pos.x + half &&
-            cursor_world.y >= world_pos.y - half && cursor_world.y <= world_pos.y + half {
-            let dx = (tile_pos.x as i32 - unit_pos.x as i32).abs();
-            let dy = (tile_pos.y as i32 - unit_pos.y as i32).abs();
-            if dx + dy > stats.movement as i32 {
-                info!("Tile too far");
-                return false;
-            }
-
-            if let Ok(mut transform) = unit_transforms.get_mut(selected_entity) {
-                transform.translation = tile_transform.translation + Vec3::new(0.0, 0.0, 1.0);
-            }
+/// Hand control to the unit whose turn just began, selecting it if it is the player's. Its
+/// `TurnActions` were reset when its last turn ended, or restored from a save.
+fn start_unit_turn(
+    current: Res<CurrentTurn>,
+    mut turn: ResMut<Turn>,
+    mut selected: ResMut<SelectedUnit>,
+    mut units: Query<(&Unit, &mut Sprite)>,
+) {
+    if !current.is_changed() {
+        return;
+    }
 
-            *unit_pos = tile_pos.clone();
-            return true;
+    if let Some(previous) = selected.0.take() {
+        if let Ok((_, mut sprite)) = units.get_mut(previous) {
+            sprite.color.set_alpha(1.0);
         }
     }
 
-    false
-}
-
-fn end_ai_turn(mut turn: ResMut<Turn>, mut done: ResMut<AIDone>) {
-    if done.0 {
-        done.0 = false;
-        *turn = Turn::Player;
-        info!("Switching to Player turn");
+    let Some(entity) = current.0 else { return };
+    let Ok((unit, mut sprite)) = units.get_mut(entity) else { return };
+    match unit {
+        Unit::Player => {
+            *turn = Turn::Player;
+            selected.0 = Some(entity);
+            sprite.color.set_alpha(0.6);
+        }
+        Unit::Enemy => *turn = Turn::AI,
     }
 }
 
-fn end_player_turn(mut turn: ResMut<Turn>, mut done: ResMut<PlayerDone>) {
-    if done.0 {
-        done.0 = false;
-        *turn = Turn::AI;
-        info!("Switching to AI turn");
-    }
+type AiUnitQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static TilePos,
+        &'static Unit,
+        &'static Stats,
+        &'static TurnActions,
+        Option<&'static Tactics>,
+        Has<Walking>,
+    ),
+>;
+
+/// The map and what each side can see of it.
+#[derive(SystemParam)]
+struct Battlefield<'w> {
+    map: Res<'w, Map>,
+    fog: Res<'w, FogOfWar>,
 }
 
-/// One AI turn: pick each enemy unit and move it to a random adjacent empty tile.
-/// When every enemy has tried to move, mark the turn as done.
+/// The acting enemy's turn, as its faction's strategy plans it: walk somewhere, then, once it
+/// has arrived, attack the unit it planned to. It plans only around the units its side can see.
 fn ai_turn_system(
-    mut done: ResMut<AIDone>,
-    mut unit_query: Query<(Entity, &mut TilePos, &mut Transform, &Unit)>,
+    current: Res<CurrentTurn>,
+    field: Battlefield,
+    mut ai_rng: ResMut<AiRng>,
+    mut planned_target: Local<Option<Entity>>,
+    mut actions: EventWriter<Action>,
+    unit_query: AiUnitQuery,
 ) {
-    // Build a quick‐lookup set of all occupied tiles.
-    let mut occupied: HashSet<(u32, u32)> = HashSet::new();
-    for (_, pos, _, _) in unit_query.iter() {
-        occupied.insert((pos.x, pos.y));
+    let Some(enemy) = current.0 else { return };
+    let map = &field.map;
+    let Ok((_, _, side, _, turn_actions, tactics, walking)) = unit_query.get(enemy) else {
+        return;
+    };
+    // Let the walk finish before doing anything else.
+    if walking {
+        return;
     }
 
-    // Directions the AI can try (right, left, up, down).
-    let mut dirs = [(1i32, 0i32), (-1, 0), (0, 1), (0, -1)];
-    let mut rng = thread_rng();
+    if !turn_actions.moved {
+        let tactics = tactics.copied().unwrap_or_default();
+
+        // Position order: no two units share a tile, and unlike entity ids, which come back
+        // reused after a reload, it plans the same match the same way every time.
+        let vision = field.fog.side(*side);
+        let mut entities: Vec<(Coord, Entity)> = unit_query
+            .iter()
+            .filter(|(_, pos, unit, stats, ..)| {
+                stats.hp > 0 && (*unit == side || vision.is_visible((pos.x, pos.y)))
+            })
+            .map(|(entity, pos, ..)| ((pos.x, pos.y), entity))
+            .collect();
+        entities.sort();
+        let views: Vec<UnitView> = entities
+            .iter()
+            .filter_map(|&(_, entity)| unit_query.get(entity).ok())
+            .map(|(_, pos, unit, stats, ..)| UnitView {
+                side: *unit,
+                position: (pos.x, pos.y),
+                stats: stats.clone(),
+            })
+            .collect();
+        let Some(actor) = entities.iter().position(|&(_, entity)| entity == enemy) else {
+            return;
+        };
 
-    for (_, mut pos, mut transform, unit_kind) in unit_query.iter_mut() {
-        // We only move enemy units.
-        if !matches!(unit_kind, Unit::Enemy) {
-            continue;
+        let plan = plan_turn(&map.0, &views, actor, tactics.strategy(), &mut ai_rng.rng);
+        *planned_target = plan.target.map(|index| entities[index].1);
+
+        // Everyone blocks the way, seen or not; a walk into someone hidden just stays put.
+        let blocked: HashSet<Coord> = unit_query
+            .iter()
+            .filter(|(entity, _, _, stats, ..)| *entity != enemy && stats.hp > 0)
+            .map(|(_, pos, ..)| (pos.x, pos.y))
+            .collect();
+        let Ok((_, pos, _, stats, ..)) = unit_query.get(enemy) else {
+            return;
+        };
+        let reachable = find_path(&map.0, (pos.x, pos.y), plan.destination, &blocked)
+            .is_some_and(|path| path.len() > 1 && path_cost(&map.0, &path) <= stats.movement);
+        if reachable {
+            actions.send(Action::Move { to: plan.destination });
+            return;
         }
+    }
 
-        dirs.shuffle(&mut rng);
-
-        // Try the four neighbouring tiles in random order.
-        for (dx, dy) in dirs {
-            let nx = pos.x as i32 + dx;
-            let ny = pos.y as i32 + dy;
-
-            // Stay inside the board.
-            if nx < 0 || ny < 0 || nx >= GRID_WIDTH as i32 || ny >= GRID_HEIGHT as i32 {
-                continue;
-            }
-
-            let nxu = nx as u32;
-            let nyu = ny as u32;
-
-            // Skip if another unit is already there.
-            if occupied.contains(&(nxu, nyu)) {
-                continue;
+    // Attack the unit picked when planning, if it is still there to hit.
+    if let Some(target) = planned_target.take() {
+        let attacker = unit_query.get(enemy).ok();
+        let defender = unit_query.get(target).ok();
+        if let (Some((_, from, _, attacker, ..)), Some((_, target_pos, ..))) = (attacker, defender) {
+            if in_range(attacker, from, target_pos) {
+                actions.send(Action::Attack { target: (target_pos.x, target_pos.y) });
             }
-
-            // === Move the unit ===
-            occupied.remove(&(pos.x, pos.y)); // old spot is now free
-            occupied.insert((nxu, nyu)); // new spot is taken
-
-            pos.x = nxu;
-            pos.y = nyu;
-
-            // Convert grid coords → world coords.
-            let offset_x = -(GRID_WIDTH as f32 * TILE_SIZE) / 2.0 + TILE_SIZE / 2.0;
-            let offset_y = -(GRID_HEIGHT as f32 * TILE_SIZE) / 2.0 + TILE_SIZE / 2.0;
-            transform.translation = Vec3::new(
-                nxu as f32 * TILE_SIZE + offset_x,
-                nyu as f32 * TILE_SIZE + offset_y,
-                1.0,
-            );
-            break; // move only once per unit
         }
     }
-
-    // AI finished its turn.
-    done.0 = true;
+    actions.send(Action::EndTurn);
 }
 
 fn highlight_reachable_tiles(
     selected: Res<SelectedUnit>,
-    unit_query: Query<(&TilePos, &Stats), With<Unit>>,
+    map: Res<Map>,
+    fog: Res<FogOfWar>,
+    unit_query: Query<(Entity, &Unit, &TilePos, &Stats, &TurnActions)>,
     mut tile_query: Query<(&TilePos, &mut Sprite), With<Tile>>,
 ) {
     // First, clear all highlights
-    for (_, mut sprite) in tile_query.iter_mut() {
-        sprite.color = Color::srgb(0.0, 0.0, 1.0);
+    for (tile_pos, mut sprite) in tile_query.iter_mut() {
+        sprite.color = tile_color(&map.0, &fog.player, (tile_pos.x, tile_pos.y));
     }
 
     // If no unit is selected, stop here
     let Some(selected_entity) = selected.0 else { return };
 
     // Get the selected unit's position and movement
-    let Ok((unit_pos, stats)) = unit_query.get(selected_entity) else { return };
+    let Ok((_, _, unit_pos, stats, actions)) = unit_query.get(selected_entity) else { return };
+    if actions.moved {
+        return;
+    }
 
-    // Highlight reachable tiles
+    // Highlight the tiles it can walk to this turn, as far as the player can tell
+    let blocked: HashSet<Coord> = unit_query
+        .iter()
+        .filter(|(entity, ..)| *entity != selected_entity)
+        .filter(|(_, unit, pos, ..)| **unit == Unit::Player || fog.player.is_visible((pos.x, pos.y)))
+        .map(|(_, _, pos, ..)| (pos.x, pos.y))
+        .collect();
+    let reach = reachable(&map.0, (unit_pos.x, unit_pos.y), stats.movement, &blocked);
     for (tile_pos, mut sprite) in tile_query.iter_mut() {
-        let dx = (tile_pos.x as i32 - unit_pos.x as i32).abs();
-        let dy = (tile_pos.y as i32 - unit_pos.y as i32).abs();
-        if dx + dy <= stats.movement as i32 {
-            sprite.color = Color::srgb(0.2, 0.4, 0.4); // Cyan-ish highlight
+        if reach.contains_key(&(tile_pos.x, tile_pos.y)) {
+            // Cyan-ish highlight that still shows the terrain underneath
+            sprite.color = sprite.color.mix(&Color::srgb(0.2, 0.8, 0.8), 0.5);
         }
     }
 }
 
-fn update_turn_text(turn: Res<Turn>, mut text_query: Query<&mut Text, With<TurnText>>) {
+fn update_turn_text(
+    turn: Res<Turn>,
+    state: Res<State<MatchState>>,
+    viewer: Option<Res<ReplayViewer>>,
+    mut text_query: Query<&mut Text, With<TurnText>>,
+) {
     if let Ok(mut text) = text_query.get_single_mut() {
-        text.0 = match *turn {
-            Turn::Player => "Player Turn".to_string(),
-            Turn::AI => "AI Turn".to_string(),
+        text.0 = match (state.get(), *turn) {
+            (MatchState::Replay, _) => {
+                let (next, total, fast) = viewer.map_or((0, 0, false), |viewer| {
+                    (viewer.next, viewer.log.actions.len(), viewer.fast_forward)
+                });
+                let speed = if fast { "F to slow down" } else { "F to fast-forward" };
+                format!("Replay {next}/{total}\n(Right to step, {speed},\nEscape to leave)")
+            }
+            (MatchState::MainMenu | MatchState::Results, _) => String::new(),
+            (MatchState::Deployment, _) => "Deploy your units\n(Enter to fight)".to_string(),
+            (MatchState::Victory, _) => "Victory!\n(Enter for results)".to_string(),
+            (MatchState::Defeat, _) => "Defeat\n(Enter for results)".to_string(),
+            (MatchState::Battle, Turn::Player) => "Player Turn\n(Space to end)".to_string(),
+            (MatchState::Battle, Turn::AI) => "AI Turn".to_string(),
         };
     }
 }
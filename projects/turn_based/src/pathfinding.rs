@@ -0,0 +1,285 @@
+// Grid pathfinding: which tiles a unit can reach and the path it walks to get there.
+// Plain Rust on tile coordinates so it can be tested without an app.
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::terrain::Terrain;
+
+/// A tile as `(x, y)`, with `(0, 0)` in the bottom-left corner.
+pub type Coord = (u32, u32);
+
+/// The map as far as movement is concerned: its size and the terrain of every tile.
+#[derive(Debug, Clone, Default)]
+pub struct Grid {
+    pub width: u32,
+    pub height: u32,
+    /// Row by row from `(0, 0)`.
+    terrain: Vec<Terrain>,
+}
+
+impl Grid {
+    /// A map of open plains.
+    pub fn new(width: u32, height: u32) -> Self {
+        Grid {
+            width,
+            height,
+            terrain: vec![Terrain::Plains; (width * height) as usize],
+        }
+    }
+
+    fn index(&self, (x, y): Coord) -> Option<usize> {
+        self.in_bounds((x, y))
+            .then(|| (y * self.width + x) as usize)
+    }
+
+    /// Terrain of `tile`; anything off the map counts as wall.
+    pub fn terrain(&self, tile: Coord) -> Terrain {
+        self.index(tile)
+            .map_or(Terrain::Wall, |index| self.terrain[index])
+    }
+
+    pub fn set_terrain(&mut self, tile: Coord, terrain: Terrain) {
+        if let Some(index) = self.index(tile) {
+            self.terrain[index] = terrain;
+        }
+    }
+
+    pub fn in_bounds(&self, (x, y): Coord) -> bool {
+        x < self.width && y < self.height
+    }
+
+    pub fn is_passable(&self, tile: Coord) -> bool {
+        self.terrain(tile).is_passable()
+    }
+
+    /// Movement points it takes to step onto `tile`, which must be passable.
+    pub fn cost(&self, tile: Coord) -> u32 {
+        self.terrain(tile).move_cost().unwrap_or(u32::MAX)
+    }
+
+    /// The four tiles next to `tile` that are on the map.
+    pub fn neighbours(&self, (x, y): Coord) -> impl Iterator<Item = Coord> + '_ {
+        let candidates = [
+            x.checked_add(1).map(|x| (x, y)),
+            x.checked_sub(1).map(|x| (x, y)),
+            y.checked_add(1).map(|y| (x, y)),
+            y.checked_sub(1).map(|y| (x, y)),
+        ];
+        candidates
+            .into_iter()
+            .flatten()
+            .filter(|&tile| self.in_bounds(tile))
+    }
+
+    /// Whether a unit may step onto `tile`, given the tiles other units stand on.
+    fn can_enter(&self, tile: Coord, blocked: &HashSet<Coord>) -> bool {
+        self.is_passable(tile) && !blocked.contains(&tile)
+    }
+}
+
+/// Every tile reachable from `start` for at most `budget` movement points, with the cheapest
+/// cost to get there. `blocked` holds tiles other units stand on, which can be neither entered
+/// nor passed through. `start` itself is always included at cost 0.
+pub fn reachable(
+    grid: &Grid,
+    start: Coord,
+    budget: u32,
+    blocked: &HashSet<Coord>,
+) -> HashMap<Coord, u32> {
+    let mut costs = HashMap::from([(start, 0)]);
+    let mut frontier = BinaryHeap::from([Reverse((0, start))]);
+    while let Some(Reverse((cost, tile))) = frontier.pop() {
+        if cost > costs[&tile] {
+            continue;
+        }
+        for next in grid.neighbours(tile) {
+            if !grid.can_enter(next, blocked) {
+                continue;
+            }
+            let next_cost = cost + grid.cost(next);
+            if next_cost <= budget && costs.get(&next).is_none_or(|&old| next_cost < old) {
+                costs.insert(next, next_cost);
+                frontier.push(Reverse((next_cost, next)));
+            }
+        }
+    }
+    costs
+}
+
+/// Cheapest path from `start` to `goal` by A*, both ends included, or `None` when `goal` cannot
+/// be reached. `blocked` works as in [`reachable`].
+pub fn find_path(
+    grid: &Grid,
+    start: Coord,
+    goal: Coord,
+    blocked: &HashSet<Coord>,
+) -> Option<Vec<Coord>> {
+    if start == goal {
+        return Some(vec![start]);
+    }
+    if !grid.can_enter(goal, blocked) {
+        return None;
+    }
+
+    // No terrain costs less than 1 per step, so Manhattan distance never overestimates.
+    let heuristic = |tile: Coord| manhattan(tile, goal);
+    let mut costs = HashMap::from([(start, 0)]);
+    let mut came_from: HashMap<Coord, Coord> = HashMap::new();
+    let mut frontier = BinaryHeap::from([Reverse((heuristic(start), 0, start))]);
+    while let Some(Reverse((_, cost, tile))) = frontier.pop() {
+        if tile == goal {
+            let mut path = vec![goal];
+            while let Some(&previous) = came_from.get(path.last().unwrap()) {
+                path.push(previous);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if cost > costs[&tile] {
+            continue;
+        }
+        for next in grid.neighbours(tile) {
+            if !grid.can_enter(next, blocked) {
+                continue;
+            }
+            let next_cost = cost + grid.cost(next);
+            if costs.get(&next).is_none_or(|&old| next_cost < old) {
+                costs.insert(next, next_cost);
+                came_from.insert(next, tile);
+                frontier.push(Reverse((next_cost + heuristic(next), next_cost, next)));
+            }
+        }
+    }
+    None
+}
+
+/// Movement points a path costs, not counting the tile it starts on.
+pub fn path_cost(grid: &Grid, path: &[Coord]) -> u32 {
+    path.iter().skip(1).map(|&tile| grid.cost(tile)).sum()
+}
+
+/// Steps between two tiles on a grid with no diagonal moves, whatever lies in between.
+pub fn manhattan((ax, ay): Coord, (bx, by): Coord) -> u32 {
+    ax.abs_diff(bx) + ay.abs_diff(by)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The size of the skirmish map.
+    const GRID_WIDTH: u32 = 10;
+    const GRID_HEIGHT: u32 = 10;
+
+    fn grid() -> Grid {
+        Grid::new(GRID_WIDTH, GRID_HEIGHT)
+    }
+
+    fn is_connected(path: &[Coord]) -> bool {
+        path.windows(2).all(|pair| manhattan(pair[0], pair[1]) == 1)
+    }
+
+    #[test]
+    fn open_grid_reaches_a_diamond() {
+        let tiles = reachable(&grid(), (5, 5), 3, &HashSet::new());
+        // 1 + 4 + 8 + 12 tiles within three steps.
+        assert_eq!(tiles.len(), 25);
+        assert_eq!(tiles[&(5, 5)], 0);
+        assert_eq!(tiles[&(8, 5)], 3);
+        assert_eq!(tiles[&(6, 7)], 3);
+        assert!(!tiles.contains_key(&(7, 7)));
+    }
+
+    #[test]
+    fn reachable_stays_on_the_map() {
+        let tiles = reachable(&grid(), (0, 0), 2, &HashSet::new());
+        assert_eq!(tiles.len(), 6);
+        let tiles = reachable(&grid(), (0, 0), 100, &HashSet::new());
+        assert_eq!(tiles.len(), (GRID_WIDTH * GRID_HEIGHT) as usize);
+        assert!(tiles.keys().all(|&tile| grid().in_bounds(tile)));
+    }
+
+    #[test]
+    fn walls_and_units_block_movement() {
+        let mut grid = grid();
+        grid.set_terrain((1, 0), Terrain::Wall);
+        let units = HashSet::from([(0, 1)]);
+        // Boxed into the corner.
+        assert_eq!(reachable(&grid, (0, 0), 5, &units).len(), 1);
+        assert_eq!(find_path(&grid, (0, 0), (5, 5), &units), None);
+
+        grid.set_terrain((1, 0), Terrain::Plains);
+        let tiles = reachable(&grid, (0, 0), 2, &units);
+        assert!(!tiles.contains_key(&(0, 1)));
+        assert_eq!(tiles[&(1, 1)], 2);
+        assert!(!tiles.contains_key(&(0, 2)));
+    }
+
+    #[test]
+    fn path_walks_around_a_wall() {
+        let mut grid = grid();
+        // A wall across x = 4 with a single gap at the top.
+        for y in 0..GRID_HEIGHT - 1 {
+            grid.set_terrain((4, y), Terrain::Wall);
+        }
+        let path = find_path(&grid, (2, 0), (6, 0), &HashSet::new()).unwrap();
+        assert_eq!(path.first(), Some(&(2, 0)));
+        assert_eq!(path.last(), Some(&(6, 0)));
+        assert!(path.contains(&(4, GRID_HEIGHT - 1)));
+        assert!(is_connected(&path));
+        assert!(path.iter().all(|&tile| grid.is_passable(tile)));
+        assert_eq!(path_cost(&grid, &path), 4 + 2 * (GRID_HEIGHT - 1));
+    }
+
+    #[test]
+    fn path_is_as_short_as_the_flood_fill_says() {
+        let mut grid = grid();
+        for tile in [(3, 3), (3, 4), (3, 5), (4, 5), (5, 5)] {
+            grid.set_terrain(tile, Terrain::Wall);
+        }
+        let units = HashSet::from([(2, 4), (6, 6)]);
+        let costs = reachable(&grid, (4, 4), 100, &units);
+        for (&goal, &cost) in &costs {
+            let path = find_path(&grid, (4, 4), goal, &units).unwrap();
+            assert!(is_connected(&path));
+            assert_eq!(path_cost(&grid, &path), cost, "path to {goal:?}");
+        }
+    }
+
+    #[test]
+    fn no_path_onto_walls_units_or_off_the_map() {
+        let mut grid = grid();
+        grid.set_terrain((3, 3), Terrain::Water);
+        let units = HashSet::from([(4, 4)]);
+        assert_eq!(find_path(&grid, (0, 0), (3, 3), &units), None);
+        assert_eq!(find_path(&grid, (0, 0), (4, 4), &units), None);
+        assert_eq!(find_path(&grid, (0, 0), (GRID_WIDTH, 0), &units), None);
+        assert_eq!(find_path(&grid, (1, 1), (1, 1), &units), Some(vec![(1, 1)]));
+    }
+
+    #[test]
+    fn terrain_costs_shape_reach_and_paths() {
+        let mut grid = grid();
+        grid.set_terrain((1, 0), Terrain::Forest);
+        grid.set_terrain((0, 1), Terrain::Mountain);
+        let tiles = reachable(&grid, (0, 0), 3, &HashSet::new());
+        assert_eq!(tiles[&(1, 0)], 2);
+        assert_eq!(tiles[&(0, 1)], 3);
+        assert_eq!(tiles[&(2, 0)], 3);
+        assert_eq!(tiles[&(1, 1)], 3);
+        assert!(!tiles.contains_key(&(2, 1)));
+
+        // Crossing a strip of forest costs one extra point, and water forces a detour.
+        let mut grid = Grid::new(GRID_WIDTH, GRID_HEIGHT);
+        for y in 0..GRID_HEIGHT - 1 {
+            grid.set_terrain((5, y), Terrain::Forest);
+        }
+        let path = find_path(&grid, (4, 0), (6, 0), &HashSet::new()).unwrap();
+        assert_eq!(path, vec![(4, 0), (5, 0), (6, 0)]);
+        assert_eq!(path_cost(&grid, &path), 3);
+        grid.set_terrain((5, 0), Terrain::Water);
+        let path = find_path(&grid, (4, 0), (6, 0), &HashSet::new()).unwrap();
+        assert_eq!(path_cost(&grid, &path), 5);
+        assert!(path.contains(&(5, 1)));
+    }
+}
@@ -0,0 +1,316 @@
+// Replays: a battle's starting position, seed included, and every action taken from there.
+// Carrying the actions out again in order plays the battle out exactly as it went.
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::action::Action;
+use crate::flow::MatchState;
+use crate::logic::CurrentTurn;
+use crate::save::{SaveError, SaveGame};
+use crate::{ScenarioEntities, SelectedUnit};
+
+/// Where the last battle fought is written, and what R watches again.
+pub const LAST_REPLAY_PATH: &str = "saves/last.replay.ron";
+
+/// A battle as it was played.
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayLog {
+    /// The position when the first action was taken, with the seed the AI planned with.
+    pub start: SaveGame,
+    pub actions: Vec<Action>,
+}
+
+impl ReplayLog {
+    pub fn new(start: SaveGame) -> Self {
+        ReplayLog {
+            start,
+            actions: Vec::new(),
+        }
+    }
+
+    pub fn to_ron(&self) -> Result<String, SaveError> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    pub fn parse(text: &str) -> Result<ReplayLog, SaveError> {
+        let log: ReplayLog = ron::from_str(text)?;
+        log.start.validate()?;
+        Ok(log)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<ReplayLog, SaveError> {
+        ReplayLog::parse(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// Fingerprint of the match going on in `world`: equal for equal saves of it.
+pub fn state_hash(world: &mut World) -> Option<u64> {
+    let text = SaveGame::capture(world)?.to_ron().ok()?;
+    // FNV-1a, which unlike the standard library's hasher is the same on every build.
+    Some(text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    }))
+}
+
+/// Start a new log from the battle as it stands when it begins.
+pub fn start_recording(world: &mut World) {
+    if let Some(start) = SaveGame::capture(world) {
+        world.insert_resource(ReplayLog::new(start));
+    }
+}
+
+/// Keep the battle just decided, to watch it again. The hash it ended on is logged, to tell
+/// whether a replay of it ends the same way.
+pub fn write_replay(world: &mut World) {
+    let hash = state_hash(world).unwrap_or_default();
+    let Some(log) = world.get_resource::<ReplayLog>() else {
+        return;
+    };
+    match log.write(LAST_REPLAY_PATH) {
+        Ok(()) => info!("Replay written to {LAST_REPLAY_PATH}, ending on state {hash:016x}"),
+        Err(err) => warn!("{err}"),
+    }
+}
+
+/// The replay being watched, and how far it has got.
+#[derive(Resource)]
+pub struct ReplayViewer {
+    pub log: ReplayLog,
+    /// Index of the next action to take.
+    pub next: usize,
+    /// Take an action every frame rather than one per key press.
+    pub fast_forward: bool,
+    /// An action was refused: the battle on the board is no longer the one recorded, and the
+    /// replay stops there.
+    pub diverged: bool,
+}
+
+/// R sets the board up as the last battle began, to watch it again.
+pub fn watch_replay(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut commands: Commands,
+    old: ScenarioEntities,
+    mut selected: ResMut<SelectedUnit>,
+    mut next: ResMut<NextState<MatchState>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyR) {
+        return;
+    }
+    let log = match ReplayLog::read(LAST_REPLAY_PATH) {
+        Ok(log) => log,
+        Err(err) => {
+            warn!("{err}");
+            return;
+        }
+    };
+    if let Err(err) = log.start.restore(&mut commands) {
+        warn!("{err}");
+        return;
+    }
+    for entity in old.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    selected.0 = None;
+    // What the replay does is recorded afresh, to tell whether each action still goes through.
+    commands.insert_resource(ReplayLog::new(log.start.clone()));
+    commands.insert_resource(ReplayViewer {
+        log,
+        next: 0,
+        fast_forward: false,
+        diverged: false,
+    });
+    next.set(MatchState::Replay);
+}
+
+/// The right arrow takes the next action, F fast-forwards, and Escape stops watching.
+pub fn play_replay(
+    keys: Res<ButtonInput<KeyCode>>,
+    current: Res<CurrentTurn>,
+    mut viewer: ResMut<ReplayViewer>,
+    taken: Res<ReplayLog>,
+    mut actions: EventWriter<Action>,
+    mut next: ResMut<NextState<MatchState>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        next.set(MatchState::MainMenu);
+        return;
+    }
+    if keys.just_pressed(KeyCode::KeyF) {
+        viewer.fast_forward = !viewer.fast_forward;
+    }
+    if viewer.diverged {
+        return;
+    }
+    // Every action sent so far should have been carried out and logged again.
+    if taken.actions.len() < viewer.next {
+        warn!(
+            "Replay action {} ({:?}) was refused; stopping here",
+            viewer.next,
+            viewer.log.actions[viewer.next - 1]
+        );
+        viewer.diverged = true;
+        return;
+    }
+    // Actions were only ever taken on someone's turn.
+    if current.0.is_none() {
+        return;
+    }
+    if !viewer.fast_forward && !keys.just_pressed(KeyCode::ArrowRight) {
+        return;
+    }
+    if let Some(&action) = viewer.log.actions.get(viewer.next) {
+        actions.send(action);
+        viewer.next += 1;
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use crate::action::perform_actions;
+    use crate::combat::despawn_dead;
+    use crate::fog::update_fog;
+    use crate::logic::{begin_turn, end_turn, EndTurnEvent};
+    use crate::scenario::Scenario;
+    use crate::unit::{Stats, Walking};
+    use crate::{ai_turn_system, Unit};
+
+    const SKIRMISH: &str = include_str!("../assets/scenarios/skirmish.scenario.ron");
+
+    /// A world holding just the state a save covers, ready to take turns in.
+    pub(crate) fn load(save: &SaveGame) -> World {
+        let mut world = World::new();
+        save.restore(&mut world.commands()).unwrap();
+        world.flush();
+        world.insert_resource(SelectedUnit(None));
+        world.init_resource::<Events<Action>>();
+        world.init_resource::<Events<EndTurnEvent>>();
+        world
+    }
+
+    /// One frame of the turn loop, with `decide` ordering the acting unit's actions.
+    pub(crate) fn turn_loop<M>(decide: impl IntoSystemConfigs<M>) -> Schedule {
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (
+                begin_turn,
+                decide,
+                perform_actions,
+                despawn_dead,
+                update_fog,
+                end_turn,
+            )
+                .chain(),
+        );
+        schedule
+    }
+
+    pub(crate) fn next_frame(world: &mut World) {
+        world.resource_mut::<Events<Action>>().update();
+        world.resource_mut::<Events<EndTurnEvent>>().update();
+        // Nothing draws here, so walks are over at once.
+        let walkers: Vec<Entity> = world
+            .query_filtered::<Entity, With<Walking>>()
+            .iter(world)
+            .collect();
+        for walker in walkers {
+            world.entity_mut(walker).remove::<Walking>();
+        }
+    }
+
+    /// Both sides played by the AI until at least `actions` actions are logged.
+    fn record(actions: usize) -> (ReplayLog, u64) {
+        let scenario = Scenario::parse(SKIRMISH).unwrap();
+        let start = SaveGame::from_scenario("scenarios/skirmish.scenario.ron", &scenario, 42);
+        let mut world = load(&start);
+        world.insert_resource(ReplayLog::new(start));
+        let mut schedule = turn_loop(ai_turn_system);
+        while world.resource::<ReplayLog>().actions.len() < actions {
+            schedule.run(&mut world);
+            next_frame(&mut world);
+        }
+        let hash = state_hash(&mut world).unwrap();
+        (world.remove_resource::<ReplayLog>().unwrap(), hash)
+    }
+
+    /// Take the logged actions one a frame, as the replay viewer does.
+    fn replay(log: &ReplayLog) -> World {
+        let mut world = load(&log.start);
+        world.insert_resource(ReplayViewer {
+            log: log.clone(),
+            next: 0,
+            fast_forward: true,
+            diverged: false,
+        });
+        world.insert_resource(ReplayLog::new(log.start.clone()));
+        world.init_resource::<ButtonInput<KeyCode>>();
+        world.init_resource::<NextState<MatchState>>();
+        let mut schedule = turn_loop(play_replay);
+        loop {
+            let viewer = world.resource::<ReplayViewer>();
+            if viewer.diverged || viewer.next == log.actions.len() {
+                break;
+            }
+            schedule.run(&mut world);
+            next_frame(&mut world);
+        }
+        world
+    }
+
+    #[test]
+    fn replays_reach_the_recorded_state() {
+        let (log, recorded) = record(60);
+        let log = ReplayLog::parse(&log.to_ron().unwrap()).unwrap();
+        assert!(log
+            .actions
+            .iter()
+            .any(|action| matches!(action, Action::Attack { .. })));
+
+        let mut world = replay(&log);
+        assert_eq!(state_hash(&mut world), Some(recorded));
+        assert_eq!(recorded, 0x241c_69bd_11f2_69a2);
+
+        // Somebody got hurt on the way.
+        let hurt = world
+            .query::<(&Unit, &Stats)>()
+            .iter(&world)
+            .any(|(_, stats)| stats.hp < stats.max_hp);
+        assert!(hurt);
+
+        // Leaving out an action ends somewhere else.
+        let mut edited = log.clone();
+        let attack = edited
+            .actions
+            .iter()
+            .position(|action| matches!(action, Action::Attack { .. }))
+            .unwrap();
+        edited.actions.remove(attack);
+        assert_ne!(state_hash(&mut replay(&edited)), Some(recorded));
+    }
+
+    #[test]
+    fn replays_stop_at_a_refused_action() {
+        let (mut log, _) = record(10);
+        log.actions.insert(3, Action::Move { to: (99, 99) });
+        let world = replay(&log);
+        let viewer = world.resource::<ReplayViewer>();
+        assert!(viewer.diverged);
+        assert_eq!(viewer.next, 4);
+        assert_eq!(world.resource::<ReplayLog>().actions, log.actions[..3]);
+    }
+}
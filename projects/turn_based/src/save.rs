@@ -0,0 +1,574 @@
+// Saved matches: everything needed to pick a match up where it was left, written as RON.
+use std::cmp::Reverse;
+use std::fmt;
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::ability::{Abilities, AbilitySlot};
+use crate::ai::AiRng;
+use crate::combat::TurnActions;
+use crate::flow::MatchStats;
+use crate::fog::{visible_tiles, FogOfWar};
+use crate::logic::{CurrentTurn, Round, TurnQueue};
+use crate::pathfinding::Coord;
+use crate::replay::ReplayLog;
+use crate::scenario::{
+    check_terrain, terrain_grid, terrain_rows, ActiveScenario, Allegiance, Faction, MatchResult,
+    Scenario, Vip,
+};
+use crate::status::Statuses;
+use crate::unit::{spawn_unit, Stats};
+use crate::{
+    tile_color, tile_to_world, Map, ScenarioEntities, SelectedUnit, Tile, TilePos, Unit, TILE_SIZE,
+};
+
+/// Where F5 saves the match and F9 loads it from.
+pub const QUICK_SAVE_PATH: &str = "saves/quicksave.ron";
+
+/// The scenario being played and the factions in it.
+#[derive(Resource, Debug, Clone)]
+pub struct Roster {
+    /// Asset path of the scenario, whose victory conditions decide the match.
+    pub scenario: String,
+    pub factions: Vec<Faction>,
+}
+
+/// A match as it stands between two actions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub scenario: String,
+    pub width: u32,
+    pub height: u32,
+    /// One string per row, top row first, as in scenario files.
+    pub terrain: Vec<String>,
+    pub factions: Vec<Faction>,
+    /// Living units, ordered by position.
+    pub units: Vec<SavedUnit>,
+    /// Turn order as indices into `units`, the acting unit first.
+    pub queue: Vec<usize>,
+    /// Index into `units` of the unit whose turn it is.
+    pub current: Option<usize>,
+    pub round: Round,
+    /// Units yet to act this round, as indices into `units`.
+    #[serde(default)]
+    pub waiting: Vec<usize>,
+    /// Tiles each side has explored, sorted.
+    pub explored_by_player: Vec<Coord>,
+    pub explored_by_enemy: Vec<Coord>,
+    pub seed: u64,
+    /// How far the AI has got through the randomness of `seed`.
+    #[serde(default)]
+    pub draws: u64,
+    #[serde(default)]
+    pub stats: MatchStats,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedUnit {
+    pub faction: String,
+    pub position: Coord,
+    pub stats: Stats,
+    pub actions: TurnActions,
+    pub statuses: Statuses,
+    pub abilities: Abilities,
+    #[serde(default)]
+    pub vip: bool,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Write(ron::Error),
+    Read(ron::error::SpannedError),
+    /// Parsed fine but does not describe a match.
+    Invalid(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "could not access save: {err}"),
+            SaveError::Write(err) => write!(f, "could not write save: {err}"),
+            SaveError::Read(err) => write!(f, "bad save file: {err}"),
+            SaveError::Invalid(reason) => write!(f, "invalid save: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<std::io::Error> for SaveError {
+    fn from(err: std::io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl From<ron::Error> for SaveError {
+    fn from(err: ron::Error) -> Self {
+        SaveError::Write(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for SaveError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        SaveError::Read(err)
+    }
+}
+
+impl SaveGame {
+    /// The opening position of `scenario`, loaded from `path`.
+    pub fn from_scenario(path: &str, scenario: &Scenario, seed: u64) -> SaveGame {
+        let units: Vec<SavedUnit> = scenario
+            .units
+            .iter()
+            .map(|spawn| SavedUnit {
+                faction: spawn.faction.clone(),
+                position: spawn.position,
+                stats: spawn.stats.clone(),
+                actions: TurnActions::default(),
+                statuses: Statuses::default(),
+                abilities: Abilities(
+                    spawn
+                        .abilities
+                        .iter()
+                        .filter_map(|name| scenario.ability(name))
+                        .map(|ability| AbilitySlot {
+                            ability: ability.clone(),
+                            cooldown: 0,
+                        })
+                        .collect(),
+                ),
+                vip: spawn.vip,
+            })
+            .collect();
+        // Fastest first; ties keep the order the scenario lists them in.
+        let mut queue: Vec<usize> = (0..units.len()).collect();
+        queue.sort_by_key(|&index| Reverse(units[index].stats.speed));
+        SaveGame {
+            scenario: path.to_string(),
+            width: scenario.width,
+            height: scenario.height,
+            terrain: scenario.terrain.clone(),
+            factions: scenario.factions.clone(),
+            units,
+            queue,
+            current: None,
+            round: Round::default(),
+            waiting: Vec::new(),
+            explored_by_player: Vec::new(),
+            explored_by_enemy: Vec::new(),
+            seed,
+            draws: 0,
+            stats: MatchStats::default(),
+        }
+    }
+
+    /// The match going on in `world`, or `None` before one has started.
+    pub fn capture(world: &mut World) -> Option<SaveGame> {
+        let roster = world.get_resource::<Roster>()?.clone();
+        let mut query = world.query::<(
+            Entity,
+            &Allegiance,
+            &TilePos,
+            &Stats,
+            &TurnActions,
+            &Statuses,
+            &Abilities,
+            Has<Vip>,
+        )>();
+        let mut units: Vec<(Entity, SavedUnit)> = query
+            .iter(world)
+            .filter(|(_, _, _, stats, ..)| stats.hp > 0)
+            .map(
+                |(entity, allegiance, pos, stats, actions, statuses, abilities, vip)| {
+                    let unit = SavedUnit {
+                        faction: allegiance.0.clone(),
+                        position: (pos.x, pos.y),
+                        stats: stats.clone(),
+                        actions: actions.clone(),
+                        statuses: statuses.clone(),
+                        abilities: abilities.clone(),
+                        vip,
+                    };
+                    (entity, unit)
+                },
+            )
+            .collect();
+        // No two units share a tile, so this order survives a reload; entity ids do not.
+        units.sort_by_key(|(_, unit)| unit.position);
+        let index_of = |entity: Entity| units.iter().position(|&(unit, _)| unit == entity);
+
+        let queue = world
+            .resource::<TurnQueue>()
+            .0
+            .iter()
+            .filter_map(|&entity| index_of(entity))
+            .collect();
+        let current = world.resource::<CurrentTurn>().0.and_then(index_of);
+        let waiting = world
+            .resource::<Round>()
+            .waiting
+            .iter()
+            .filter_map(|&entity| index_of(entity))
+            .collect();
+        let grid = &world.resource::<Map>().0;
+        let fog = world.resource::<FogOfWar>();
+        let sorted = |tiles: &std::collections::HashSet<Coord>| {
+            let mut tiles: Vec<Coord> = tiles.iter().copied().collect();
+            tiles.sort();
+            tiles
+        };
+        Some(SaveGame {
+            scenario: roster.scenario,
+            width: grid.width,
+            height: grid.height,
+            terrain: terrain_rows(grid),
+            factions: roster.factions,
+            units: units.into_iter().map(|(_, unit)| unit).collect(),
+            queue,
+            current,
+            round: world.resource::<Round>().clone(),
+            waiting,
+            explored_by_player: sorted(&fog.player.explored),
+            explored_by_enemy: sorted(&fog.enemy.explored),
+            seed: world.resource::<AiRng>().seed,
+            draws: world.resource::<AiRng>().draws(),
+            stats: world.resource::<MatchStats>().clone(),
+        })
+    }
+
+    /// Spawn the map and units and set the turn order, fog and randomness to match the save.
+    /// Whatever was there before has to be despawned first. Nothing is spawned for a save
+    /// that does not hold together.
+    pub fn restore(&self, commands: &mut Commands) -> Result<(), SaveError> {
+        self.validate()?;
+        let grid = terrain_grid(self.width, self.height, &self.terrain);
+        let mut fog = FogOfWar::default();
+        fog.player.explored.extend(&self.explored_by_player);
+        fog.enemy.explored.extend(&self.explored_by_enemy);
+        // Only what each side has explored is saved; what it sees follows from where its units
+        // stand, and the AI plans around it before the fog is next looked at again.
+        for unit in &self.units {
+            let Some(faction) = self.factions.iter().find(|f| f.name == unit.faction) else {
+                continue;
+            };
+            let vision = match faction.side {
+                Unit::Player => &mut fog.player,
+                Unit::Enemy => &mut fog.enemy,
+            };
+            vision
+                .visible
+                .extend(visible_tiles(&grid, unit.position, unit.stats.sight));
+        }
+
+        // Spawn grid tiles
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                commands.spawn((
+                    Sprite {
+                        color: tile_color(&grid, &fog.player, (x, y)),
+                        custom_size: Some(Vec2::splat(TILE_SIZE - 2.0)), // 2px gap between tiles
+                        ..default()
+                    },
+                    Transform::from_translation(tile_to_world(&grid, (x, y), 0.0)),
+                    Tile,
+                    TilePos { x, y },
+                ));
+            }
+        }
+
+        let mut entities = Vec::new();
+        for unit in &self.units {
+            let faction = self
+                .factions
+                .iter()
+                .find(|f| f.name == unit.faction)
+                .ok_or_else(|| {
+                    SaveError::Invalid(format!("no faction called {:?}", unit.faction))
+                })?;
+            let (r, g, b) = faction.color;
+            let (x, y) = unit.position;
+            let entity = spawn_unit(
+                commands,
+                &grid,
+                x,
+                y,
+                faction.side,
+                Color::srgb(r, g, b),
+                unit.stats.clone(),
+            );
+            commands.entity(entity).insert((
+                faction.tactics,
+                Allegiance(faction.name.clone()),
+                unit.actions.clone(),
+                unit.statuses.clone(),
+                unit.abilities.clone(),
+            ));
+            if unit.vip {
+                commands.entity(entity).insert(Vip);
+            }
+            entities.push(entity);
+        }
+
+        commands.insert_resource(TurnQueue(
+            self.queue.iter().map(|&index| entities[index]).collect(),
+        ));
+        commands.insert_resource(CurrentTurn(self.current.map(|index| entities[index])));
+        commands.insert_resource(Round {
+            waiting: self.waiting.iter().map(|&index| entities[index]).collect(),
+            ..self.round.clone()
+        });
+        commands.insert_resource(MatchResult(None));
+        commands.insert_resource(self.stats.clone());
+        commands.insert_resource(fog);
+        commands.insert_resource(Map(grid));
+        // The AI sees units in position order, so picking up where it left off in its
+        // randomness plays on the same way.
+        commands.insert_resource(AiRng::resume(self.seed, self.draws));
+        commands.insert_resource(Roster {
+            scenario: self.scenario.clone(),
+            factions: self.factions.clone(),
+        });
+        Ok(())
+    }
+
+    pub fn to_ron(&self) -> Result<String, SaveError> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    pub fn parse(text: &str) -> Result<SaveGame, SaveError> {
+        let save: SaveGame = ron::from_str(text)?;
+        save.validate()?;
+        Ok(save)
+    }
+
+    pub fn validate(&self) -> Result<(), SaveError> {
+        let invalid = |reason: String| Err(SaveError::Invalid(reason));
+        check_terrain(self.width, self.height, &self.terrain).map_err(SaveError::Invalid)?;
+        if let Some(unit) = self
+            .units
+            .iter()
+            .find(|unit| !self.factions.iter().any(|f| f.name == unit.faction))
+        {
+            return invalid(format!("no faction called {:?}", unit.faction));
+        }
+        let count = self.units.len();
+        if let Some(index) = self
+            .queue
+            .iter()
+            .chain(&self.current)
+            .chain(&self.waiting)
+            .find(|&&i| i >= count)
+        {
+            return invalid(format!("unit {index} of {count} in the turn order"));
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<SaveGame, SaveError> {
+        SaveGame::parse(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// F5 saves the match to [`QUICK_SAVE_PATH`].
+pub fn quick_save(world: &mut World) {
+    if !world
+        .resource::<ButtonInput<KeyCode>>()
+        .just_pressed(KeyCode::F5)
+    {
+        return;
+    }
+    let Some(save) = SaveGame::capture(world) else {
+        return;
+    };
+    match save.write(QUICK_SAVE_PATH) {
+        Ok(()) => info!("Saved to {QUICK_SAVE_PATH}"),
+        Err(err) => warn!("{err}"),
+    }
+}
+
+/// F9 replaces the battle with the one in [`QUICK_SAVE_PATH`], if it was saved from the same
+/// scenario, and carries on from there. Only runs during a battle; the replay of it starts
+/// over from the loaded position, as the battle is not entered again.
+pub fn quick_load(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut commands: Commands,
+    active: Res<ActiveScenario>,
+    old: ScenarioEntities,
+    mut selected: ResMut<SelectedUnit>,
+) {
+    if !keys.just_pressed(KeyCode::F9) {
+        return;
+    }
+    let save = match SaveGame::read(QUICK_SAVE_PATH) {
+        Ok(save) => save,
+        Err(err) => {
+            warn!("{err}");
+            return;
+        }
+    };
+    let playing = active.0.path().map(|path| path.to_string());
+    if playing.as_deref() != Some(save.scenario.as_str()) {
+        warn!("{QUICK_SAVE_PATH} is a save of {}", save.scenario);
+        return;
+    }
+
+    if let Err(err) = save.restore(&mut commands) {
+        warn!("{err}");
+        return;
+    }
+    for entity in old.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.insert_resource(ReplayLog::new(save));
+    selected.0 = None;
+    info!("Loaded {QUICK_SAVE_PATH}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    use crate::action::Action;
+    use crate::ai::Tactics;
+    use crate::ai_turn_system;
+    use crate::logic::{end_turn, EndTurnEvent};
+    use crate::replay::tests::{load as load_to_play, next_frame, turn_loop};
+
+    const SKIRMISH: &str = include_str!("../assets/scenarios/skirmish.scenario.ron");
+
+    /// A world holding just the state a save covers.
+    fn load(save: &SaveGame) -> World {
+        let mut world = World::new();
+        save.restore(&mut world.commands()).unwrap();
+        world.flush();
+        world
+    }
+
+    /// Both sides played by the AI until at least `actions` more actions are taken and the
+    /// turn under way is over; the actions taken.
+    fn play(world: &mut World, schedule: &mut Schedule, actions: usize) -> Vec<Action> {
+        let start = SaveGame::capture(world).unwrap();
+        world.insert_resource(ReplayLog::new(start));
+        while world.resource::<ReplayLog>().actions.len() < actions
+            || world.resource::<CurrentTurn>().0.is_some()
+        {
+            schedule.run(world);
+            next_frame(world);
+        }
+        world.remove_resource::<ReplayLog>().unwrap().actions
+    }
+
+    #[test]
+    fn save_load_save_round_trips() {
+        let scenario = Scenario::parse(SKIRMISH).unwrap();
+        let start = SaveGame::from_scenario("scenarios/skirmish.scenario.ron", &scenario, 7);
+        let mut world = load(&start);
+
+        // Play a little: the first unit moves, is hurt and ends its turn, the next one moves.
+        world.init_resource::<Events<EndTurnEvent>>();
+        let first = world.resource::<TurnQueue>().0[0];
+        world.resource_mut::<CurrentTurn>().0 = Some(first);
+        world.get_mut::<TilePos>(first).unwrap().x += 1;
+        world.get_mut::<Stats>(first).unwrap().hp -= 3;
+        world.send_event(EndTurnEvent);
+        world.run_system_once(end_turn).unwrap();
+        let second = world.resource::<TurnQueue>().0[0];
+        world.resource_mut::<CurrentTurn>().0 = Some(second);
+        world.get_mut::<TurnActions>(second).unwrap().moved = true;
+        world
+            .resource_mut::<FogOfWar>()
+            .player
+            .explored
+            .insert((3, 4));
+
+        let saved = SaveGame::capture(&mut world).unwrap();
+        let text = saved.to_ron().unwrap();
+        let mut reloaded = load(&SaveGame::parse(&text).unwrap());
+        let again = SaveGame::capture(&mut reloaded).unwrap().to_ron().unwrap();
+        assert_eq!(text, again);
+
+        assert_eq!(saved.round.completed, 0);
+        assert_eq!(saved.units[saved.queue[0]].stats.speed, 4);
+        assert_eq!(saved.current, Some(saved.queue[0]));
+        assert!(saved.units[saved.queue[0]].actions.moved);
+        assert_eq!(saved.units[saved.queue[3]].position, (2, 1));
+        assert_eq!(saved.units[saved.queue[3]].stats.hp, 7);
+        assert_eq!(saved.explored_by_player, vec![(3, 4)]);
+        assert_eq!(saved.terrain, scenario.terrain);
+    }
+
+    #[test]
+    fn reloaded_matches_play_on_the_same_way() {
+        // Raiders that pick at random and go first, within reach of both wardens. The wardens
+        // are listed in another order than their positions', the order a reload spawns them in.
+        let mut scenario = Scenario::parse(SKIRMISH).unwrap();
+        for faction in &mut scenario.factions {
+            if faction.side == Unit::Enemy {
+                faction.tactics = Tactics::Random;
+            }
+        }
+        let placed = [((2, 2), 5), ((1, 1), 3), ((2, 3), 9), ((3, 2), 8)];
+        for (unit, (position, speed)) in scenario.units.iter_mut().zip(placed) {
+            unit.position = position;
+            unit.stats.speed = speed;
+        }
+        let start = SaveGame::from_scenario("scenarios/skirmish.scenario.ron", &scenario, 2);
+        let mut world = load_to_play(&start);
+        let mut schedule = turn_loop(ai_turn_system);
+        play(&mut world, &mut schedule, 1);
+
+        let saved = SaveGame::capture(&mut world).unwrap();
+        assert!(saved.draws > 0);
+        let mut reloaded = load_to_play(&SaveGame::parse(&saved.to_ron().unwrap()).unwrap());
+        let played = play(&mut world, &mut schedule, 30);
+        assert!(played
+            .iter()
+            .any(|action| matches!(action, Action::Attack { .. })));
+        assert_eq!(
+            played,
+            play(&mut reloaded, &mut turn_loop(ai_turn_system), 30)
+        );
+    }
+
+    #[test]
+    fn broken_saves_are_rejected() {
+        let scenario = Scenario::parse(SKIRMISH).unwrap();
+        let mut save = SaveGame::from_scenario("scenarios/skirmish.scenario.ron", &scenario, 7);
+        save.queue.push(9);
+        assert!(matches!(
+            SaveGame::parse(&save.to_ron().unwrap()),
+            Err(SaveError::Invalid(_))
+        ));
+        assert!(matches!(
+            SaveGame::parse("(scenario: \"skirmish\")"),
+            Err(SaveError::Read(_))
+        ));
+
+        // Restoring checks too, and spawns nothing for a save that does not hold together.
+        let mut save = SaveGame::from_scenario("scenarios/skirmish.scenario.ron", &scenario, 7);
+        save.units[1].faction = "Nobody".to_string();
+        let mut world = World::new();
+        assert!(matches!(
+            save.restore(&mut world.commands()),
+            Err(SaveError::Invalid(_))
+        ));
+        world.flush();
+        assert_eq!(world.entities().len(), 0);
+    }
+}
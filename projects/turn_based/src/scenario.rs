@@ -0,0 +1,444 @@
+// Maps and the armies on them, loaded from `assets/scenarios/*.scenario.ron`.
+use std::fmt;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::ability::Ability;
+use crate::ai::Tactics;
+use crate::pathfinding::{Coord, Grid};
+use crate::terrain::Terrain;
+use crate::unit::Stats;
+use crate::Unit;
+
+/// Which scenario is being played, and so which one to respawn when its file changes.
+#[derive(Resource)]
+pub struct ActiveScenario(pub Handle<Scenario>);
+
+/// The side that won the match, once it is decided.
+#[derive(Resource, Default)]
+pub struct MatchResult(pub Option<Unit>);
+
+/// Scenario played when no `--scenario <name>` is given.
+pub const DEFAULT_SCENARIO: &str = "skirmish";
+
+/// Scenarios offered in the main menu, by file name.
+pub const SCENARIOS: [&str; 2] = ["skirmish", "crossing"];
+
+/// Asset path of the scenario called `name`.
+pub fn scenario_path(name: &str) -> String {
+    format!("scenarios/{name}.scenario.ron")
+}
+
+/// Asset path of the scenario picked on the command line.
+pub fn scenario_path_from_args() -> String {
+    let name = std::env::args()
+        .skip_while(|arg| arg != "--scenario")
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_SCENARIO.to_string());
+    scenario_path(&name)
+}
+
+/// Everything needed to start a match.
+///
+/// ```ron
+/// (
+///     name: "Skirmish",
+///     width: 4,
+///     height: 2,
+///     terrain: ["..f.", "^..#"],
+///     factions: [(name: "Blue", side: Player, color: (0.2, 1.0, 0.2))],
+///     units: [(faction: "Blue", position: (0, 0), stats: (hp: 10, max_hp: 10, attack: 4))],
+///     victory: [EliminateAll],
+/// )
+/// ```
+#[derive(Asset, TypePath, Debug, Clone, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// One string per row, top row first, in the symbols of `Terrain::from_symbol`.
+    pub terrain: Vec<String>,
+    pub factions: Vec<Faction>,
+    /// Abilities units can be given by name.
+    #[serde(default)]
+    pub abilities: Vec<Ability>,
+    pub units: Vec<UnitSpawn>,
+    /// Tiles the player may place their units on before the battle; around their starting
+    /// tiles when not given.
+    #[serde(default)]
+    pub deployment: Vec<Coord>,
+    /// The player wins as soon as any of these holds, and loses when all their units are gone
+    /// or a VIP falls.
+    pub victory: Vec<VictoryCondition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Faction {
+    pub name: String,
+    /// Who controls the faction's units.
+    pub side: Unit,
+    /// Unit color as sRGB components from 0 to 1.
+    pub color: (f32, f32, f32),
+    /// How the AI plays the faction's units; aggressive unless given.
+    #[serde(default)]
+    pub tactics: Tactics,
+}
+
+/// Name of the faction a unit fights for.
+#[derive(Component, Debug, Clone)]
+pub struct Allegiance(pub String);
+
+/// A unit the player loses the match without.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Vip;
+
+/// A unit still standing, as victory conditions see it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Survivor {
+    pub side: Unit,
+    pub position: Coord,
+    pub vip: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UnitSpawn {
+    /// Name of one of the scenario's factions.
+    pub faction: String,
+    pub position: Coord,
+    pub stats: Stats,
+    /// Names of the scenario's abilities the unit has, in number key order.
+    #[serde(default)]
+    pub abilities: Vec<String>,
+    /// The player's side loses if this unit dies.
+    #[serde(default)]
+    pub vip: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum VictoryCondition {
+    /// No enemy unit is left standing.
+    EliminateAll,
+    /// At least one player unit is still alive after this many rounds.
+    Survive { rounds: u32 },
+    /// A player unit stands on this tile.
+    Reach { tile: Coord },
+}
+
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    /// Parsed fine but does not describe a playable map.
+    Invalid(String),
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::Io(err) => write!(f, "could not read scenario: {err}"),
+            ScenarioError::Ron(err) => write!(f, "bad scenario file: {err}"),
+            ScenarioError::Invalid(reason) => write!(f, "invalid scenario: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+impl From<std::io::Error> for ScenarioError {
+    fn from(err: std::io::Error) -> Self {
+        ScenarioError::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for ScenarioError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        ScenarioError::Ron(err)
+    }
+}
+
+impl Scenario {
+    pub fn parse(text: &str) -> Result<Scenario, ScenarioError> {
+        let scenario: Scenario = ron::from_str(text)?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    fn validate(&self) -> Result<(), ScenarioError> {
+        let invalid = |reason: String| Err(ScenarioError::Invalid(reason));
+        check_terrain(self.width, self.height, &self.terrain).map_err(ScenarioError::Invalid)?;
+
+        let grid = self.grid();
+        let mut taken = Vec::new();
+        for unit in &self.units {
+            if self.faction(&unit.faction).is_none() {
+                return invalid(format!("no faction called {:?}", unit.faction));
+            }
+            if !grid.is_passable(unit.position) {
+                return invalid(format!("a unit starts on {:?}", unit.position));
+            }
+            if taken.contains(&unit.position) {
+                return invalid(format!("two units start on {:?}", unit.position));
+            }
+            if let Some(name) = unit
+                .abilities
+                .iter()
+                .find(|name| self.ability(name).is_none())
+            {
+                return invalid(format!("no ability called {name:?}"));
+            }
+            taken.push(unit.position);
+        }
+        if let Some(tile) = self
+            .deployment
+            .iter()
+            .find(|&&tile| !grid.is_passable(tile))
+        {
+            return invalid(format!("units cannot be deployed on {tile:?}"));
+        }
+        Ok(())
+    }
+
+    pub fn faction(&self, name: &str) -> Option<&Faction> {
+        self.factions.iter().find(|faction| faction.name == name)
+    }
+
+    pub fn ability(&self, name: &str) -> Option<&Ability> {
+        self.abilities.iter().find(|ability| ability.name == name)
+    }
+
+    /// The terrain as a pathfinding grid.
+    pub fn grid(&self) -> Grid {
+        terrain_grid(self.width, self.height, &self.terrain)
+    }
+
+    /// Tiles the player may deploy on: `deployment`, or else their starting tiles and the
+    /// open tiles next to them.
+    pub fn deployment_zone(&self) -> Vec<Coord> {
+        if !self.deployment.is_empty() {
+            return self.deployment.clone();
+        }
+        let grid = self.grid();
+        let side = |spawn: &UnitSpawn| self.faction(&spawn.faction).map(|faction| faction.side);
+        let enemy_tiles: Vec<Coord> = self
+            .units
+            .iter()
+            .filter(|spawn| side(spawn) == Some(Unit::Enemy))
+            .map(|spawn| spawn.position)
+            .collect();
+        let mut zone: Vec<Coord> = self
+            .units
+            .iter()
+            .filter(|spawn| side(spawn) == Some(Unit::Player))
+            .flat_map(|spawn| {
+                std::iter::once(spawn.position).chain(grid.neighbours(spawn.position))
+            })
+            .filter(|&tile| grid.is_passable(tile) && !enemy_tiles.contains(&tile))
+            .collect();
+        zone.sort();
+        zone.dedup();
+        zone
+    }
+
+    /// The side that has won, given the units still standing and the number of rounds
+    /// completed.
+    pub fn winner(&self, survivors: &[Survivor], rounds: u32) -> Option<Unit> {
+        let alive = |side: Unit| survivors.iter().any(|unit| unit.side == side);
+        let vips = self.units.iter().filter(|spawn| spawn.vip).count();
+        let vips_alive = survivors.iter().filter(|unit| unit.vip).count();
+        if !alive(Unit::Player) || vips_alive < vips {
+            return Some(Unit::Enemy);
+        }
+        let won = self.victory.iter().any(|condition| match *condition {
+            VictoryCondition::EliminateAll => !alive(Unit::Enemy),
+            VictoryCondition::Survive { rounds: needed } => rounds >= needed,
+            VictoryCondition::Reach { tile } => survivors
+                .iter()
+                .any(|unit| unit.side == Unit::Player && unit.position == tile),
+        });
+        won.then_some(Unit::Player)
+    }
+}
+
+/// Why terrain rows, one string per row and top row first, do not make a `width` by `height`
+/// map.
+pub fn check_terrain(width: u32, height: u32, rows: &[String]) -> Result<(), String> {
+    if width == 0 || height == 0 {
+        return Err(format!("the map is {width}x{height}"));
+    }
+    if rows.len() != height as usize {
+        return Err(format!(
+            "{} terrain rows for a map {height} tall",
+            rows.len()
+        ));
+    }
+    for row in rows {
+        if row.chars().count() != width as usize {
+            return Err(format!("terrain row {row:?} is not {width} wide"));
+        }
+        if let Some(symbol) = row.chars().find(|&c| Terrain::from_symbol(c).is_none()) {
+            return Err(format!("unknown terrain {symbol:?}"));
+        }
+    }
+    Ok(())
+}
+
+/// Terrain rows as a pathfinding grid.
+pub fn terrain_grid(width: u32, height: u32, rows: &[String]) -> Grid {
+    let mut grid = Grid::new(width, height);
+    for (row, line) in rows.iter().enumerate() {
+        let y = height - 1 - row as u32;
+        for (x, symbol) in line.chars().enumerate() {
+            grid.set_terrain(
+                (x as u32, y),
+                Terrain::from_symbol(symbol).unwrap_or_default(),
+            );
+        }
+    }
+    grid
+}
+
+/// Terrain rows of `grid`, the inverse of [`terrain_grid`].
+pub fn terrain_rows(grid: &Grid) -> Vec<String> {
+    (0..grid.height)
+        .rev()
+        .map(|y| {
+            (0..grid.width)
+                .map(|x| grid.terrain((x, y)).symbol())
+                .collect()
+        })
+        .collect()
+}
+
+#[derive(Default)]
+pub struct ScenarioLoader;
+
+impl AssetLoader for ScenarioLoader {
+    type Asset = Scenario;
+    type Settings = ();
+    type Error = ScenarioError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Scenario, ScenarioError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let text = String::from_utf8(bytes)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        Scenario::parse(&text)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["scenario.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SKIRMISH: &str = include_str!("../assets/scenarios/skirmish.scenario.ron");
+    const CROSSING: &str = include_str!("../assets/scenarios/crossing.scenario.ron");
+
+    #[test]
+    fn bundled_scenarios_load() {
+        let skirmish = Scenario::parse(SKIRMISH).unwrap();
+        assert_eq!((skirmish.width, skirmish.height), (10, 10));
+        assert_eq!(skirmish.units.len(), 4);
+        assert_eq!(skirmish.grid().terrain((4, 4)), Terrain::Wall);
+        assert_eq!(skirmish.units[0].stats.speed, 5);
+        assert_eq!(skirmish.units[1].abilities[1], "Fireball");
+        assert_eq!(skirmish.ability("Fireball").unwrap().cost, 3);
+
+        let crossing = Scenario::parse(CROSSING).unwrap();
+        assert_eq!((crossing.width, crossing.height), (12, 8));
+        assert_eq!(crossing.faction("Archers").unwrap().side, Unit::Enemy);
+        assert!(crossing
+            .victory
+            .contains(&VictoryCondition::Reach { tile: (11, 4) }));
+    }
+
+    #[test]
+    fn malformed_scenarios_are_rejected() {
+        let short_row = SKIRMISH.replace("\"..f....^^.\"", "\"..f....^^\"");
+        assert!(matches!(
+            Scenario::parse(&short_row),
+            Err(ScenarioError::Invalid(_))
+        ));
+        let on_a_wall = SKIRMISH.replace("position: (1, 1)", "position: (4, 4)");
+        assert!(matches!(
+            Scenario::parse(&on_a_wall),
+            Err(ScenarioError::Invalid(_))
+        ));
+        let unknown_faction = SKIRMISH.replace("faction: \"Raiders\"", "faction: \"Pirates\"");
+        assert!(matches!(
+            Scenario::parse(&unknown_faction),
+            Err(ScenarioError::Invalid(_))
+        ));
+        let unknown_ability = SKIRMISH.replace("\"Venom\"]", "\"Meteor\"]");
+        assert!(matches!(
+            Scenario::parse(&unknown_ability),
+            Err(ScenarioError::Invalid(_))
+        ));
+        assert!(matches!(
+            Scenario::parse("(name: \"Empty\")"),
+            Err(ScenarioError::Ron(_))
+        ));
+    }
+
+    fn unit(side: Unit, position: Coord, vip: bool) -> Survivor {
+        Survivor {
+            side,
+            position,
+            vip,
+        }
+    }
+
+    #[test]
+    fn victory_conditions() {
+        let crossing = Scenario::parse(CROSSING).unwrap();
+        let scout = unit(Unit::Player, (2, 5), true);
+        let raider = unit(Unit::Enemy, (9, 4), false);
+        let both = [scout, unit(Unit::Player, (1, 3), false), raider];
+        assert_eq!(crossing.winner(&both, 0), None);
+        assert_eq!(crossing.winner(&both, 8), Some(Unit::Player));
+        assert_eq!(
+            crossing.winner(&[unit(Unit::Player, (11, 4), true), raider], 0),
+            Some(Unit::Player)
+        );
+        assert_eq!(crossing.winner(&[scout], 0), Some(Unit::Player));
+        assert_eq!(crossing.winner(&[raider], 0), Some(Unit::Enemy));
+        // Losing the scout loses the match, even on the last round.
+        assert_eq!(
+            crossing.winner(&[unit(Unit::Player, (1, 3), false), raider], 8),
+            Some(Unit::Enemy)
+        );
+
+        let skirmish = Scenario::parse(SKIRMISH).unwrap();
+        let both = [unit(Unit::Player, (1, 3), false), raider];
+        assert_eq!(skirmish.winner(&both, 100), None);
+    }
+
+    #[test]
+    fn deployment_zone_surrounds_the_starting_tiles() {
+        let skirmish = Scenario::parse(SKIRMISH).unwrap();
+        let zone = skirmish.deployment_zone();
+        assert_eq!(zone.len(), 8);
+        assert!(zone.contains(&(1, 1)) && zone.contains(&(2, 3)));
+        assert!(!zone.contains(&(3, 3)));
+
+        let fixed = SKIRMISH.replace("victory:", "deployment: [(0, 0), (0, 1)],\n    victory:");
+        let fixed = Scenario::parse(&fixed).unwrap();
+        assert_eq!(fixed.deployment_zone(), vec![(0, 0), (0, 1)]);
+        let walled = SKIRMISH.replace("victory:", "deployment: [(4, 4)],\n    victory:");
+        assert!(matches!(
+            Scenario::parse(&walled),
+            Err(ScenarioError::Invalid(_))
+        ));
+    }
+}
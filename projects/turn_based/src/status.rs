@@ -0,0 +1,164 @@
+// Lasting effects on a unit: poison, lost turns, slowed movement and shields. Slow and shield
+// change `Stats` while they last and give the change back when they run out.
+use std::mem::discriminant;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::unit::Stats;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatusKind {
+    /// Loses this much hp at the start of each of its turns.
+    Poison(i32),
+    /// Loses its turns.
+    Stun,
+    /// Moves this many tiles less.
+    Slow(u32),
+    /// Has this much more defense.
+    Shield(i32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Status {
+    pub kind: StatusKind,
+    /// The unit's own turns it still lasts for, counting the current one.
+    pub turns: u32,
+    /// How much it changed `Stats` by, to undo exactly that when it runs out.
+    applied: i32,
+}
+
+/// Everything currently affecting a unit.
+#[derive(Component, Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Statuses(pub Vec<Status>);
+
+impl Statuses {
+    /// Put `kind` on the unit for `turns` of its turns. A status of the same kind is replaced
+    /// rather than stacked.
+    pub fn apply(&mut self, stats: &mut Stats, kind: StatusKind, turns: u32) {
+        if turns == 0 {
+            return;
+        }
+        if let Some(index) = self
+            .0
+            .iter()
+            .position(|status| discriminant(&status.kind) == discriminant(&kind))
+        {
+            let old = self.0.remove(index);
+            lift(&old, stats);
+        }
+        let applied = match kind {
+            StatusKind::Slow(by) => {
+                let by = by.min(stats.movement);
+                stats.movement -= by;
+                by as i32
+            }
+            StatusKind::Shield(amount) => {
+                stats.defense += amount;
+                amount
+            }
+            StatusKind::Poison(_) | StatusKind::Stun => 0,
+        };
+        self.0.push(Status {
+            kind,
+            turns,
+            applied,
+        });
+    }
+
+    /// The unit's turn begins: poison bites. Returns whether the unit is stunned and loses
+    /// the turn.
+    pub fn start_turn(&self, stats: &mut Stats) -> bool {
+        let mut stunned = false;
+        for status in &self.0 {
+            match status.kind {
+                StatusKind::Poison(damage) => stats.hp = (stats.hp - damage).max(0),
+                StatusKind::Stun => stunned = true,
+                StatusKind::Slow(_) | StatusKind::Shield(_) => {}
+            }
+        }
+        stunned
+    }
+
+    /// The unit's turn is over: count down, and lift whatever has run out.
+    pub fn end_turn(&mut self, stats: &mut Stats) {
+        for status in &mut self.0 {
+            status.turns = status.turns.saturating_sub(1);
+        }
+        for status in self.0.iter().filter(|status| status.turns == 0) {
+            lift(status, stats);
+        }
+        self.0.retain(|status| status.turns > 0);
+    }
+}
+
+/// Undo what `status` did to `stats`.
+fn lift(status: &Status, stats: &mut Stats) {
+    match status.kind {
+        StatusKind::Slow(_) => stats.movement += status.applied as u32,
+        StatusKind::Shield(_) => stats.defense -= status.applied,
+        StatusKind::Poison(_) | StatusKind::Stun => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> Stats {
+        Stats {
+            hp: 10,
+            max_hp: 10,
+            defense: 1,
+            movement: 3,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn slow_and_shield_change_stats_while_they_last() {
+        let mut stats = stats();
+        let mut statuses = Statuses::default();
+        statuses.apply(&mut stats, StatusKind::Slow(5), 2);
+        statuses.apply(&mut stats, StatusKind::Shield(2), 1);
+        assert_eq!((stats.movement, stats.defense), (0, 3));
+
+        statuses.end_turn(&mut stats);
+        assert_eq!((stats.movement, stats.defense), (0, 1));
+        statuses.end_turn(&mut stats);
+        assert_eq!((stats.movement, stats.defense), (3, 1));
+        assert!(statuses.0.is_empty());
+    }
+
+    #[test]
+    fn reapplying_refreshes_instead_of_stacking() {
+        let mut stats = stats();
+        let mut statuses = Statuses::default();
+        statuses.apply(&mut stats, StatusKind::Shield(2), 1);
+        statuses.apply(&mut stats, StatusKind::Shield(3), 2);
+        assert_eq!(stats.defense, 4);
+        assert_eq!(statuses.0.len(), 1);
+        statuses.end_turn(&mut stats);
+        statuses.end_turn(&mut stats);
+        assert_eq!(stats.defense, 1);
+    }
+
+    #[test]
+    fn poison_bites_and_stun_skips_turns() {
+        let mut stats = stats();
+        let mut statuses = Statuses::default();
+        statuses.apply(&mut stats, StatusKind::Poison(4), 3);
+        statuses.apply(&mut stats, StatusKind::Stun, 1);
+        assert!(statuses.start_turn(&mut stats));
+        assert_eq!(stats.hp, 6);
+        statuses.end_turn(&mut stats);
+
+        assert!(!statuses.start_turn(&mut stats));
+        assert_eq!(stats.hp, 2);
+        statuses.end_turn(&mut stats);
+        statuses.start_turn(&mut stats);
+        assert_eq!(stats.hp, 0);
+        statuses.end_turn(&mut stats);
+        assert!(statuses.0.is_empty());
+    }
+}
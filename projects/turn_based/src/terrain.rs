@@ -0,0 +1,64 @@
+// What a tile is made of, and what that means for moving onto it and fighting from it.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Terrain {
+    #[default]
+    Plains,
+    Forest,
+    Water,
+    Mountain,
+    Wall,
+}
+
+impl Terrain {
+    /// Movement points it takes to step onto this terrain, or `None` when nothing can.
+    pub fn move_cost(self) -> Option<u32> {
+        match self {
+            Terrain::Plains => Some(1),
+            Terrain::Forest => Some(2),
+            Terrain::Mountain => Some(3),
+            Terrain::Water | Terrain::Wall => None,
+        }
+    }
+
+    pub fn is_passable(self) -> bool {
+        self.move_cost().is_some()
+    }
+
+    /// Added to the defense of a unit standing here.
+    pub fn defense_bonus(self) -> i32 {
+        match self {
+            Terrain::Forest => 1,
+            Terrain::Mountain => 2,
+            Terrain::Plains | Terrain::Water | Terrain::Wall => 0,
+        }
+    }
+
+    /// Whether this terrain hides what lies behind it.
+    pub fn blocks_sight(self) -> bool {
+        matches!(self, Terrain::Mountain | Terrain::Wall)
+    }
+
+    /// Map symbol: `.` plains, `f` forest, `~` water, `^` mountain, `#` wall.
+    pub fn from_symbol(symbol: char) -> Option<Terrain> {
+        match symbol {
+            '.' => Some(Terrain::Plains),
+            'f' => Some(Terrain::Forest),
+            '~' => Some(Terrain::Water),
+            '^' => Some(Terrain::Mountain),
+            '#' => Some(Terrain::Wall),
+            _ => None,
+        }
+    }
+
+    /// The map symbol `from_symbol` reads back as this terrain.
+    pub fn symbol(self) -> char {
+        match self {
+            Terrain::Plains => '.',
+            Terrain::Forest => 'f',
+            Terrain::Water => '~',
+            Terrain::Mountain => '^',
+            Terrain::Wall => '#',
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+
+use crate::fog::FogOfWar;
+use crate::logic::{CurrentTurn, TurnQueue};
+use crate::{TilePos, Unit};
+
+/// Size of a portrait in the turn order bar; the acting unit's is drawn larger.
+const PORTRAIT_SIZE: f32 = 36.0;
+const CURRENT_PORTRAIT_SIZE: f32 = 48.0;
+
+/// Portrait color of an enemy out of the player's sight.
+const UNSEEN_COLOR: Color = Color::srgb(0.35, 0.35, 0.35);
+
+/// Row of portraits along the top of the screen showing who acts next.
+#[derive(Component)]
+pub struct TurnOrderBar;
+
+pub fn spawn_turn_order_bar(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Px(5.0),
+            column_gap: Val::Px(6.0),
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        TurnOrderBar,
+    ));
+}
+
+/// Rebuild the portraits whenever the queue turns over, a unit dies or comes into or out of
+/// sight.
+pub fn update_turn_order_bar(
+    mut commands: Commands,
+    queue: Res<TurnQueue>,
+    current: Res<CurrentTurn>,
+    fog: Res<FogOfWar>,
+    bars: Query<Entity, With<TurnOrderBar>>,
+    units: Query<(&Sprite, &Unit, &TilePos)>,
+) {
+    if !queue.is_changed() && !current.is_changed() && !fog.is_changed() {
+        return;
+    }
+    let Ok(bar) = bars.get_single() else { return };
+
+    commands.entity(bar).despawn_descendants();
+    commands.entity(bar).with_children(|bar| {
+        for &entity in &queue.0 {
+            let Ok((sprite, unit, pos)) = units.get(entity) else {
+                continue;
+            };
+            let seen = *unit == Unit::Player || fog.player.is_visible((pos.x, pos.y));
+            let acting = current.0 == Some(entity);
+            let size = if acting {
+                CURRENT_PORTRAIT_SIZE
+            } else {
+                PORTRAIT_SIZE
+            };
+            bar.spawn((
+                Node {
+                    width: Val::Px(size),
+                    height: Val::Px(size),
+                    border: UiRect::all(Val::Px(3.0)),
+                    ..default()
+                },
+                // The acting unit's sprite is dimmed while selected; portraits stay opaque.
+                BackgroundColor(if seen {
+                    sprite.color.with_alpha(1.0)
+                } else {
+                    UNSEEN_COLOR
+                }),
+                BorderColor(if acting { Color::WHITE } else { Color::BLACK }),
+            ));
+        }
+    });
+}
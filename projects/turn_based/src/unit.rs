@@ -1,7 +1,16 @@
-use crate::{TilePos, Unit, TILE_SIZE};
+use crate::combat::TurnActions;
+use crate::pathfinding::{Coord, Grid};
+use crate::status::Statuses;
+use crate::{tile_to_world, Map, TilePos, Unit, TILE_SIZE};
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 
-#[derive(Component, Debug, Clone, Default)]
+/// Seconds a unit takes to walk from one tile to the next.
+const STEP_SECONDS: f32 = 0.15;
+
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Stats {
     pub hp: i32,
     pub max_hp: i32,
@@ -11,31 +20,79 @@ pub struct Stats {
     pub range: u32, // attack range in tiles
     pub speed: u32,
     pub max_move: u32,
+    /// How many tiles away the unit can see.
+    pub sight: u32,
+    /// Spent on abilities; some comes back every turn.
+    pub energy: i32,
+    pub max_energy: i32,
 }
 
 pub fn spawn_unit(
     commands: &mut Commands,
+    grid: &Grid,
     x: u32,
     y: u32,
     kind: Unit,
     color: Color,
-    offset_x: f32,
-    offset_y: f32,
     stats: Stats,
+) -> Entity {
+    commands
+        .spawn((
+            Sprite {
+                color,
+                custom_size: Some(Vec2::splat(TILE_SIZE * 0.6)),
+                ..default()
+            },
+            Transform::from_translation(tile_to_world(grid, (x, y), 1.0)),
+            kind,
+            TilePos { x, y },
+            stats, // add this
+            TurnActions::default(),
+            Statuses::default(),
+        ))
+        .id()
+}
+
+/// A unit walking a path one tile at a time. Its `TilePos` already holds the destination;
+/// this only moves the sprite.
+#[derive(Component)]
+pub struct Walking {
+    steps: VecDeque<Coord>,
+    from: Option<Vec3>,
+    timer: Timer,
+}
+
+impl Walking {
+    /// Walk `path`, which starts on the tile the unit stands on.
+    pub fn along(path: &[Coord]) -> Self {
+        Walking {
+            steps: path.iter().skip(1).copied().collect(),
+            from: None,
+            timer: Timer::from_seconds(STEP_SECONDS, TimerMode::Repeating),
+        }
+    }
+}
+
+pub fn walk_units(
+    time: Res<Time>,
+    map: Res<Map>,
+    mut commands: Commands,
+    mut walkers: Query<(Entity, &mut Transform, &mut Walking)>,
 ) {
-    commands.spawn((
-        Sprite {
-            color,
-            custom_size: Some(Vec2::splat(TILE_SIZE * 0.6)),
-            ..default()
-        },
-        Transform::from_xyz(
-            x as f32 * TILE_SIZE + offset_x,
-            y as f32 * TILE_SIZE + offset_y,
-            1.0,
-        ),
-        kind,
-        TilePos { x, y },
-        stats, // add this
-    ));
+    for (entity, mut transform, mut walking) in walkers.iter_mut() {
+        let Some(&next) = walking.steps.front() else {
+            commands.entity(entity).remove::<Walking>();
+            continue;
+        };
+        let from = *walking.from.get_or_insert(transform.translation);
+        let to = tile_to_world(&map.0, next, transform.translation.z);
+        walking.timer.tick(time.delta());
+        if walking.timer.just_finished() {
+            transform.translation = to;
+            walking.from = Some(to);
+            walking.steps.pop_front();
+        } else {
+            transform.translation = from.lerp(to, walking.timer.fraction());
+        }
+    }
 }